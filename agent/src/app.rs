@@ -12,7 +12,7 @@ use tracing::info;
 use crate::{
     brokers::state_broker::StateBroker,
     config::Config,
-    models::error::Error,
+    models::{artifact::ArtifactStore, error::Error},
     proto::action_service_server::ActionServiceServer,
     server::ActionsLauncher,
     services::{
@@ -37,13 +37,16 @@ impl App {
         docker.ping().await.map_err(Error::DockerConnectionError)?;
 
         let state_broker = Arc::new(StateBroker::new());
-        let action_service = ActionService::new(docker, state_broker.clone());
+        let artifact_store = ArtifactStore::new(config.artifacts_dir.clone());
+        let action_service = ActionService::new(docker, state_broker.clone(), artifact_store);
         let actions = ActionsLauncher { action_service };
         let action_service_grpc = ActionServiceServer::new(actions);
         let mut scheduler_service = SchedulerService::init(
             config.shost.clone(),
             config.ahost.clone(),
             config.port.clone(),
+            config.labels.clone(),
+            config.max_concurrency,
             health_service,
         )
         .await?;
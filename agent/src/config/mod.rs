@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 #[derive(Parser, Clone)]
@@ -14,4 +16,16 @@ pub struct Config {
     /// The port of the agent to listen on
     #[clap(long, default_value = "9001")]
     pub port: u32,
+
+    /// The labels advertised to the scheduler, comma separated (e.g. "arch:arm64,privileged")
+    #[clap(long, value_delimiter = ',')]
+    pub labels: Vec<String>,
+
+    /// The maximum number of actions the scheduler may run on the agent at once, 0 for no limit
+    #[clap(long, default_value = "4")]
+    pub max_concurrency: u32,
+
+    /// The directory where the artifacts of the actions are kept until the scheduler downloads them
+    #[clap(long, default_value = "/tmp/sealci/artifacts")]
+    pub artifacts_dir: PathBuf,
 }
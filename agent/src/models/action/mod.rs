@@ -1,19 +1,39 @@
-use super::{container::ContainerOperations, error::Error::ExecError, step::Step};
 use super::{
-    error::Error::{self, StepOutputError},
-    output_pipe::OutputPipe,
+    artifact::{Artifact, ArtifactStore},
+    container::ContainerOperations,
+    error::Error::{
+        ArtifactDownloadError, ArtifactError, ContainerArchiveError, ContainerExecError, ExecError,
+    },
+    step::Step,
+};
+use super::{
+    error::Error::{self, CommandExitError, StepOutputError},
+    output_pipe::{LineBuffer, OutputPipe},
 };
 use crate::brokers::state_broker::{StateBroker, StateEvent};
 use crate::brokers::Broker;
-use crate::{models::output_pipe::Pipe, proto::ActionResponseStream};
+use crate::{
+    models::output_pipe::Pipe,
+    proto::{ActionResponseStream, ActionStatus, ArtifactReference, LogStream},
+};
+use bollard::container::LogOutput;
 use state::State;
-use std::sync::Arc;
-use tokio::{sync::mpsc::UnboundedSender, task};
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{fs, io::AsyncWriteExt, sync::mpsc::UnboundedSender, task};
 use tokio_stream::StreamExt;
 use tonic::Status;
 use tracing::{debug, error};
 pub mod state;
 
+/// Print the paths matching the glob patterns given as arguments. A pattern is only expanded as a glob: it is neither
+/// split on whitespace nor evaluated.
+const MATCH_ARTIFACTS_SCRIPT: &str = r#"IFS=; for pattern in "$@"; do for path in $pattern; do if [ -e "$path" ]; then echo "$path"; fi; done; done"#;
+
 #[derive(Clone)]
 pub struct Action<T: ContainerOperations> {
     pub id: u32,
@@ -23,6 +43,11 @@ pub struct Action<T: ContainerOperations> {
     pub repository_url: String,
     pub state: State,
     pub state_broker: Arc<StateBroker>,
+    /// Shell glob patterns, from the repository, of the files kept once the steps succeeded
+    artifacts: Vec<String>,
+    artifact_store: Option<ArtifactStore>,
+    /// Artifacts of other actions, unpacked in the repository before the steps
+    input_artifacts: Vec<ArtifactReference>,
 }
 
 impl<T: ContainerOperations> Action<T> {
@@ -32,9 +57,10 @@ impl<T: ContainerOperations> Action<T> {
         commands: Vec<String>,
         stdout: UnboundedSender<Result<ActionResponseStream, Status>>,
         repository_url: String,
+        secrets: Vec<String>,
         state_broker: Arc<StateBroker>,
     ) -> Self {
-        let pipe = Arc::new(OutputPipe::new(id, stdout));
+        let pipe = Arc::new(OutputPipe::new(id, secrets, stdout));
         let container = Arc::new(container);
         let steps: Vec<Step<T>> = commands
             .iter()
@@ -49,35 +75,66 @@ impl<T: ContainerOperations> Action<T> {
             pipe,
             state,
             state_broker,
+            artifacts: Vec::new(),
+            artifact_store: None,
+            input_artifacts: Vec::new(),
         }
     }
 
+    /// Keep the files matching the patterns in the store once the steps succeeded.
+    pub fn with_artifacts(mut self, artifacts: Vec<String>, artifact_store: ArtifactStore) -> Self {
+        self.artifacts = artifacts;
+        self.artifact_store = Some(artifact_store);
+        self
+    }
+
+    /// Unpack the artifacts of other actions in the repository before the steps.
+    pub fn with_input_artifacts(mut self, input_artifacts: Vec<ArtifactReference>) -> Self {
+        self.input_artifacts = input_artifacts;
+        self
+    }
+
     pub async fn execute(&mut self) -> Result<(), Error> {
-        for step in &self.steps {
+        for (index, step) in self.steps.iter().enumerate() {
+            let index = index as u32;
             // Execute the step in the folder where we cloned the repository
             // When cloning we use the action id as a name for the folder
             let mut exec_result = step.execute().await?;
             let command = step.command.clone();
             debug!("Executing command {} for action {}", command, self.id);
-            self.pipe.clone().output_log(command, 2, None);
+            self.pipe.output_step_log(command, index, LogStream::System);
             let pipe = self.pipe.clone();
-            let id = self.id.clone();
-            task::spawn(async move {
+            let id = self.id;
+            let output = task::spawn(async move {
+                let mut stdout = LineBuffer::default();
+                let mut stderr = LineBuffer::default();
                 while let Some(log) = exec_result.output.next().await {
                     match log {
                         Ok(log_output) => {
                             debug!("Command output: {} ", log_output);
-                            pipe.output_log(log_output.to_string(), 2, None);
+                            let (buffer, stream) = match log_output {
+                                LogOutput::StdErr { .. } => (&mut stderr, LogStream::Stderr),
+                                _ => (&mut stdout, LogStream::Stdout),
+                            };
+                            for line in buffer.push(&log_output.to_string()) {
+                                pipe.output_step_log(line, index, stream);
+                            }
                         }
                         Err(e) => {
                             error!("Action {} failed: {}", id, e);
-                            return Err(Status::aborted(format!("Execution error: {}", e)));
+                            break;
                         }
                     }
                 }
-                Ok(())
+                for (buffer, stream) in [(stdout, LogStream::Stdout), (stderr, LogStream::Stderr)] {
+                    if let Some(line) = buffer.flush() {
+                        pipe.output_step_log(line, index, stream);
+                    }
+                }
             });
             let exit_status = exec_result.exec_handle.await;
+            // The output of the step is sent before the status of the action
+            let _ = output.await;
             if let Ok(exit_code) = exit_status {
                 if exit_code != 0 {
                     self.cleanup().await?;
@@ -88,23 +145,277 @@ impl<T: ContainerOperations> Action<T> {
                 }
             }
         }
+        self.collect_artifacts().await;
         self.cleanup().await?;
         self.set_state(State::Completed);
+        self.pipe.output_log(
+            "Action completed".to_string(),
+            ActionStatus::Completed.into(),
+            Some(0),
+        );
+        Ok(())
+    }
+
+    /// Start the container then run the steps, the whole of it being what a timeout or a cancellation interrupts.
+    pub async fn run(&mut self, client: &reqwest::Client) -> Result<(), Error> {
+        self.prepare(client).await?;
+        self.execute().await
+    }
+
+    /// Start the container, clone the repository and fetch the input artifacts. The action cannot run without them,
+    /// so on failure its container is removed and the action is reported as failed.
+    pub async fn prepare(&mut self, client: &reqwest::Client) -> Result<(), Error> {
+        let prepared = async {
+            self.container.start().await?;
+            self.setup_repository().await?;
+            self.fetch_artifacts(client).await
+        }
+        .await;
+        if let Err(e) = prepared {
+            error!("Failed to prepare action {}: {:?}", self.id, e);
+            if let Err(cleanup_error) = self.cleanup().await {
+                error!(
+                    "Failed to remove the container of action {}: {:?}",
+                    self.id, cleanup_error
+                );
+            }
+            self.set_state(State::Failed);
+            self.pipe.output_log(
+                "Action failed".to_string(),
+                ActionStatus::Error.into(),
+                None,
+            );
+            return Err(e);
+        }
         Ok(())
     }
 
     pub async fn setup_repository(&self) -> Result<(), Error> {
         // Cloning the repository in a folder that takes as name the id of the action
-        let setup_command = format!("git clone --depth 1 {} {}", self.repository_url, self.id);
-        let exec_result = self.container.exec(setup_command, None).await?;
-        exec_result.exec_handle.await.map_err(ExecError)?;
+        // The URL comes from the request, so it is passed as an argument rather than through the shell of the action
+        let clone_command = vec![
+            "git".to_string(),
+            "clone".to_string(),
+            "--depth".to_string(),
+            "1".to_string(),
+            "--".to_string(),
+            self.repository_url.clone(),
+            self.id.to_string(),
+        ];
+        if let Err(e) = self.run_command(clone_command, None).await {
+            error!(
+                "Failed to clone {} for action {}",
+                self.repository_url, self.id
+            );
+            self.output_system_log("Failed to clone the repository".to_string());
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Run a command of the agent itself, e.g. the clone. Unlike the steps of the action, such a command fails the
+    /// action as the infrastructure would, so its exit code is not reported as the one of the action.
+    async fn run_command(&self, args: Vec<String>, workdir: Option<String>) -> Result<(), Error> {
+        let exec_result = self.container.exec_args(args, workdir).await?;
+        match exec_result.exec_handle.await.map_err(ExecError)? {
+            0 => Ok(()),
+            exit_code => Err(CommandExitError(exit_code)),
+        }
+    }
+
+    /// Download the artifacts of other actions and unpack them where they were in their repository. The action cannot
+    /// run without them, so the first one that cannot be unpacked fails it.
+    pub async fn fetch_artifacts(&self, client: &reqwest::Client) -> Result<(), Error> {
+        for artifact in &self.input_artifacts {
+            if let Err(e) = self.fetch_artifact(client, artifact).await {
+                error!(
+                    "Failed to fetch {} for action {}: {:?}",
+                    artifact.url, self.id, e
+                );
+                self.output_system_log(format!("Failed to fetch artifact {}", artifact.path));
+                return Err(e);
+            }
+            self.output_system_log(format!("Artifact {} fetched", artifact.path));
+        }
         Ok(())
     }
 
+    async fn fetch_artifact(
+        &self,
+        client: &reqwest::Client,
+        artifact: &ArtifactReference,
+    ) -> Result<(), Error> {
+        // The archive holds the file or directory under its name, so it is unpacked in its parent directory
+        let directory = match artifact.path.rsplit_once('/') {
+            Some((parent, _)) => format!("/{}/{}", self.id, parent),
+            None => format!("/{}", self.id),
+        };
+        let mkdir_command = vec![
+            "mkdir".to_string(),
+            "-p".to_string(),
+            "--".to_string(),
+            directory.clone(),
+        ];
+        self.run_command(mkdir_command, None).await?;
+
+        let response = client
+            .get(&artifact.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(ArtifactDownloadError)?;
+        // The archive is unpacked as it is downloaded, a download error ends it early and is told afterwards
+        let failure = Arc::new(Mutex::new(None));
+        let chunks = futures_util::StreamExt::scan(
+            response.bytes_stream(),
+            Arc::clone(&failure),
+            |failure, chunk| {
+                futures_util::future::ready(match chunk {
+                    Ok(chunk) => Some(chunk.to_vec()),
+                    Err(e) => {
+                        *failure.lock().unwrap() = Some(e);
+                        None
+                    }
+                })
+            },
+        );
+        let extracted = self.container.extract(directory, Box::pin(chunks)).await;
+        if let Some(e) = failure.lock().unwrap().take() {
+            return Err(ArtifactDownloadError(e));
+        }
+        extracted
+    }
+
+    /// Archive the files matching the artifact patterns before the container is removed. An artifact that cannot be
+    /// kept is reported in the logs, and the action still completes.
+    async fn collect_artifacts(&self) {
+        let Some(store) = &self.artifact_store else {
+            return;
+        };
+        if self.artifacts.is_empty() {
+            return;
+        }
+        let paths = match self.match_artifacts().await {
+            Ok(paths) => paths,
+            Err(e) => {
+                error!(
+                    "Failed to match the artifacts of action {}: {:?}",
+                    self.id, e
+                );
+                self.output_system_log("Failed to find the artifacts".to_string());
+                return;
+            }
+        };
+        if paths.is_empty() {
+            self.output_system_log("No file matches the artifacts".to_string());
+        }
+        for (index, path) in paths.into_iter().enumerate() {
+            let file = store.file(self.id, index);
+            match self.archive(&path, &file).await {
+                Ok(()) => {
+                    self.output_system_log(format!("Artifact {} archived", path));
+                    store.add(self.id, Artifact { path, file });
+                }
+                Err(e) => {
+                    error!("Failed to archive {} of action {}: {:?}", path, self.id, e);
+                    self.output_system_log(format!("Failed to archive artifact {}", path));
+                    let _ = fs::remove_file(&file).await;
+                }
+            }
+        }
+    }
+
+    /// The paths, from the repository, of the files and directories matching the artifact patterns.
+    async fn match_artifacts(&self) -> Result<Vec<String>, Error> {
+        // The patterns come from the manifest, so they are passed as arguments rather than pasted into the script
+        let mut command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            MATCH_ARTIFACTS_SCRIPT.to_string(),
+            "sh".to_string(),
+        ];
+        command.extend(self.artifacts.iter().cloned());
+        let mut exec_result = self
+            .container
+            .exec_args(command, Some(format!("/{}", self.id)))
+            .await?;
+        let mut stdout = LineBuffer::default();
+        let mut paths = Vec::new();
+        while let Some(log) = exec_result.output.next().await {
+            if let LogOutput::StdOut { message } = log.map_err(ContainerExecError)? {
+                paths.extend(stdout.push(&String::from_utf8_lossy(&message)));
+            }
+        }
+        paths.extend(stdout.flush());
+        match exec_result.exec_handle.await.map_err(ExecError)? {
+            0 => {}
+            exit_code => return Err(CommandExitError(exit_code)),
+        }
+        // Overlapping patterns match the same path more than once
+        let mut matched = HashSet::new();
+        paths.retain(|path| matched.insert(path.clone()));
+        Ok(paths)
+    }
+
+    /// Write the tar archive of a path of the repository to a file.
+    async fn archive(&self, path: &str, file: &Path) -> Result<(), Error> {
+        if let Some(directory) = file.parent() {
+            fs::create_dir_all(directory).await.map_err(ArtifactError)?;
+        }
+        let mut archive = self
+            .container
+            .archive(format!("/{}/{}", self.id, path))
+            .await?;
+        let mut output = fs::File::create(file).await.map_err(ArtifactError)?;
+        while let Some(chunk) = archive.next().await {
+            let chunk = chunk.map_err(ContainerArchiveError)?;
+            output.write_all(&chunk).await.map_err(ArtifactError)?;
+        }
+        output.flush().await.map_err(ArtifactError)
+    }
+
+    fn output_system_log(&self, log: String) {
+        self.pipe
+            .output_log(log, ActionStatus::Running.into(), None);
+    }
+
     pub async fn cleanup(&self) -> Result<(), Error> {
         self.container.remove().await
     }
 
+    /// Stop the action: removing the container kills the running command.
+    pub async fn cancel(&mut self) -> Result<(), Error> {
+        self.abort(
+            State::Cancelled,
+            "Action cancelled".to_string(),
+            ActionStatus::Cancelled,
+        )
+        .await
+    }
+
+    /// Stop the action once it ran for longer than its timeout.
+    pub async fn time_out(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.abort(
+            State::TimedOut,
+            format!("Action timed out after {}s", timeout.as_secs()),
+            ActionStatus::TimedOut,
+        )
+        .await
+    }
+
+    /// The status is reported even when the container cannot be removed, which happens when it was not started yet.
+    async fn abort(
+        &mut self,
+        state: State,
+        log: String,
+        status: ActionStatus,
+    ) -> Result<(), Error> {
+        let removed = self.cleanup().await;
+        self.set_state(state);
+        self.pipe.output_log(log, status.into(), None);
+        removed
+    }
+
     fn set_state(&mut self, state: State) {
         self.state = state.clone();
         let _ = self.state_broker.state_channel.send_event(StateEvent {
@@ -118,7 +429,7 @@ impl<T: ContainerOperations> Action<T> {
 mod tests {
     use super::*;
     use crate::models::container::mock::MockContainer;
-    use std::sync::{Arc, Mutex};
+    use std::sync::Arc;
     use tokio::sync::mpsc;
 
     #[tokio::test]
@@ -126,10 +437,7 @@ mod tests {
         let state_broker = Arc::new(StateBroker::new());
         // Arrange
         let (tx, _rx) = mpsc::unbounded_channel();
-        let mock_container = MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: false,
-        };
+        let mock_container = MockContainer::new(false);
 
         let action_id = 42;
         let repo_url = "https://github.com/user/repo.git".to_string();
@@ -139,6 +447,7 @@ mod tests {
             vec!["echo 'test'".to_string()],
             tx,
             repo_url.clone(),
+            Vec::new(),
             state_broker,
         );
 
@@ -149,13 +458,115 @@ mod tests {
         assert!(result.is_ok());
 
         let container = Arc::new(&action.container);
-        let calls = container.exec_calls.lock().unwrap();
+        let calls = container.exec_args_calls.lock().unwrap();
         assert_eq!(calls.len(), 1);
 
         // Verify the git clone command has correct format
-        let expected_cmd = format!("git clone --depth 1 {} {}", repo_url, action_id);
-        assert_eq!(calls[0].0, expected_cmd);
+        let expected_args = vec![
+            "git",
+            "clone",
+            "--depth",
+            "1",
+            "--",
+            repo_url.as_str(),
+            "42",
+        ];
+        assert_eq!(calls[0].0, expected_args);
         assert_eq!(calls[0].1, None); // No working directory for clone
+
+        // Nothing went through the shell of the action
+        assert!(container.exec_calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_action_setup_repository_passes_url_as_single_argument() {
+        // Arrange
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+
+        let repo_url = "https://example.com/repo.git; rm -rf / & $(id) x".to_string();
+        let action = Action::new(
+            1,
+            mock_container,
+            vec![],
+            tx,
+            repo_url.clone(),
+            vec![],
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.setup_repository().await;
+
+        // Assert
+        assert!(result.is_ok());
+
+        let calls = action.container.exec_args_calls.lock().unwrap();
+        assert_eq!(calls[0].0.len(), 7);
+        assert_eq!(calls[0].0[5], repo_url);
+        assert_eq!(calls[0].0[6], "1");
+    }
+
+    #[tokio::test]
+    async fn test_action_setup_repository_fails_on_clone_exit_code() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut mock_container = MockContainer::new(false);
+        mock_container.exit_code = 128;
+
+        let action = Action::new(
+            1,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/missing.git".to_string(),
+            vec![],
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.setup_repository().await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::CommandExitError(128))));
+
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Failed to clone the repository");
+    }
+
+    #[tokio::test]
+    async fn test_action_prepare_reports_failure() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(true);
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec!["echo 'never run'".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            vec![],
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.run(&reqwest::Client::new()).await;
+
+        // Assert
+        assert!(result.is_err());
+        assert!(matches!(action.state, State::Failed));
+
+        // Only the clone was attempted
+        assert_eq!(action.container.exec_args_calls.lock().unwrap().len(), 1);
+        assert!(action.container.exec_calls.lock().unwrap().is_empty());
+
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Failed to clone the repository");
+        let message = rx.recv().await.unwrap().unwrap();
+        let result = message.result.unwrap();
+        assert_eq!(result.completion(), ActionStatus::Error);
+        assert_eq!(result.exit_code, None);
     }
 
     #[tokio::test]
@@ -164,10 +575,7 @@ mod tests {
 
         // Arrange
         let (tx, _rx) = mpsc::unbounded_channel();
-        let mock_container = MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: false,
-        };
+        let mock_container = MockContainer::new(false);
 
         let commands = vec![
             "echo 'step 1'".to_string(),
@@ -181,6 +589,7 @@ mod tests {
             commands.clone(),
             tx,
             "https://example.com/repo.git".to_string(),
+            Vec::new(),
             state_broker,
         );
 
@@ -203,14 +612,305 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_action_execute_reports_completed_status() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.execute().await;
+
+        // Assert
+        assert!(result.is_ok());
+
+        let message = rx.recv().await.unwrap().unwrap();
+        let result = message.result.unwrap();
+        assert_eq!(result.completion(), ActionStatus::Completed);
+        assert_eq!(result.exit_code, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_action_execute_reports_each_command_with_its_step() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec!["cargo build".to_string(), "cargo test".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.execute().await;
+
+        // Assert
+        assert!(result.is_ok());
+
+        let build = rx.recv().await.unwrap().unwrap();
+        let test = rx.recv().await.unwrap().unwrap();
+        let completed = rx.recv().await.unwrap().unwrap();
+        assert_eq!((build.log.as_str(), build.step), ("cargo build", Some(0)));
+        assert_eq!((test.log.as_str(), test.step), ("cargo test", Some(1)));
+        assert_eq!(test.stream(), LogStream::System);
+        assert_eq!(completed.step, None);
+        assert_eq!(
+            (build.sequence, test.sequence, completed.sequence),
+            (1, 2, 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_action_execute_matches_artifacts_after_steps() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+        let store = ArtifactStore::new(std::env::temp_dir().join("sealci-artifacts-match-test"));
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec!["cargo build".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        )
+        .with_artifacts(
+            vec!["target/release/app".to_string(), "*.tar.gz".to_string()],
+            store.clone(),
+        );
+
+        // Act
+        let result = action.execute().await;
+
+        // Assert
+        assert!(result.is_ok());
+
+        // The mock container matches no file
+        let _step = rx.recv().await.unwrap().unwrap();
+        let artifacts = rx.recv().await.unwrap().unwrap();
+        assert_eq!(artifacts.log, "No file matches the artifacts");
+        assert_eq!(
+            artifacts.result.unwrap().completion(),
+            ActionStatus::Running
+        );
+        assert!(store.take(1).is_empty());
+
+        let container = Arc::new(&action.container);
+        assert_eq!(container.exec_calls.lock().unwrap().len(), 1);
+        let calls = container.exec_args_calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0[..4], ["sh", "-c", MATCH_ARTIFACTS_SCRIPT, "sh"]);
+        assert_eq!(calls[0].0[4..], ["target/release/app", "*.tar.gz"]);
+        assert_eq!(calls[0].1, Some("/1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_action_archive_writes_container_archive_to_file() {
+        // Arrange
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+        let store = ArtifactStore::new(std::env::temp_dir().join("sealci-artifacts-archive-test"));
+        let file = store.file(2, 0);
+
+        let action = Action::new(
+            2,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.archive("dist", &file).await;
+
+        // Assert
+        assert!(result.is_ok());
+        // The mock archive only holds the archived path
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "/2/dist");
+        let _ = std::fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    /// Answer a single HTTP request on a local port with the given status and body, returning the URL to request.
+    async fn serve_once(status: &'static str, body: &'static [u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = tokio::io::AsyncReadExt::read(&mut socket, &mut request).await;
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(body).await.unwrap();
+        });
+        format!("http://{}/artifact", address)
+    }
+
+    #[tokio::test]
+    async fn test_action_fetch_artifacts_unpacks_in_parent_directory() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+        let url = serve_once("200 OK", b"archive").await;
+
+        let action = Action::new(
+            3,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        )
+        .with_input_artifacts(vec![ArtifactReference {
+            path: "target/release/app".to_string(),
+            url,
+        }]);
+
+        // Act
+        let result = action.fetch_artifacts(&reqwest::Client::new()).await;
+
+        // Assert
+        assert!(result.is_ok());
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Artifact target/release/app fetched");
+        let exec_args_calls = action.container.exec_args_calls.lock().unwrap();
+        assert_eq!(
+            exec_args_calls[0].0,
+            vec!["mkdir", "-p", "--", "/3/target/release"]
+        );
+        let extract_calls = action.container.extract_calls.lock().unwrap();
+        assert_eq!(
+            *extract_calls,
+            vec![("/3/target/release".to_string(), b"archive".to_vec())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_action_fetch_artifacts_fails_on_missing_artifact() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+        let url = serve_once("404 Not Found", b"").await;
+
+        let action = Action::new(
+            4,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        )
+        .with_input_artifacts(vec![ArtifactReference {
+            path: "app".to_string(),
+            url,
+        }]);
+
+        // Act
+        let result = action.fetch_artifacts(&reqwest::Client::new()).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::ArtifactDownloadError(_))));
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Failed to fetch artifact app");
+        assert!(action.container.extract_calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_action_fetch_artifacts_fails_when_directory_is_not_created() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut mock_container = MockContainer::new(false);
+        mock_container.exit_code = 1;
+
+        let action = Action::new(
+            6,
+            mock_container,
+            vec![],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        )
+        .with_input_artifacts(vec![ArtifactReference {
+            path: "dist/app".to_string(),
+            url: "http://127.0.0.1:1/unreachable".to_string(),
+        }]);
+
+        // Act
+        let result = action.fetch_artifacts(&reqwest::Client::new()).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::CommandExitError(1))));
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Failed to fetch artifact dist/app");
+        assert!(action.container.extract_calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_action_prepare_removes_container_when_fetch_fails() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+        let url = serve_once("404 Not Found", b"").await;
+
+        let mut action = Action::new(
+            7,
+            mock_container,
+            vec!["./test.sh".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        )
+        .with_input_artifacts(vec![ArtifactReference {
+            path: "app".to_string(),
+            url,
+        }]);
+
+        // Act
+        let result = action.prepare(&reqwest::Client::new()).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::ArtifactDownloadError(_))));
+        assert_eq!(*action.container.remove_calls.lock().unwrap(), 1);
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Failed to fetch artifact app");
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Action failed");
+        let result = message.result.unwrap();
+        assert_eq!(result.completion(), ActionStatus::Error);
+        assert_eq!(result.exit_code, None);
+    }
+
     #[tokio::test]
     async fn test_action_execute_handles_step_failure() {
         // Arrange - Setup a mock that will fail on execution
         let (tx, _rx) = mpsc::unbounded_channel();
-        let mock_container = MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: true,
-        };
+        let mock_container = MockContainer::new(true);
 
         let mut action = Action::new(
             1,
@@ -218,6 +918,7 @@ mod tests {
             vec!["echo 'will fail'".to_string()],
             tx,
             "https://example.com/repo.git".to_string(),
+            Vec::new(),
             Arc::new(StateBroker::new()),
         );
 
@@ -236,10 +937,7 @@ mod tests {
     async fn test_action_cleanup_removes_container() {
         // Arrange
         let (tx, _rx) = mpsc::unbounded_channel();
-        let mock_container = MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: false,
-        };
+        let mock_container = MockContainer::new(false);
 
         let action = Action::new(
             1,
@@ -247,6 +945,7 @@ mod tests {
             vec![],
             tx,
             "https://example.com/repo.git".to_string(),
+            Vec::new(),
             Arc::new(StateBroker::new()),
         );
 
@@ -257,14 +956,68 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_action_cancel_reports_cancelled_status() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec!["sleep 3600".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.cancel().await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert!(matches!(action.state, State::Cancelled));
+
+        let message = rx.recv().await.unwrap().unwrap();
+        let result = message.result.unwrap();
+        assert_eq!(result.completion(), ActionStatus::Cancelled);
+        assert_eq!(result.exit_code, None);
+    }
+
+    #[tokio::test]
+    async fn test_action_time_out_reports_timed_out_status() {
+        // Arrange
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mock_container = MockContainer::new(false);
+
+        let mut action = Action::new(
+            1,
+            mock_container,
+            vec!["sleep 3600".to_string()],
+            tx,
+            "https://example.com/repo.git".to_string(),
+            Vec::new(),
+            Arc::new(StateBroker::new()),
+        );
+
+        // Act
+        let result = action.time_out(Duration::from_secs(90)).await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert!(matches!(action.state, State::TimedOut));
+
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "Action timed out after 90s");
+        assert_eq!(message.result.unwrap().completion(), ActionStatus::TimedOut);
+    }
+
     #[tokio::test]
     async fn test_action_new_initializes_with_correct_values() {
         // Arrange
         let (tx, _rx) = mpsc::unbounded_channel();
-        let mock_container = MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: false,
-        };
+        let mock_container = MockContainer::new(false);
 
         let action_id = 99;
         let commands = vec!["cmd1".to_string(), "cmd2".to_string()];
@@ -277,6 +1030,7 @@ mod tests {
             commands.clone(),
             tx,
             repo_url.clone(),
+            Vec::new(),
             Arc::new(StateBroker::new()),
         );
 
@@ -4,4 +4,6 @@ pub enum State {
     InProgress = 0,
     Completed = 1,
     Failed = 2,
+    Cancelled = 3,
+    TimedOut = 4,
 }
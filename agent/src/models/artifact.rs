@@ -0,0 +1,83 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// A file or a directory kept from the container of an action, archived as a tar on the agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    /// Path of the file or directory, as matched from the repository
+    pub path: String,
+    /// Where the archive is stored on the agent
+    pub file: PathBuf,
+}
+
+/// The artifacts of the actions that completed, by action id, until they are downloaded.
+#[derive(Clone)]
+pub struct ArtifactStore {
+    directory: PathBuf,
+    artifacts: Arc<Mutex<HashMap<u32, Vec<Artifact>>>>,
+}
+
+impl ArtifactStore {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            artifacts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Where the archive of the artifact of the action at the given index is stored.
+    pub fn file(&self, action_id: u32, index: usize) -> PathBuf {
+        self.directory
+            .join(action_id.to_string())
+            .join(format!("{}.tar", index))
+    }
+
+    /// Keep an artifact of an action until it is downloaded.
+    pub fn add(&self, action_id: u32, artifact: Artifact) {
+        self.artifacts
+            .lock()
+            .unwrap()
+            .entry(action_id)
+            .or_default()
+            .push(artifact);
+    }
+
+    /// The artifacts of an action, which the store forgets.
+    pub fn take(&self, action_id: u32) -> Vec<Artifact> {
+        self.artifacts
+            .lock()
+            .unwrap()
+            .remove(&action_id)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_store_files_by_action() {
+        let store = ArtifactStore::new(PathBuf::from("/tmp/artifacts"));
+
+        assert_eq!(store.file(7, 0), PathBuf::from("/tmp/artifacts/7/0.tar"));
+        assert_eq!(store.file(7, 1), PathBuf::from("/tmp/artifacts/7/1.tar"));
+    }
+
+    #[test]
+    fn test_artifact_store_take_forgets_artifacts() {
+        let store = ArtifactStore::new(PathBuf::from("/tmp/artifacts"));
+        let artifact = Artifact {
+            path: "target/release/app".to_string(),
+            file: store.file(7, 0),
+        };
+        store.add(7, artifact.clone());
+
+        assert_eq!(store.take(8), Vec::new());
+        assert_eq!(store.take(7), vec![artifact]);
+        assert_eq!(store.take(7), Vec::new());
+    }
+}
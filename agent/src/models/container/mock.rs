@@ -1,29 +1,42 @@
 use std::{pin::Pin, sync::Mutex};
 
 use bollard::container::LogOutput;
-use futures_util::{stream, Stream};
+use futures_util::{stream, Stream, StreamExt};
 
 use crate::models::error::Error;
 
-use super::{exec_handle::ExecResult, ContainerOperations};
+use super::{exec_handle::ExecResult, ArchiveStream, ContainerOperations, UploadStream};
 
 // A simpler mock implementation of ContainerOperations
 pub struct MockContainer {
     // Track what was passed to exec
     pub exec_calls: Mutex<Vec<(String, Option<String>)>>,
+    // Track what was passed to exec_args
+    pub exec_args_calls: Mutex<Vec<(Vec<String>, Option<String>)>>,
     // Configure if exec should return success or error
     pub should_fail: bool,
+    // Exit code of the commands run through exec
+    pub exit_code: i32,
+    // Track the archives unpacked, by directory
+    pub extract_calls: Mutex<Vec<(String, Vec<u8>)>>,
+    // Count the removals of the container
+    pub remove_calls: Mutex<usize>,
 }
 
-impl ContainerOperations for MockContainer {
-    async fn start(&self) -> Result<(), Error> {
-        Ok(())
+impl MockContainer {
+    /// A container whose exec calls all succeed, or all fail with `should_fail`.
+    pub fn new(should_fail: bool) -> Self {
+        Self {
+            exec_calls: Mutex::new(Vec::new()),
+            exec_args_calls: Mutex::new(Vec::new()),
+            should_fail,
+            exit_code: 0,
+            extract_calls: Mutex::new(Vec::new()),
+            remove_calls: Mutex::new(0),
+        }
     }
 
-    async fn exec(&self, command: String, workdir: Option<String>) -> Result<ExecResult, Error> {
-        // Record the call
-        self.exec_calls.lock().unwrap().push((command, workdir));
-
+    async fn exec_result(&self) -> Result<ExecResult, Error> {
         if self.should_fail {
             return Err(Error::ContainerExecError(bollard::errors::Error::from(
                 std::io::Error::new(std::io::ErrorKind::Other, "Mock exec error"),
@@ -34,16 +47,54 @@ impl ContainerOperations for MockContainer {
         let empty_stream = Box::pin(stream::empty::<Result<LogOutput, bollard::errors::Error>>())
             as Pin<Box<dyn Stream<Item = Result<LogOutput, bollard::errors::Error>> + Send>>;
 
-        // Create a task that just returns 0
-        let handle = tokio::task::spawn(async { 0 });
+        // Create a task that just returns the configured exit code
+        let exit_code = self.exit_code;
+        let handle = tokio::task::spawn(async move { exit_code });
 
         Ok(ExecResult {
             output: empty_stream,
             exec_handle: handle,
         })
     }
+}
+
+impl ContainerOperations for MockContainer {
+    async fn start(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn exec(&self, command: String, workdir: Option<String>) -> Result<ExecResult, Error> {
+        // Record the call
+        self.exec_calls.lock().unwrap().push((command, workdir));
+        self.exec_result().await
+    }
+
+    async fn exec_args(
+        &self,
+        args: Vec<String>,
+        workdir: Option<String>,
+    ) -> Result<ExecResult, Error> {
+        // Record the call
+        self.exec_args_calls.lock().unwrap().push((args, workdir));
+        self.exec_result().await
+    }
 
     async fn remove(&self) -> Result<(), Error> {
+        *self.remove_calls.lock().unwrap() += 1;
+        Ok(())
+    }
+
+    async fn archive(&self, path: String) -> Result<ArchiveStream, Error> {
+        // The archive only holds the archived path
+        Ok(Box::pin(stream::iter([Ok(path.into_bytes())])))
+    }
+
+    async fn extract(&self, directory: String, archive: UploadStream) -> Result<(), Error> {
+        let archive: Vec<Vec<u8>> = archive.collect().await;
+        self.extract_calls
+            .lock()
+            .unwrap()
+            .push((directory, archive.concat()));
         Ok(())
     }
 }
@@ -1,25 +1,37 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
 use tokio::{task, time::sleep};
 pub mod exec_handle;
 pub mod mock;
 use bollard::{
-    container::Config,
+    container::{Config, DownloadFromContainerOptions, UploadToContainerOptions},
     exec::{CreateExecOptions, StartExecResults},
     image::CreateImageOptions,
     Docker,
 };
 use exec_handle::ExecResult;
-use futures_util::TryStreamExt;
+use futures_util::{Stream, StreamExt, TryStreamExt};
 
 use super::error::Error::{
-    self, ContainerExecDetachedError, ContainerExecError, ContainerRemoveError,
-    ContainerStartError, PullImageError,
+    self, ContainerExecDetachedError, ContainerExecError, ContainerExtractError,
+    ContainerRemoveError, ContainerStartError, PullImageError,
 };
+use super::shell::Shell;
+
+/// The parts of a tar archive of files of a container, in order
+pub type ArchiveStream =
+    Pin<Box<dyn Stream<Item = Result<Vec<u8>, bollard::errors::Error>> + Send>>;
+
+/// The parts of a tar archive to unpack in a container, in order
+pub type UploadStream = Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>;
 
 #[derive(Debug, Clone)]
 pub struct Container {
     pub id: String,
     pub config: Config<String>,
+    /// The shell running the commands executed in the container
+    pub shell: Shell,
+    /// The environment variables of the commands, as `KEY=VALUE`
+    pub env: Vec<String>,
     docker: Option<Arc<Docker>>,
 }
 
@@ -28,24 +40,55 @@ pub trait ContainerOperations {
     /// Start the container
     fn start(&self) -> impl std::future::Future<Output = Result<(), Error>>;
 
-    /// Execute a command in the container
+    /// Execute a command in the container, as a script of its shell
     fn exec(
         &self,
         command: String,
         workdir: Option<String>,
     ) -> impl std::future::Future<Output = Result<ExecResult, Error>>;
 
+    /// Execute a program in the container, its arguments being passed as is instead of through the shell
+    fn exec_args(
+        &self,
+        args: Vec<String>,
+        workdir: Option<String>,
+    ) -> impl std::future::Future<Output = Result<ExecResult, Error>>;
+
     /// Remove the container
     fn remove(&self) -> impl std::future::Future<Output = Result<(), Error>> + Send;
+
+    /// Archive a file or a directory of the container as a tar
+    fn archive(
+        &self,
+        path: String,
+    ) -> impl std::future::Future<Output = Result<ArchiveStream, Error>>;
+
+    /// Unpack a tar archive in a directory of the container, which must exist
+    fn extract(
+        &self,
+        directory: String,
+        archive: UploadStream,
+    ) -> impl std::future::Future<Output = Result<(), Error>>;
 }
 
 impl Container {
-    pub fn new(image: String, docker: Arc<Docker>) -> Self {
+    pub fn new(
+        image: String,
+        shell: Shell,
+        env: HashMap<String, String>,
+        docker: Arc<Docker>,
+    ) -> Self {
         let id = format!("{:x}", rand::random::<u128>());
         let entrypoint = Some(vec!["/bin/sh".to_string()]);
+        let mut env: Vec<String> = env
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        env.sort();
         let config = Config {
             entrypoint,
             image: Some(image),
+            env: Some(env.clone()),
             attach_stdin: Some(true),
             attach_stdout: Some(true),
             attach_stderr: Some(true),
@@ -55,6 +98,8 @@ impl Container {
         Container {
             id,
             config,
+            shell,
+            env,
             docker: Some(docker),
         }
     }
@@ -106,14 +151,24 @@ impl ContainerOperations for Container {
     }
 
     async fn exec(&self, command: String, workdir: Option<String>) -> Result<ExecResult, Error> {
+        self.exec_args(self.shell.command(&command), workdir).await
+    }
+
+    async fn exec_args(
+        &self,
+        args: Vec<String>,
+        workdir: Option<String>,
+    ) -> Result<ExecResult, Error> {
         let docker = self.docker()?;
 
         let exec = docker
             .create_exec(
                 &self.id,
                 CreateExecOptions {
-                    cmd: Some(command.split(' ').map(String::from).collect()),
-                    tty: Some(true),
+                    cmd: Some(args),
+                    env: Some(self.env.clone()),
+                    // Without a terminal, the output of the command tells stdout and stderr apart
+                    tty: Some(false),
                     attach_stdin: Some(true),
                     attach_stdout: Some(true),
                     attach_stderr: Some(true),
@@ -172,6 +227,28 @@ impl ContainerOperations for Container {
             .map_err(ContainerRemoveError)?;
         Ok(())
     }
+
+    async fn archive(&self, path: String) -> Result<ArchiveStream, Error> {
+        let archive = self
+            .docker()?
+            .download_from_container(&self.id, Some(DownloadFromContainerOptions { path }))
+            .map_ok(|bytes| bytes.to_vec());
+        Ok(Box::pin(archive))
+    }
+
+    async fn extract(&self, directory: String, archive: UploadStream) -> Result<(), Error> {
+        self.docker()?
+            .upload_to_container_streaming(
+                &self.id,
+                Some(UploadToContainerOptions {
+                    path: directory,
+                    no_overwrite_dir_non_dir: "false".to_string(),
+                }),
+                archive.map(Into::into),
+            )
+            .await
+            .map_err(ContainerExtractError)
+    }
 }
 
 impl Default for Container {
@@ -179,6 +256,8 @@ impl Default for Container {
         Self {
             id: String::new(),
             config: Config::default(),
+            shell: Shell::default(),
+            env: Vec::new(),
             docker: None,
         }
     }
@@ -12,8 +12,13 @@ pub enum Error {
     ContainerRemoveError(bollard::errors::Error),
     ContainerExecError(bollard::errors::Error),
     ContainerExecDetachedError,
+    ContainerArchiveError(bollard::errors::Error),
+    ArtifactError(std::io::Error),
+    ContainerExtractError(bollard::errors::Error),
+    ArtifactDownloadError(reqwest::Error),
     ExecError(JoinError),
     StepOutputError(i32),
+    CommandExitError(i32),
     ConnectionError(tonic::transport::Error),
     ServeError(tonic::transport::Error),
     RegistrationError(Status),
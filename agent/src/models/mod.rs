@@ -1,5 +1,7 @@
 pub mod action;
+pub mod artifact;
 pub mod container;
 pub mod error;
 pub mod output_pipe;
+pub mod shell;
 pub mod step;
@@ -1,38 +1,136 @@
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use tokio::sync::mpsc::UnboundedSender;
 use tonic::Status;
 
-use crate::proto::{ActionResponseStream, ActionResult};
+use crate::proto::{ActionResponseStream, ActionResult, ActionStatus, LogStream};
+
+/// Replaces the secret values in the logs.
+const MASK: &str = "***";
 
 /// An output pipe is used to stream the output of an action.
 /// It is directly associated with an action and provides a way to send logs and results back to the client.
+/// The secret values used by the action are masked before a log is sent, and each log is numbered so that the
+/// lines of the action can be ordered.
 pub struct OutputPipe {
     action_id: u32,
+    secrets: Vec<String>,
+    /// Sequence number of the last log sent
+    sequence: AtomicU64,
     pipe: UnboundedSender<Result<ActionResponseStream, Status>>,
 }
 
 pub trait Pipe {
+    /// Send a log about the whole action, along with its status.
     fn output_log(&self, log: String, completion: i32, exit_code: Option<i32>);
+
+    /// Send a line about a step, or written by its command on the given stream.
+    fn output_step_log(&self, log: String, step: u32, stream: LogStream);
 }
 
 impl OutputPipe {
     pub fn new(
         action_id: u32,
+        secrets: Vec<String>,
         pipe: UnboundedSender<Result<ActionResponseStream, Status>>,
     ) -> Self {
-        Self { action_id, pipe }
+        // A multi-line secret is printed line by line by most commands, so each of its lines is masked as well
+        let lines: Vec<String> = secrets
+            .iter()
+            .filter(|secret| secret.contains('\n'))
+            .flat_map(|secret| secret.lines().map(String::from).collect::<Vec<_>>())
+            .collect();
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .chain(lines)
+            .filter(|secret| !secret.trim().is_empty())
+            .collect();
+        // Longest first, so a secret containing another one is masked as a whole
+        secrets.sort_by_key(|secret| Reverse(secret.len()));
+        Self {
+            action_id,
+            secrets,
+            sequence: AtomicU64::new(0),
+            pipe,
+        }
+    }
+
+    /// Replace every secret value found in the log.
+    fn mask(&self, log: String) -> String {
+        self.secrets
+            .iter()
+            .fold(log, |log, secret| log.replace(secret.as_str(), MASK))
+    }
+
+    fn send(&self, log: String, result: ActionResult, step: Option<u32>, stream: LogStream) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as i64)
+            .unwrap_or_default();
+        let _ = self.pipe.send(Ok(ActionResponseStream {
+            log: self.mask(log),
+            action_id: self.action_id,
+            result: Some(result),
+            sequence: self.sequence.fetch_add(1, Ordering::SeqCst) + 1,
+            timestamp,
+            step,
+            stream: stream.into(),
+        }));
     }
 }
 
 impl Pipe for OutputPipe {
     fn output_log(&self, log: String, completion: i32, exit_code: Option<i32>) {
-        let _ = self.pipe.send(Ok(ActionResponseStream {
+        self.send(
             log,
-            action_id: self.action_id,
-            result: Some(ActionResult {
+            ActionResult {
                 completion,
                 exit_code,
-            }),
-        }));
+            },
+            None,
+            LogStream::System,
+        );
+    }
+
+    fn output_step_log(&self, log: String, step: u32, stream: LogStream) {
+        self.send(
+            log,
+            ActionResult {
+                completion: ActionStatus::Running.into(),
+                exit_code: None,
+            },
+            Some(step),
+            stream,
+        );
+    }
+}
+
+/// Splits the output of a command into lines, keeping the end of the output until its line is complete.
+#[derive(Default)]
+pub struct LineBuffer {
+    partial: String,
+}
+
+impl LineBuffer {
+    /// The lines completed by this chunk of output.
+    pub fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.partial.push_str(chunk);
+        let Some(end) = self.partial.rfind('\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let lines = self.partial.lines().map(|line| line.to_string()).collect();
+        self.partial = rest;
+        lines
+    }
+
+    /// The last line, if the output did not end with a line break.
+    pub fn flush(self) -> Option<String> {
+        Some(self.partial).filter(|line| !line.is_empty())
     }
 }
 
@@ -41,6 +139,8 @@ impl Default for OutputPipe {
         let (tx, _) = tokio::sync::mpsc::unbounded_channel();
         Self {
             action_id: 0,
+            secrets: Vec::new(),
+            sequence: AtomicU64::new(0),
             pipe: tx,
         }
     }
@@ -59,7 +159,7 @@ mod tests {
         UnboundedReceiver<Result<ActionResponseStream, Status>>,
     ) {
         let (tx, rx) = mpsc::unbounded_channel();
-        let pipe = OutputPipe::new(action_id, tx);
+        let pipe = OutputPipe::new(action_id, Vec::new(), tx);
         (pipe, rx)
     }
 
@@ -112,7 +212,7 @@ mod tests {
     async fn test_new_creates_pipe_with_correct_id() {
         let action_id = 789;
         let (tx, _rx) = mpsc::unbounded_channel();
-        let pipe = OutputPipe::new(action_id, tx);
+        let pipe = OutputPipe::new(action_id, Vec::new(), tx);
 
         // We can't directly access action_id since it's private
         // So we'll test indirectly by sending a message
@@ -122,4 +222,87 @@ mod tests {
         // In a real test, you might want to structure things differently
         // This is mainly to show that the constructor works
     }
+
+    #[tokio::test]
+    async fn test_output_log_masks_secrets() {
+        // Setup
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let secrets = vec![
+            "hunter2".to_string(),
+            "".to_string(),
+            "hunter2-admin".to_string(),
+        ];
+        let pipe = OutputPipe::new(1, secrets, tx);
+
+        // Exercise
+        pipe.output_log(
+            "login hunter2-admin with hunter2 then hunter2".to_string(),
+            2,
+            None,
+        );
+
+        // Verify
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "login *** with *** then ***");
+    }
+
+    #[tokio::test]
+    async fn test_output_log_masks_each_line_of_multi_line_secrets() {
+        // Setup
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let key = "-----BEGIN KEY-----\nMIIBOgIBAAJBAKj34GkxFhD9\n-----END KEY-----\n".to_string();
+        let pipe = OutputPipe::new(1, vec![key.clone()], tx);
+
+        // Exercise
+        pipe.output_log(key.clone(), 2, None);
+        for line in key.lines() {
+            pipe.output_log(format!("key: {}", line), 2, None);
+        }
+
+        // Verify
+        let message = rx.recv().await.unwrap().unwrap();
+        assert_eq!(message.log, "***");
+        for _ in key.lines() {
+            let message = rx.recv().await.unwrap().unwrap();
+            assert_eq!(message.log, "key: ***");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_output_logs_are_numbered_with_their_step_and_stream() {
+        // Setup
+        let (pipe, mut rx) = create_test_pipe(1);
+
+        // Exercise
+        pipe.output_step_log("cargo build".to_string(), 0, LogStream::System);
+        pipe.output_step_log("error[E0308]".to_string(), 0, LogStream::Stderr);
+        pipe.output_log("Action failed".to_string(), 3, Some(101));
+
+        // Verify
+        let command = rx.recv().await.unwrap().unwrap();
+        let error = rx.recv().await.unwrap().unwrap();
+        let failure = rx.recv().await.unwrap().unwrap();
+        assert_eq!(
+            (command.sequence, error.sequence, failure.sequence),
+            (1, 2, 3)
+        );
+        assert_eq!(error.step, Some(0));
+        assert_eq!(error.stream(), LogStream::Stderr);
+        assert_eq!(failure.step, None);
+        assert_eq!(failure.stream(), LogStream::System);
+        assert!(command.timestamp > 0 && command.timestamp <= failure.timestamp);
+    }
+
+    #[test]
+    fn test_line_buffer_splits_chunks_into_lines() {
+        let mut buffer = LineBuffer::default();
+
+        assert_eq!(buffer.push("Compiling"), Vec::<String>::new());
+        assert_eq!(
+            buffer.push(" agent\r\nFinished\n\nRunning"),
+            vec!["Compiling agent", "Finished", ""]
+        );
+        assert_eq!(buffer.push(" tests"), Vec::<String>::new());
+        assert_eq!(buffer.flush(), Some("Running tests".to_string()));
+    }
 }
@@ -0,0 +1,41 @@
+/// The shell used to run the commands of an action, e.g. `sh` or `bash -eo pipefail`.
+/// Each command is given to the shell as a script through `-c`, so quoting, pipes, redirects,
+/// `&&`, environment expansion and multi-line scripts behave as in a terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shell {
+    program: Vec<String>,
+}
+
+/// Why a shell command line was rejected.
+#[derive(Debug, PartialEq)]
+pub enum ShellError {
+    /// The command line does not name a program.
+    Empty,
+}
+
+impl Shell {
+    /// Parse a shell from its command line, e.g. `bash -eo pipefail`.
+    pub fn new(command_line: &str) -> Result<Self, ShellError> {
+        let program: Vec<String> = command_line.split_whitespace().map(String::from).collect();
+        if program.is_empty() {
+            return Err(ShellError::Empty);
+        }
+        Ok(Self { program })
+    }
+
+    /// Build the arguments running the script through the shell.
+    pub fn command(&self, script: &str) -> Vec<String> {
+        let mut command = self.program.clone();
+        command.push("-c".to_string());
+        command.push(script.to_string());
+        command
+    }
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self {
+            program: vec!["sh".to_string()],
+        }
+    }
+}
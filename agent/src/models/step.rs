@@ -35,15 +35,11 @@ mod tests {
     use crate::models::container::mock::MockContainer;
 
     use super::*;
-    use std::sync::Mutex;
 
     #[tokio::test]
     async fn test_step_execute_passes_command_to_container() {
         // Setup
-        let container = Arc::new(MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: false,
-        });
+        let container = Arc::new(MockContainer::new(false));
 
         let command = "echo 'test'".to_string();
         let workdir = Some("/tmp".to_string());
@@ -63,10 +59,7 @@ mod tests {
     #[tokio::test]
     async fn test_step_execute_handles_error_from_container() {
         // Setup
-        let container = Arc::new(MockContainer {
-            exec_calls: Mutex::new(Vec::new()),
-            should_fail: true, // Configure to return an error
-        });
+        let container = Arc::new(MockContainer::new(true));
 
         let step = Step::new("any command".to_string(), None, container);
 
@@ -1,14 +1,37 @@
 use crate::proto::{
     action_service_server::ActionService as ActionServiceGrpc, ActionRequest, ActionResponseStream,
+    ArtifactChunk, ArtifactsRequest, CancelActionRequest, CancelActionResponse,
 };
-use crate::services::action_service::ActionService;
+use crate::models::shell::Shell;
+use crate::services::action_service::{ActionService, ActionSpec};
 use futures_util::{Stream, StreamExt};
 use std::pin::Pin;
-use tokio::sync::mpsc::unbounded_channel;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc::{channel, unbounded_channel};
 use tokio::sync::oneshot;
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::wrappers::{ReceiverStream, UnboundedReceiverStream};
 use tonic::{async_trait, Request, Response, Status};
-use tracing::info;
+use tracing::{error, info};
+
+/// Size of the parts in which the archives of the artifacts are sent.
+const ARTIFACT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How the execution of an action ended.
+enum Outcome {
+    Executed,
+    Cancelled,
+    TimedOut,
+}
+
+/// Resolve once the timeout elapsed, or never if there is no timeout.
+async fn deadline(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
 
 pub struct ActionsLauncher {
     pub action_service: ActionService,
@@ -18,6 +41,8 @@ pub struct ActionsLauncher {
 impl ActionServiceGrpc for ActionsLauncher {
     type ExecutionActionStream =
         Pin<Box<dyn Stream<Item = Result<ActionResponseStream, Status>> + Send>>;
+    type DownloadArtifactsStream =
+        Pin<Box<dyn Stream<Item = Result<ArtifactChunk, Status>> + Send>>;
 
     async fn execution_action(
         &self,
@@ -37,23 +62,70 @@ impl ActionServiceGrpc for ActionsLauncher {
         let container_image = context
             .container_image
             .ok_or(Status::invalid_argument("Container image is missing"))?;
+        let shell = match context.shell {
+            Some(shell) => {
+                Shell::new(&shell).map_err(|_| Status::invalid_argument("Shell is invalid"))?
+            }
+            None => Shell::default(),
+        };
+
+        // Register the action before creating it, so a cancellation received during the setup is not lost
+        let action_id = request_body.action_id;
+        let cancellations = self.action_service.cancellations.clone();
+        let mut cancelled = cancellations.register(action_id);
 
-        let mut action = self
+        let spec = ActionSpec {
+            action_id,
+            image: container_image,
+            shell,
+            env: context.env,
+            secrets: context.secrets,
+            commands: request_body.commands,
+            repo_url: request_body.repo_url,
+            artifacts: request_body.artifacts,
+            input_artifacts: request_body.input_artifacts,
+        };
+        let mut action = match self
             .action_service
-            .create(
-                container_image,
-                request_body.commands,
-                log_tx.clone(),
-                request_body.repo_url,
-                request_body.action_id,
-            )
+            .create(spec, log_tx.clone())
             .await
-            .map_err(|_| Status::failed_precondition("Failed to create action"))?;
+        {
+            Ok(action) => action,
+            Err(_) => {
+                cancellations.unregister(action_id);
+                return Err(Status::failed_precondition("Failed to create action"));
+            }
+        };
 
-        // Spawn a task to execute the action and signal completion
+        let timeout = request_body.timeout.map(|secs| Duration::from_secs(secs.into()));
+        let http_client = self.action_service.http_client.clone();
+
+        // Spawn a task to execute the action, unless it is cancelled or times out, and signal completion
         tokio::spawn(async move {
-            let _ = action.execute().await;
-            info!("Action executed");
+            let outcome = tokio::select! {
+                biased;
+                Ok(()) = &mut cancelled => Outcome::Cancelled,
+                _ = deadline(timeout) => Outcome::TimedOut,
+                _ = action.run(&http_client) => Outcome::Executed,
+            };
+            cancellations.unregister(action_id);
+            match outcome {
+                Outcome::Cancelled => {
+                    if let Err(e) = action.cancel().await {
+                        error!("Failed to cancel action {}: {:?}", action_id, e);
+                    }
+                    info!("Action {} cancelled", action_id);
+                }
+                Outcome::TimedOut => {
+                    // Only reachable when a timeout is set
+                    let timeout = timeout.unwrap_or_default();
+                    if let Err(e) = action.time_out(timeout).await {
+                        error!("Failed to stop timed out action {}: {:?}", action_id, e);
+                    }
+                    info!("Action {} timed out", action_id);
+                }
+                Outcome::Executed => info!("Action executed"),
+            }
 
             // Signal completion then drop the sender
             let _ = done_tx.send(());
@@ -64,4 +136,80 @@ impl ActionServiceGrpc for ActionsLauncher {
         let stream = log_stream.take_until(done_rx);
         Ok(Response::new(Box::pin(stream)))
     }
+
+    async fn cancel_action(
+        &self,
+        request: Request<CancelActionRequest>,
+    ) -> Result<Response<CancelActionResponse>, Status> {
+        let action_id = request.into_inner().action_id;
+        self.action_service
+            .cancellations
+            .cancel(action_id)
+            .map_err(|_| Status::not_found(format!("Action {} is not running", action_id)))?;
+        info!("Cancelling action {}", action_id);
+        Ok(Response::new(CancelActionResponse { action_id }))
+    }
+
+    async fn download_artifacts(
+        &self,
+        request: Request<ArtifactsRequest>,
+    ) -> Result<Response<Self::DownloadArtifactsStream>, Status> {
+        let action_id = request.into_inner().action_id;
+        let store = self.action_service.artifact_store.clone();
+        let artifacts = store.take(action_id);
+        info!(
+            "Sending {} artifacts of action {}",
+            artifacts.len(),
+            action_id
+        );
+
+        let (tx, rx) = channel::<Result<ArtifactChunk, Status>>(4);
+        tokio::spawn(async move {
+            'artifacts: for artifact in artifacts {
+                let mut file = match fs::File::open(&artifact.file).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        error!("Failed to open artifact {}: {:?}", artifact.path, e);
+                        let _ = tx
+                            .send(Err(Status::internal("Failed to read artifact")))
+                            .await;
+                        break 'artifacts;
+                    }
+                };
+                // An artifact is sent in at least one chunk, so that it is known even when empty
+                let mut sent = false;
+                loop {
+                    let mut data = vec![0; ARTIFACT_CHUNK_SIZE];
+                    let read = match file.read(&mut data).await {
+                        Ok(read) => read,
+                        Err(e) => {
+                            error!("Failed to read artifact {}: {:?}", artifact.path, e);
+                            let _ = tx
+                            .send(Err(Status::internal("Failed to read artifact")))
+                            .await;
+                            break 'artifacts;
+                        }
+                    };
+                    if read == 0 && sent {
+                        break;
+                    }
+                    data.truncate(read);
+                    let chunk = ArtifactChunk {
+                        path: artifact.path.clone(),
+                        data,
+                    };
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        break 'artifacts;
+                    }
+                    sent = true;
+                }
+            }
+            // The artifacts are sent once
+            if let Some(directory) = store.file(action_id, 0).parent() {
+                let _ = fs::remove_dir_all(directory).await;
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
 }
@@ -1,28 +1,89 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use bollard::Docker;
-use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
 use tonic::Status;
 
 use crate::{
     brokers::{action_broker::ActionBroker, state_broker::StateBroker, Broker},
     models::{
         action::Action,
-        container::{Container, ContainerOperations},
+        artifact::ArtifactStore,
+        container::Container,
         error::Error,
+        shell::Shell,
     },
-    proto::ActionResponseStream,
+    proto::{ActionResponseStream, ArtifactReference},
 };
 
+/// The cancellation requests of the actions running on the agent, by action id.
+#[derive(Clone, Default)]
+pub struct Cancellations {
+    senders: Arc<Mutex<HashMap<u32, oneshot::Sender<()>>>>,
+}
+
+impl Cancellations {
+    /// Register a running action, the returned receiver resolves once the action is cancelled.
+    pub fn register(&self, action_id: u32) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.senders.lock().unwrap().insert(action_id, tx);
+        rx
+    }
+
+    /// Forget an action once it is over.
+    pub fn unregister(&self, action_id: u32) {
+        self.senders.lock().unwrap().remove(&action_id);
+    }
+
+    /// Request the cancellation of a running action.
+    pub fn cancel(&self, action_id: u32) -> Result<(), Error> {
+        let sender = self
+            .senders
+            .lock()
+            .unwrap()
+            .remove(&action_id)
+            .ok_or(Error::ActionNotFound)?;
+        sender.send(()).map_err(|_| Error::ActionNotFound)
+    }
+}
+
+/// What an action is created from, as requested by the scheduler.
+pub struct ActionSpec {
+    pub action_id: u32,
+    /// The image of the container the commands run in
+    pub image: String,
+    pub shell: Shell,
+    pub env: HashMap<String, String>,
+    /// The values masked in the logs
+    pub secrets: Vec<String>,
+    pub commands: Vec<String>,
+    pub repo_url: String,
+    /// Patterns of the files kept once the commands succeeded
+    pub artifacts: Vec<String>,
+    /// Artifacts of other actions unpacked before the first command
+    pub input_artifacts: Vec<ArtifactReference>,
+}
+
 pub struct ActionService {
     docker_client: Arc<Docker>,
     actions: HashMap<u32, Action<Container>>,
     pub action_broker: ActionBroker,
     pub state_broker: Arc<StateBroker>,
+    pub cancellations: Cancellations,
+    pub artifact_store: ArtifactStore,
+    /// Downloads the artifacts of other actions
+    pub http_client: reqwest::Client,
 }
 
 impl ActionService {
-    pub fn new(docker_client: Arc<Docker>, state_broker: Arc<StateBroker>) -> Self {
+    pub fn new(
+        docker_client: Arc<Docker>,
+        state_broker: Arc<StateBroker>,
+        artifact_store: ArtifactStore,
+    ) -> Self {
         let actions = HashMap::new();
         let action_broker = ActionBroker::new();
         Self {
@@ -30,28 +91,35 @@ impl ActionService {
             actions,
             action_broker,
             state_broker,
+            cancellations: Cancellations::default(),
+            artifact_store,
+            http_client: reqwest::Client::new(),
         }
     }
 
     pub async fn create(
         &self,
-        image: String,
-        commands: Vec<String>,
+        spec: ActionSpec,
         log_input: UnboundedSender<Result<ActionResponseStream, Status>>,
-        repo_url: String,
-        action_id: u32,
     ) -> Result<Action<Container>, Error> {
-        let container = Container::new(image, self.docker_client.clone());
-        container.start().await?;
+        // The container is started by the action, so that its setup is bound by the timeout of the action
+        let container = Container::new(
+            spec.image,
+            spec.shell,
+            spec.env,
+            self.docker_client.clone(),
+        );
         let action = Action::new(
-            action_id,
+            spec.action_id,
             container,
-            commands,
+            spec.commands,
             log_input,
-            repo_url,
+            spec.repo_url,
+            spec.secrets,
             self.state_broker.clone(),
-        );
-        action.setup_repository().await?;
+        )
+        .with_artifacts(spec.artifacts, self.artifact_store.clone())
+        .with_input_artifacts(spec.input_artifacts);
         self.action_broker
             .create_action_channel
             .send_event(action.clone())?;
@@ -85,3 +153,29 @@ impl ActionService {
             .ok_or(Error::ActionNotFound)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancellations_signal_registered_action() {
+        let cancellations = Cancellations::default();
+        let cancelled = cancellations.register(7);
+
+        assert!(cancellations.cancel(7).is_ok());
+        assert!(cancelled.await.is_ok());
+        // The cancellation is consumed.
+        assert!(matches!(cancellations.cancel(7), Err(Error::ActionNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_cancellations_ignore_unknown_action() {
+        let cancellations = Cancellations::default();
+        let _cancelled = cancellations.register(7);
+        cancellations.unregister(7);
+
+        assert!(matches!(cancellations.cancel(7), Err(Error::ActionNotFound)));
+        assert!(matches!(cancellations.cancel(8), Err(Error::ActionNotFound)));
+    }
+}
@@ -7,6 +7,10 @@ use tokio::{
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+/// Maximum delay between two health reports, even without significant change.
+/// The scheduler evicts agents that stay silent for too long, so this acts as a heartbeat.
+const HEARTBEAT_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(10);
+
 #[derive(Clone)]
 pub struct HealthService {
     system: Arc<Mutex<System>>,
@@ -25,15 +29,19 @@ impl HealthService {
             cpu_avail: 0,
             memory_avail: 0,
         };
+        let mut last_report = tokio::time::Instant::now();
         let mut service = self.clone();
         let handle_health_lifecycle = tokio::spawn(async move {
             loop {
                 // Fetch current usage
                 let current_health = service.get_health().await;
 
-                // Check if the change is significant
-                if HealthService::has_significant_change(previous_usage, current_health, 5.0) {
+                // Report if the change is significant, or if the heartbeat is due
+                if HealthService::has_significant_change(previous_usage, current_health, 5.0)
+                    || last_report.elapsed() >= HEARTBEAT_INTERVAL
+                {
                     previous_usage = current_health;
+                    last_report = tokio::time::Instant::now();
                     let _ = tx.send(current_health);
                 }
 
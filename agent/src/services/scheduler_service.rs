@@ -16,6 +16,10 @@ pub struct SchedulerService {
     /// The URL that the agent will give to the scheduler.
     agent_advertise_url: String,
     port: u32,
+    /// The labels the agent advertises to the scheduler, matched against the labels required by actions.
+    labels: Vec<String>,
+    /// The maximum number of actions the scheduler may run on the agent at once.
+    max_concurrency: u32,
     agent_id: Option<u32>,
 }
 
@@ -24,6 +28,8 @@ impl SchedulerService {
         scheduler_url: String,
         agent_host: String,
         port: u32,
+        labels: Vec<String>,
+        max_concurrency: u32,
         health_service: HealthService,
     ) -> Result<Self, Error> {
         info!("{}", scheduler_url.to_string());
@@ -36,6 +42,8 @@ impl SchedulerService {
             health_service,
             agent_advertise_url,
             port,
+            labels,
+            max_concurrency,
             agent_id: None,
         })
     }
@@ -49,6 +57,8 @@ impl SchedulerService {
         let req = RegisterAgentRequest {
             health: Some(health),
             hostname: Some(host),
+            labels: self.labels.clone(),
+            max_concurrency: self.max_concurrency,
         };
         let request = tonic::Request::new(req);
         let res = self
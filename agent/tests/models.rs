@@ -1,20 +1,19 @@
 use agent::{brokers::state_broker::StateBroker, models::{
     action::Action,
-    container::mock::MockContainer,
+    container::{mock::MockContainer, Container},
     output_pipe::{OutputPipe, Pipe},
+    shell::Shell,
     step::Step,
 }};
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
 #[tokio::test]
 async fn test_model_integration_workflow() {
     // 1. Setup - Create container and communication channels
     let (tx, _) = mpsc::unbounded_channel();
-    let mock_container = MockContainer {
-        exec_calls: Mutex::new(Vec::new()),
-        should_fail: false,
-    };
+    let mock_container = MockContainer::new(false);
 
     // 2. Create the action with multiple steps
     let action_id = 123;
@@ -31,6 +30,7 @@ async fn test_model_integration_workflow() {
         commands.clone(),
         tx.clone(),
         repo_url.clone(),
+        Vec::new(),
         Arc::new(StateBroker::new())
     );
 
@@ -43,23 +43,21 @@ async fn test_model_integration_workflow() {
 
     // 4. Verify container was accessed correctly
     let container = Arc::new(&action.container);
+    let clone_calls = container.exec_args_calls.lock().unwrap();
     let calls = container.exec_calls.lock().unwrap();
 
-    // First call should be the git clone
+    // The git clone runs first, outside of the shell
+    assert_eq!(clone_calls[0].0[..2], ["git", "clone"], "Should start with git clone");
     assert!(
-        calls[0].0.contains("git clone"),
-        "Should start with git clone"
-    );
-    assert!(
-        calls[0].0.contains(&repo_url),
+        clone_calls[0].0.contains(&repo_url),
         "Should clone the correct repo"
     );
 
     // Check that each command was executed
     for (i, cmd) in commands.iter().enumerate() {
-        assert_eq!(&calls[i + 1].0, cmd, "Command {} should be executed", i + 1);
+        assert_eq!(&calls[i].0, cmd, "Command {} should be executed", i + 1);
         assert_eq!(
-            calls[i + 1].1,
+            calls[i].1,
             Some(format!("/{}", action_id)),
             "Command {} should execute in the right directory",
             i + 1
@@ -74,13 +72,10 @@ async fn test_step_to_action_to_output_integration() {
 
     // Create an output pipe
     let action_id = 42;
-    let pipe = Arc::new(OutputPipe::new(action_id, tx.clone()));
+    let pipe = Arc::new(OutputPipe::new(action_id, Vec::new(), tx.clone()));
 
     // Create a mock container
-    let mock_container = Arc::new(MockContainer {
-        exec_calls: Mutex::new(Vec::new()),
-        should_fail: false,
-    });
+    let mock_container = Arc::new(MockContainer::new(false));
 
     // Create a step
     let command = "echo 'Hello Integration'".to_string();
@@ -120,10 +115,7 @@ async fn test_step_to_action_to_output_integration() {
 async fn test_action_error_propagation() {
     // Setup - Create a failing container
     let (tx, _) = mpsc::unbounded_channel();
-    let mock_container = MockContainer {
-        exec_calls: Mutex::new(Vec::new()),
-        should_fail: true, // Configure to fail
-    };
+    let mock_container = MockContainer::new(true);
 
     let mut action = Action::new(
         1,
@@ -131,6 +123,7 @@ async fn test_action_error_propagation() {
         vec!["will_fail".to_string()],
         tx,
         "https://example.com/repo.git".to_string(),
+        Vec::new(),
         Arc::new(StateBroker::new())
     );
 
@@ -159,10 +152,7 @@ async fn test_action_error_propagation() {
 async fn test_complete_workflow_with_cleanup() {
     // Setup
     let (tx, _) = mpsc::unbounded_channel();
-    let mock_container = MockContainer {
-        exec_calls: Mutex::new(Vec::new()),
-        should_fail: false,
-    };
+    let mock_container = MockContainer::new(false);
 
     let mut action = Action::new(
         99,
@@ -170,6 +160,7 @@ async fn test_complete_workflow_with_cleanup() {
         vec!["echo 'success'".to_string()],
         tx,
         "https://example.com/test.git".to_string(),
+        Vec::new(),
         Arc::new(StateBroker::new())
     );
 
@@ -185,17 +176,93 @@ async fn test_complete_workflow_with_cleanup() {
 
     // Verify the full sequence of operations
     let container = Arc::new(&action.container);
+    let clone_calls = container.exec_args_calls.lock().unwrap();
     let calls = container.exec_calls.lock().unwrap();
 
-    // Expect at least 1 git clone + 1 command execution
-    assert!(calls.len() >= 2, "Should have at least 2 operations");
+    // Expect 1 git clone + 1 command execution
+    assert_eq!(clone_calls.len(), 1, "Should clone once");
+    assert_eq!(calls.len(), 1, "Should run the command");
 
-    // Check that the first call is git clone
-    assert!(
-        calls[0].0.contains("git clone"),
-        "First call should be git clone"
-    );
+    // Check that the clone is a git clone
+    assert_eq!(clone_calls[0].0[..2], ["git", "clone"], "Should be a git clone");
 
     // Check that the command was executed
-    assert_eq!(&calls[1].0, "echo 'success'", "Command should be executed");
+    assert_eq!(&calls[0].0, "echo 'success'", "Command should be executed");
+}
+
+#[test]
+fn test_shell_runs_command_as_a_single_script() {
+    let shell = Shell::default();
+
+    let command = shell.command("echo 'hello world' | tr a-z A-Z > /tmp/out && cat /tmp/out");
+
+    assert_eq!(
+        command,
+        vec![
+            "sh",
+            "-c",
+            "echo 'hello world' | tr a-z A-Z > /tmp/out && cat /tmp/out"
+        ]
+    );
+}
+
+#[test]
+fn test_shell_keeps_quotes_and_expansions_untouched() {
+    let shell = Shell::default();
+    let scripts = [
+        r#"echo "two  spaces""#,
+        r#"echo 'single "nested" quotes'"#,
+        r#"echo "it's""#,
+        r#"echo "$HOME" ${PATH} $(pwd)"#,
+        r#"echo escaped\ space"#,
+        "",
+    ];
+
+    for script in scripts {
+        let command = shell.command(script);
+        assert_eq!(command.len(), 3, "{:?} should be a single argument", script);
+        assert_eq!(command[2], script);
+    }
+}
+
+#[test]
+fn test_shell_runs_multi_line_scripts() {
+    let shell = Shell::default();
+    let script = "set -e\nfor i in 1 2 3; do\n  echo \"line $i\"\ndone\n";
+
+    let command = shell.command(script);
+
+    assert_eq!(command, vec!["sh", "-c", script]);
+}
+
+#[test]
+fn test_shell_with_options() {
+    let shell = Shell::new("bash   -eo pipefail").unwrap();
+
+    let command = shell.command("false | true");
+
+    assert_eq!(command, vec!["bash", "-eo", "pipefail", "-c", "false | true"]);
+}
+
+#[test]
+fn test_shell_cannot_be_blank() {
+    assert!(matches!(
+        Shell::new("  "),
+        Err(agent::models::shell::ShellError::Empty)
+    ));
+}
+
+#[test]
+fn test_container_receives_action_environment() {
+    let docker = Arc::new(bollard::Docker::connect_with_http_defaults().unwrap());
+    let env = HashMap::from([
+        ("RUST_LOG".to_string(), "debug".to_string()),
+        ("GREETING".to_string(), "hello world".to_string()),
+    ]);
+
+    let container = Container::new("alpine:3.20".to_string(), Shell::default(), env, docker);
+
+    let expected = vec!["GREETING=hello world".to_string(), "RUST_LOG=debug".to_string()];
+    assert_eq!(container.env, expected);
+    assert_eq!(container.config.env, Some(expected));
 }
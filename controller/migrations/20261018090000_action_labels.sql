@@ -0,0 +1,2 @@
+ALTER TABLE
+    "actions" ADD COLUMN "labels" TEXT[] NOT NULL DEFAULT '{}';
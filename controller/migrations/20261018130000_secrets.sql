@@ -0,0 +1,10 @@
+CREATE TABLE "secrets"(
+    "id" BIGSERIAL NOT NULL,
+    "name" VARCHAR(255) NOT NULL,
+    "nonce" BYTEA NOT NULL,
+    "value" BYTEA NOT NULL
+);
+ALTER TABLE
+    "secrets" ADD PRIMARY KEY("id");
+ALTER TABLE
+    "secrets" ADD CONSTRAINT "secrets_name_unique" UNIQUE("name");
@@ -0,0 +1,2 @@
+ALTER TABLE
+    "actions" ADD COLUMN "needs" TEXT[] NOT NULL DEFAULT '{}';
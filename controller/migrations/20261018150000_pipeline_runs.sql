@@ -0,0 +1,29 @@
+CREATE TABLE "pipeline_runs"(
+    "id" BIGSERIAL NOT NULL,
+    "pipeline_id" BIGINT NOT NULL,
+    "number" INTEGER NOT NULL,
+    "trigger" VARCHAR(255) NOT NULL,
+    "commit_sha" VARCHAR(255),
+    "status" VARCHAR(255) NOT NULL,
+    "started_at" TIMESTAMPTZ,
+    "finished_at" TIMESTAMPTZ
+);
+ALTER TABLE
+    "pipeline_runs" ADD PRIMARY KEY("id");
+ALTER TABLE
+    "pipeline_runs" ADD CONSTRAINT "pipeline_runs_pipeline_id_number_unique" UNIQUE("pipeline_id", "number");
+ALTER TABLE
+    "pipeline_runs" ADD CONSTRAINT "pipeline_runs_pipeline_id_foreign" FOREIGN KEY("pipeline_id") REFERENCES "pipelines"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- The actions are the executions of a run: existing pipelines get a first run holding their actions
+INSERT INTO
+    "pipeline_runs" ("pipeline_id", "number", "trigger", "status")
+SELECT "id", 1, 'manual', 'pending' FROM "pipelines";
+ALTER TABLE
+    "actions" ADD COLUMN "run_id" BIGINT;
+UPDATE
+    "actions" SET "run_id" = "pipeline_runs"."id" FROM "pipeline_runs" WHERE "pipeline_runs"."pipeline_id" = "actions"."pipeline_id";
+ALTER TABLE
+    "actions" ALTER COLUMN "run_id" SET NOT NULL;
+ALTER TABLE
+    "actions" ADD CONSTRAINT "actions_run_id_foreign" FOREIGN KEY("run_id") REFERENCES "pipeline_runs"("id") ON DELETE CASCADE ON UPDATE CASCADE;
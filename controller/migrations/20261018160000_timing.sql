@@ -0,0 +1,8 @@
+ALTER TABLE
+    "pipelines" ADD COLUMN "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE
+    "actions" ADD COLUMN "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE
+    "actions" ADD COLUMN "started_at" TIMESTAMPTZ;
+ALTER TABLE
+    "actions" ADD COLUMN "finished_at" TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+ALTER TABLE
+    "logs" ADD COLUMN "sequence" BIGINT;
+ALTER TABLE
+    "logs" ADD COLUMN "timestamp" TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE
+    "logs" ADD COLUMN "step" INTEGER;
+ALTER TABLE
+    "logs" ADD COLUMN "stream" VARCHAR NOT NULL DEFAULT 'system';
@@ -0,0 +1,5 @@
+CREATE INDEX "pipelines_repository_url_index" ON "pipelines"("repository_url", "id");
+CREATE INDEX "pipelines_created_at_index" ON "pipelines"("created_at");
+CREATE INDEX "actions_run_id_index" ON "actions"("run_id");
+CREATE INDEX "commands_action_id_index" ON "commands"("action_id");
+CREATE INDEX "logs_action_id_index" ON "logs"("action_id", "id");
@@ -0,0 +1,4 @@
+ALTER TABLE
+    "actions" ADD COLUMN "condition" TEXT;
+ALTER TABLE
+    "pipeline_runs" ADD COLUMN "branch" VARCHAR(255);
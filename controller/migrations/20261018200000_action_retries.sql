@@ -0,0 +1,22 @@
+ALTER TABLE
+    "actions" ADD COLUMN "retry_max_attempts" INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE
+    "actions" ADD COLUMN "retry_backoff" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE
+    "actions" ADD COLUMN "retry_on" TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE
+    "logs" ADD COLUMN "attempt" INTEGER NOT NULL DEFAULT 1;
+
+-- The attempts of an action that are over, the last one giving the status of the action
+CREATE TABLE "action_attempts"(
+    "action_id" BIGINT NOT NULL,
+    "number" INTEGER NOT NULL,
+    "status" VARCHAR(255) NOT NULL,
+    "exit_code" INTEGER,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "finished_at" TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+ALTER TABLE
+    "action_attempts" ADD PRIMARY KEY("action_id", "number");
+ALTER TABLE
+    "action_attempts" ADD CONSTRAINT "action_attempts_action_id_foreign" FOREIGN KEY("action_id") REFERENCES "actions"("id") ON DELETE CASCADE ON UPDATE CASCADE;
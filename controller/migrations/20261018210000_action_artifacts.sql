@@ -0,0 +1,14 @@
+ALTER TABLE
+    "actions" ADD COLUMN "artifacts" TEXT[] NOT NULL DEFAULT '{}';
+
+-- The files kept from an action that completed, each stored as a tar archive by the controller
+CREATE TABLE "artifacts"(
+    "id" BIGSERIAL PRIMARY KEY,
+    "action_id" BIGINT NOT NULL,
+    "path" TEXT NOT NULL,
+    "size" BIGINT NOT NULL,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX "artifacts_action_id_index" ON "artifacts"("action_id");
+ALTER TABLE
+    "artifacts" ADD CONSTRAINT "artifacts_action_id_foreign" FOREIGN KEY("action_id") REFERENCES "actions"("id") ON DELETE CASCADE ON UPDATE CASCADE;
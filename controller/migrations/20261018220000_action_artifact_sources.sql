@@ -0,0 +1,3 @@
+-- The actions whose artifacts are unpacked in the repository of the action before its commands run
+ALTER TABLE
+    "actions" ADD COLUMN "artifacts_from" TEXT[] NOT NULL DEFAULT '{}';
@@ -1,22 +1,75 @@
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+use time::OffsetDateTime;
 
 use crate::{
-    action::action_service::ActionDTO, grpc_scheduler::ActionStatus, parser::pipe_parser::Type,
+    action::action_service::ActionDTO,
+    grpc_scheduler::ActionStatus,
+    logs::Log,
+    parser::{
+        manifest::FailureClass,
+        pipe_parser::{RetryPolicy, Type},
+    },
+    timing::Timing,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Action {
     pub id: i64,
     pub pipeline_id: i64,
+    /// The run of the pipeline this action is an execution of
+    pub run_id: i64,
     pub name: String,
     pub container_uri: String,
+    /// Left out of the pipeline listing unless verbose
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub commands: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub logs: Option<Vec<String>>,
-    r#type: Type,
+    pub logs: Option<Vec<Log>>,
+    /// Attempts that are over, left out of the pipeline listing unless verbose
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<Vec<Attempt>>,
+    pub r#type: Type,
     status: String,
+    pub labels: Vec<String>,
+    /// Seconds after which the action is stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<i32>,
+    /// Shell running the commands
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Environment variables of the action container
+    pub env: BTreeMap<String, String>,
+    /// Names of the actions that must be over before this one starts
+    pub needs: Vec<String>,
+    /// Expression telling whether the action runs once the actions it needs are over
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// When and how often the action runs again after it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    /// Paths or shell glob patterns of the files of the repository kept once the commands succeeded
+    pub artifacts: Vec<String>,
+    /// Names of the actions it needs whose artifacts are unpacked in the repository before the commands run
+    pub artifacts_from: Vec<String>,
+    #[serde(flatten)]
+    pub timing: Timing,
+}
+
+/// An attempt of an action that is over. Its lines are the logs of the action with its number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    /// Position of the attempt among those of the action, starting at 1
+    pub number: i32,
+    pub status: String,
+    /// Exit code of the command that ended the attempt, if one ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub finished_at: OffsetDateTime,
 }
 
 #[derive(Debug)]
@@ -25,33 +78,87 @@ pub enum ActionCreationError {
 }
 
 impl Action {
-    pub fn new(
-        id: i64,
-        pipeline_id: i64,
-        name: String,
-        container_uri: String,
-        commands: Vec<String>,
-        r#type: Type,
-        status: String,
-    ) -> Result<Self, ActionCreationError> {
-        let status = ActionStatus::from_str_name(status.as_str());
+    /// Build the action stored as `dto`, along with its commands.
+    pub fn new(dto: ActionDTO, commands: Vec<String>) -> Result<Self, ActionCreationError> {
+        let status = ActionStatus::from_str_name(dto.status.as_str());
         if status.is_none() {
             return Err(ActionCreationError::UnknownStatus);
         }
         let status = ActionStatus::as_str_name(&status.unwrap()).to_string();
         return Ok(Action {
-            id,
-            pipeline_id,
-            name,
-            container_uri,
+            id: dto.id.unwrap_or_default(),
+            pipeline_id: dto.pipeline_id,
+            run_id: dto.run_id,
+            name: dto.name,
+            container_uri: dto.container_uri,
             status,
             logs: None,
-            r#type,
+            attempts: None,
+            r#type: dto.r#type,
             commands,
+            labels: dto.labels,
+            timeout: dto.timeout,
+            shell: dto.shell,
+            env: env_from_entries(&dto.env),
+            needs: dto.needs,
+            condition: dto.condition,
+            retry: retry_from_columns(dto.retry_max_attempts, dto.retry_backoff, &dto.retry_on),
+            artifacts: dto.artifacts,
+            artifacts_from: dto.artifacts_from,
+            timing: Timing::new(dto.created_at, dto.started_at, dto.finished_at),
         });
     }
 }
 
+/// Convert environment variables to `KEY=VALUE` entries, as they are stored.
+pub fn env_to_entries(env: &BTreeMap<String, String>) -> Vec<String> {
+    env.iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+/// Convert stored `KEY=VALUE` entries back to environment variables.
+pub fn env_from_entries(entries: &[String]) -> BTreeMap<String, String> {
+    entries
+        .iter()
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Convert a retry policy to the columns it is stored in: the maximum number of attempts, the backoff and the failures
+/// retried. An action without retry policy is attempted once.
+pub fn retry_to_columns(retry: Option<&RetryPolicy>) -> (i32, i32, Vec<String>) {
+    match retry {
+        // The parser bounds the attempts and the backoff
+        Some(retry) => (
+            retry.max_attempts as i32,
+            retry.backoff as i32,
+            retry
+                .on
+                .iter()
+                .map(|failure| failure.as_str().to_string())
+                .collect(),
+        ),
+        None => (1, 0, Vec::new()),
+    }
+}
+
+/// Convert the stored columns back to a retry policy, none if the action is attempted once.
+pub fn retry_from_columns(max_attempts: i32, backoff: i32, on: &[String]) -> Option<RetryPolicy> {
+    if max_attempts <= 1 {
+        return None;
+    }
+    Some(RetryPolicy {
+        max_attempts: max_attempts as u32,
+        backoff: backoff.max(0) as u32,
+        on: on
+            .iter()
+            .filter_map(|failure| FailureClass::from_str(failure).ok())
+            .collect(),
+    })
+}
+
 pub struct ActionRepository {
     pool: Arc<PgPool>,
 }
@@ -61,29 +168,34 @@ impl ActionRepository {
         Self { pool }
     }
 
-    pub async fn create(
-        &self,
-        pipeline_id: i64,
-        name: &String,
-        container_uri: &String,
-        r#type: &Type,
-        status: &String,
-    ) -> Result<ActionDTO, sqlx::Error> {
+    /// Insert the action, its id and timestamps being ignored as the database sets them.
+    pub async fn create(&self, action: &ActionDTO) -> Result<ActionDTO, sqlx::Error> {
         // create a nex action in psql
         sqlx::query_as!(
             ActionDTO,
-            r#"INSERT INTO actions (pipeline_id, name, container_uri, type, status) VALUES ($1, $2, $3, $4, $5) RETURNING *"#,
-            pipeline_id,
-            name,
-            container_uri,
-            &r#type.to_string(),
-            status
+            r#"INSERT INTO actions (pipeline_id, name, container_uri, type, status, labels, timeout, shell, env, needs, condition, retry_max_attempts, retry_backoff, retry_on, artifacts, artifacts_from, run_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17) RETURNING *"#,
+            action.pipeline_id,
+            &action.name,
+            &action.container_uri,
+            &action.r#type.to_string(),
+            &action.status,
+            &action.labels,
+            action.timeout,
+            action.shell.as_deref(),
+            &action.env,
+            &action.needs,
+            action.condition.as_deref(),
+            action.retry_max_attempts,
+            action.retry_backoff,
+            &action.retry_on,
+            &action.artifacts,
+            &action.artifacts_from,
+            action.run_id
         )
         .fetch_one(self.pool.as_ref())
         .await
     }
 
-    #[allow(dead_code)]
     pub async fn find_by_id(&self, id: i64) -> Result<ActionDTO, sqlx::Error> {
         sqlx::query_as!(
             ActionDTO,
@@ -94,10 +206,22 @@ impl ActionRepository {
         .await
     }
 
-    pub async fn alter_status(&self, status: &str, id: i64) -> Result<(), sqlx::Error> {
+    /// Update the status, and record when the action started and finished.
+    pub async fn alter_status(
+        &self,
+        status: &str,
+        started: bool,
+        finished: bool,
+        id: i64,
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
-            r#"UPDATE actions SET status = $1 WHERE id = $2"#,
+            r#"UPDATE actions SET status = $1,
+               started_at = CASE WHEN $2 THEN COALESCE(started_at, NOW()) ELSE started_at END,
+               finished_at = CASE WHEN $3 THEN COALESCE(finished_at, NOW()) ELSE finished_at END
+               WHERE id = $4"#,
             status,
+            started,
+            finished,
             id
         )
         .execute(&*self.pool)
@@ -106,7 +230,6 @@ impl ActionRepository {
         Ok(())
     }
 
-    #[allow(dead_code)]
     pub async fn find_by_pipeline_id(
         &self,
         pipeline_id: i64,
@@ -119,4 +242,48 @@ impl ActionRepository {
         .fetch_all(&*self.pool)
         .await
     }
+
+    pub async fn find_by_run_id(&self, run_id: i64) -> Result<Vec<ActionDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            ActionDTO,
+            r#"SELECT * FROM actions WHERE run_id = $1 ORDER BY id"#,
+            run_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// Record an attempt of the action once it is over.
+    pub async fn create_attempt(
+        &self,
+        action_id: i64,
+        number: i32,
+        status: &str,
+        exit_code: Option<i32>,
+        started_at: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"INSERT INTO action_attempts (action_id, number, status, exit_code, started_at) VALUES ($1, $2, $3, $4, $5)"#,
+            action_id,
+            number,
+            status,
+            exit_code,
+            started_at
+        )
+        .execute(&*self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The attempts of the action that are over, in their order.
+    pub async fn find_attempts(&self, action_id: i64) -> Result<Vec<Attempt>, sqlx::Error> {
+        sqlx::query_as!(
+            Attempt,
+            r#"SELECT number, status, exit_code, started_at, finished_at FROM action_attempts WHERE action_id = $1 ORDER BY number"#,
+            action_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
 }
@@ -2,6 +2,7 @@ use std::{fmt, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use time::OffsetDateTime;
 use tracing::info;
 
 use crate::{
@@ -9,7 +10,7 @@ use crate::{
     parser::pipe_parser::Type,
 };
 
-use super::action_repository::{Action, ActionRepository};
+use super::action_repository::{Action, ActionRepository, Attempt};
 
 #[derive(Debug)]
 pub enum ActionCreationError {
@@ -36,10 +37,36 @@ impl std::error::Error for ActionCreationError {
 pub struct ActionDTO {
     pub id: Option<i64>,
     pub pipeline_id: i64,
+    pub run_id: i64,
     pub name: String,
     pub container_uri: String,
     pub r#type: Type,
     pub status: String,
+    pub labels: Vec<String>,
+    pub timeout: Option<i32>,
+    pub shell: Option<String>,
+    /// Environment variables, as `KEY=VALUE`
+    pub env: Vec<String>,
+    /// Names of the actions that must be over first
+    pub needs: Vec<String>,
+    /// Expression telling whether the action runs once the actions it needs are over
+    pub condition: Option<String>,
+    /// Number of times the action runs at most, 1 without retry policy
+    pub retry_max_attempts: i32,
+    /// Seconds waited before the second attempt, doubled before each next one
+    pub retry_backoff: i32,
+    /// Failures after which the action runs again
+    pub retry_on: Vec<String>,
+    /// Paths or shell glob patterns of the files of the repository kept once the commands succeeded
+    pub artifacts: Vec<String>,
+    /// Names of the actions it needs whose artifacts are unpacked in the repository before the commands run
+    pub artifacts_from: Vec<String>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub finished_at: Option<OffsetDateTime>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -68,20 +95,10 @@ impl ActionService {
         action_dto: &ActionDTO,
         commands: Vec<String>,
     ) -> Result<Action, ActionCreationError> {
-        let action_dto = self
-            .repository
-            .create(
-                action_dto.pipeline_id,
-                &action_dto.name,
-                &action_dto.container_uri,
-                &action_dto.r#type,
-                &action_dto.status,
-            )
-            .await
-            .map_err(|e| {
-                info!("Error creating action: {:?}", e);
-                return ActionCreationError::DatabaseInsertionError;
-            })?;
+        let action_dto = self.repository.create(action_dto).await.map_err(|e| {
+            info!("Error creating action: {:?}", e);
+            return ActionCreationError::DatabaseInsertionError;
+        })?;
 
         for command in &commands {
             self.command_service
@@ -92,21 +109,61 @@ impl ActionService {
                 })?;
         }
 
-        Action::new(
-            action_dto.id.unwrap(),
-            action_dto.pipeline_id,
-            action_dto.name.clone(),
-            action_dto.container_uri.clone(),
-            commands,
-            action_dto.r#type.clone(),
-            action_dto.status.clone(),
-        )
-        .map_err(|_| {
+        Action::new(action_dto, commands).map_err(|_| {
             return ActionCreationError::WrongTypeError;
         })
     }
 
+    /// Update the status of the action. It is started once scheduled, and finished once it is over.
     pub async fn update_status(&self, id: i64, status: &ActionStatus) -> Result<(), sqlx::Error> {
-        self.repository.alter_status(status.as_str_name(), id).await
+        let started = matches!(status, ActionStatus::Scheduled | ActionStatus::Running);
+        let finished = is_finished(status.as_str_name());
+        self.repository
+            .alter_status(status.as_str_name(), started, finished, id)
+            .await
+    }
+
+    /// Record an attempt of the action once it is over.
+    pub async fn record_attempt(
+        &self,
+        id: i64,
+        number: i32,
+        status: &ActionStatus,
+        exit_code: Option<i32>,
+        started_at: OffsetDateTime,
+    ) -> Result<(), sqlx::Error> {
+        self.repository
+            .create_attempt(id, number, status.as_str_name(), exit_code, started_at)
+            .await
+    }
+
+    pub async fn find_attempts(&self, id: i64) -> Result<Vec<Attempt>, sqlx::Error> {
+        self.repository.find_attempts(id).await
     }
+
+    pub async fn find(&self, id: i64) -> Result<ActionDTO, sqlx::Error> {
+        self.repository.find_by_id(id).await
+    }
+
+    pub async fn find_by_pipeline_id(&self, pipeline_id: i64) -> Result<Vec<ActionDTO>, sqlx::Error> {
+        self.repository.find_by_pipeline_id(pipeline_id).await
+    }
+
+    pub async fn find_by_run_id(&self, run_id: i64) -> Result<Vec<ActionDTO>, sqlx::Error> {
+        self.repository.find_by_run_id(run_id).await
+    }
+}
+
+/// Whether an action with this status is over, so that it can no longer change.
+pub fn is_finished(status: &str) -> bool {
+    matches!(
+        ActionStatus::from_str_name(status),
+        Some(
+            ActionStatus::Completed
+                | ActionStatus::Error
+                | ActionStatus::Cancelled
+                | ActionStatus::TimedOut
+                | ActionStatus::Skipped
+        )
+    )
 }
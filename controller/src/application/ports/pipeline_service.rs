@@ -1,4 +1,5 @@
-use crate::domain::entities::pipeline::{ManifestPipeline, Pipeline, PipelineError};
+use crate::domain::entities::pipeline::{Pipeline, PipelineError};
+use crate::parser::pipe_parser::ManifestPipeline;
 use async_trait::async_trait;
 
 #[async_trait]
@@ -7,10 +7,11 @@ use crate::{
     domain::{
         entities::{
             action::{ActionStatus, ActionType},
-            pipeline::{ManifestPipeline, Pipeline, PipelineError},
+            pipeline::{Pipeline, PipelineError},
         },
         repositories::pipeline_repository::PipelineRepository,
     },
+    parser::pipe_parser::ManifestPipeline,
 };
 
 pub struct PipelineServiceImpl {
@@ -55,16 +56,16 @@ impl PipelineService for PipelineServiceImpl {
     ) -> Result<Pipeline, PipelineError> {
         let pipeline = self.create_pipeline(repository_url, manifest.name).await?;
 
-        for (action_name, action_data) in manifest.actions.actions.iter() {
+        for action in manifest.actions {
             let _action = self
                 .action_service
                 .create(
                     pipeline.id,
-                    action_name.to_owned(),
-                    action_data.configuration.container.clone(),
+                    action.name,
+                    action.configuration_version,
                     ActionType::Container,
                     ActionStatus::Pending.to_string(),
-                    Some(action_data.commands.clone()),
+                    Some(action.commands),
                 )
                 .await;
         }
@@ -0,0 +1,109 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::{fs, io::AsyncReadExt};
+use tracing::{error, info};
+
+use super::artifact_service::{ArtifactService, ArtifactServiceError};
+
+/// Size of the parts in which an archive is sent.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+struct ActionByIDQuery {
+    id: i64,
+    action_id: i64,
+}
+
+#[derive(Deserialize)]
+struct ArtifactByIDQuery {
+    id: i64,
+    action_id: i64,
+    artifact_id: i64,
+}
+
+#[get("/pipeline/{id}/actions/{action_id}/artifacts")]
+pub async fn get_artifacts(
+    path: web::Path<ActionByIDQuery>,
+    artifact_service: web::Data<Arc<ArtifactService>>,
+) -> impl Responder {
+    match artifact_service.list(path.id, path.action_id).await {
+        Ok(artifacts) => HttpResponse::Ok().json(artifacts),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Download the tar archive of an artifact.
+#[get("/pipeline/{id}/actions/{action_id}/artifacts/{artifact_id}")]
+pub async fn download_artifact(
+    path: web::Path<ArtifactByIDQuery>,
+    artifact_service: web::Data<Arc<ArtifactService>>,
+) -> impl Responder {
+    info!(
+        "Downloading artifact {} of action {} of pipeline {}",
+        path.artifact_id, path.action_id, path.id
+    );
+    let (artifact, file) = match artifact_service
+        .find(path.id, path.action_id, path.artifact_id)
+        .await
+    {
+        Ok(found) => found,
+        Err(e) => return error_response(e),
+    };
+    let mut file = match fs::File::open(&file).await {
+        Ok(file) => file,
+        Err(e) => {
+            error!("Error while opening artifact {}: {:?}", artifact.id, e);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let artifact_id = artifact.id;
+    let body = async_stream::stream! {
+        loop {
+            let mut data = vec![0; CHUNK_SIZE];
+            match file.read(&mut data).await {
+                Ok(0) => break,
+                Ok(read) => {
+                    data.truncate(read);
+                    yield Ok::<_, actix_web::Error>(web::Bytes::from(data));
+                }
+                Err(e) => {
+                    error!("Error while reading artifact {}: {:?}", artifact_id, e);
+                    yield Err(actix_web::error::ErrorInternalServerError(e));
+                    break;
+                }
+            }
+        }
+    };
+    HttpResponse::Ok()
+        .content_type("application/x-tar")
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", archive_name(&artifact.path)),
+        ))
+        .streaming(body)
+}
+
+/// Name of the archive of an artifact, after the last component of its path, e.g. `app.tar` for `target/release/app`.
+pub fn archive_name(path: &str) -> String {
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty() && *name != ".")
+        .unwrap_or("artifact");
+    let name: String = name
+        .chars()
+        .map(|c| if c == '"' || c.is_control() { '_' } else { c })
+        .collect();
+    format!("{}.tar", name)
+}
+
+fn error_response(error: ArtifactServiceError) -> HttpResponse {
+    match error {
+        ArtifactServiceError::ActionNotFound | ArtifactServiceError::ArtifactNotFound => {
+            HttpResponse::NotFound().finish()
+        }
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+use super::Artifact;
+
+pub struct ArtifactRepository {
+    pool: Arc<PgPool>,
+}
+
+impl ArtifactRepository {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        action_id: i64,
+        path: &str,
+        size: i64,
+    ) -> Result<Artifact, sqlx::Error> {
+        sqlx::query_as!(
+            Artifact,
+            r#"INSERT INTO artifacts (action_id, path, size) VALUES ($1, $2, $3) RETURNING id, action_id, path, size, created_at"#,
+            action_id,
+            path,
+            size
+        )
+        .fetch_one(&*self.pool)
+        .await
+    }
+
+    /// The artifacts of the action, in the order they were stored.
+    pub async fn find_by_action_id(&self, action_id: i64) -> Result<Vec<Artifact>, sqlx::Error> {
+        sqlx::query_as!(
+            Artifact,
+            r#"SELECT id, action_id, path, size, created_at FROM artifacts WHERE action_id = $1 ORDER BY id"#,
+            action_id
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    /// The artifacts of the actions of the run with the given names, in the order they were stored.
+    pub async fn find_by_run_id_and_action_names(
+        &self,
+        run_id: i64,
+        names: &[String],
+    ) -> Result<Vec<Artifact>, sqlx::Error> {
+        sqlx::query_as!(
+            Artifact,
+            r#"SELECT artifacts.id, artifacts.action_id, artifacts.path, artifacts.size, artifacts.created_at FROM artifacts JOIN actions ON actions.id = artifacts.action_id WHERE actions.run_id = $1 AND actions.name = ANY($2) ORDER BY artifacts.id"#,
+            run_id,
+            names
+        )
+        .fetch_all(&*self.pool)
+        .await
+    }
+
+    pub async fn find(&self, action_id: i64, id: i64) -> Result<Artifact, sqlx::Error> {
+        sqlx::query_as!(
+            Artifact,
+            r#"SELECT id, action_id, path, size, created_at FROM artifacts WHERE action_id = $1 AND id = $2"#,
+            action_id,
+            id
+        )
+        .fetch_one(&*self.pool)
+        .await
+    }
+}
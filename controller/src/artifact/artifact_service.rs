@@ -0,0 +1,252 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::{Stream, StreamExt};
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::error;
+
+use crate::{
+    action::{action_repository::Action, action_service::ActionService},
+    grpc_scheduler::{ArtifactChunk, ArtifactReference},
+};
+
+use super::{artifact_repository::ArtifactRepository, Artifact};
+
+#[derive(Debug)]
+pub enum ArtifactServiceError {
+    ActionNotFound,
+    ArtifactNotFound,
+    DatabaseError,
+    StorageError,
+    TransferError,
+}
+
+/// An archive received from the scheduler, before it is recorded as an artifact.
+#[derive(Debug, PartialEq)]
+pub struct ReceivedArchive {
+    pub path: String,
+    pub file: PathBuf,
+    pub size: i64,
+}
+
+pub struct ArtifactService {
+    repository: Arc<ArtifactRepository>,
+    action_service: Arc<ActionService>,
+    /// Where the archives are stored, in a directory per action
+    directory: PathBuf,
+    /// URL the agents reach the controller at, to download the artifacts
+    public_url: String,
+}
+
+impl ArtifactService {
+    pub fn new(
+        repository: Arc<ArtifactRepository>,
+        action_service: Arc<ActionService>,
+        directory: PathBuf,
+        public_url: &str,
+    ) -> Self {
+        Self {
+            repository,
+            action_service,
+            directory,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn action_directory(&self, action_id: i64) -> PathBuf {
+        self.directory.join(action_id.to_string())
+    }
+
+    /// Where the archive of the artifact is stored.
+    pub fn file(&self, artifact: &Artifact) -> PathBuf {
+        self.action_directory(artifact.action_id)
+            .join(format!("{}.tar", artifact.id))
+    }
+
+    /// Store the artifacts of an action as the scheduler sends them. Nothing is kept if the transfer fails.
+    pub async fn store<S>(
+        &self,
+        action_id: i64,
+        chunks: S,
+    ) -> Result<Vec<Artifact>, ArtifactServiceError>
+    where
+        S: Stream<Item = Result<ArtifactChunk, tonic::Status>> + Unpin,
+    {
+        let directory = self.action_directory(action_id);
+        let archives = match receive_archives(&directory, chunks).await {
+            Ok(archives) => archives,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&directory).await;
+                return Err(e);
+            }
+        };
+        let mut artifacts = Vec::new();
+        for archive in archives {
+            let artifact = self
+                .repository
+                .create(action_id, &archive.path, archive.size)
+                .await
+                .map_err(|e| {
+                    error!(
+                        "Error while storing artifact of action {}: {:?}",
+                        action_id, e
+                    );
+                    ArtifactServiceError::DatabaseError
+                })?;
+            fs::rename(&archive.file, self.file(&artifact))
+                .await
+                .map_err(storage_error)?;
+            artifacts.push(artifact);
+        }
+        Ok(artifacts)
+    }
+
+    /// The artifacts of an action of the pipeline.
+    pub async fn list(
+        &self,
+        pipeline_id: i64,
+        action_id: i64,
+    ) -> Result<Vec<Artifact>, ArtifactServiceError> {
+        self.check_action(pipeline_id, action_id).await?;
+        self.repository
+            .find_by_action_id(action_id)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Error while fetching artifacts of action {}: {:?}",
+                    action_id, e
+                );
+                ArtifactServiceError::DatabaseError
+            })
+    }
+
+    /// The artifacts the action takes from the actions it needs, along with where the agent downloads them from.
+    pub async fn inputs(
+        &self,
+        action: &Action,
+    ) -> Result<Vec<ArtifactReference>, ArtifactServiceError> {
+        if action.artifacts_from.is_empty() {
+            return Ok(Vec::new());
+        }
+        let artifacts = self
+            .repository
+            .find_by_run_id_and_action_names(action.run_id, &action.artifacts_from)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Error while fetching the artifacts needed by action {}: {:?}",
+                    action.id, e
+                );
+                ArtifactServiceError::DatabaseError
+            })?;
+        Ok(artifacts
+            .into_iter()
+            .map(|artifact| ArtifactReference {
+                url: format!(
+                    "{}/pipeline/{}/actions/{}/artifacts/{}",
+                    self.public_url, action.pipeline_id, artifact.action_id, artifact.id
+                ),
+                path: artifact.path,
+            })
+            .collect())
+    }
+
+    /// An artifact of an action of the pipeline, along with where its archive is stored.
+    pub async fn find(
+        &self,
+        pipeline_id: i64,
+        action_id: i64,
+        artifact_id: i64,
+    ) -> Result<(Artifact, PathBuf), ArtifactServiceError> {
+        self.check_action(pipeline_id, action_id).await?;
+        let artifact = self
+            .repository
+            .find(action_id, artifact_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => ArtifactServiceError::ArtifactNotFound,
+                e => {
+                    error!("Error while fetching artifact {}: {:?}", artifact_id, e);
+                    ArtifactServiceError::DatabaseError
+                }
+            })?;
+        let file = self.file(&artifact);
+        Ok((artifact, file))
+    }
+
+    /// Check the action is one of the pipeline.
+    async fn check_action(
+        &self,
+        pipeline_id: i64,
+        action_id: i64,
+    ) -> Result<(), ArtifactServiceError> {
+        let action = self
+            .action_service
+            .find(action_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => ArtifactServiceError::ActionNotFound,
+                e => {
+                    error!("Error while fetching action {}: {:?}", action_id, e);
+                    ArtifactServiceError::DatabaseError
+                }
+            })?;
+        if action.pipeline_id != pipeline_id {
+            return Err(ArtifactServiceError::ActionNotFound);
+        }
+        Ok(())
+    }
+}
+
+/// Write the archives sent by the scheduler to files of the directory. The chunks of an archive follow each other, so
+/// a chunk with another path starts the next archive.
+pub async fn receive_archives<S>(
+    directory: &Path,
+    mut chunks: S,
+) -> Result<Vec<ReceivedArchive>, ArtifactServiceError>
+where
+    S: Stream<Item = Result<ArtifactChunk, tonic::Status>> + Unpin,
+{
+    fs::create_dir_all(directory).await.map_err(storage_error)?;
+    let mut received = Vec::new();
+    let mut current: Option<(fs::File, ReceivedArchive)> = None;
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|e| {
+            error!("Error while receiving artifacts: {:?}", e);
+            ArtifactServiceError::TransferError
+        })?;
+        if current
+            .as_ref()
+            .is_none_or(|(_, archive)| archive.path != chunk.path)
+        {
+            if let Some((mut file, archive)) = current.take() {
+                file.flush().await.map_err(storage_error)?;
+                received.push(archive);
+            }
+            let path = directory.join(format!("{}.part", received.len()));
+            let file = fs::File::create(&path).await.map_err(storage_error)?;
+            let archive = ReceivedArchive {
+                path: chunk.path.clone(),
+                file: path,
+                size: 0,
+            };
+            current = Some((file, archive));
+        }
+        if let Some((file, archive)) = current.as_mut() {
+            file.write_all(&chunk.data).await.map_err(storage_error)?;
+            archive.size += chunk.data.len() as i64;
+        }
+    }
+    if let Some((mut file, archive)) = current {
+        file.flush().await.map_err(storage_error)?;
+        received.push(archive);
+    }
+    Ok(received)
+}
+
+fn storage_error(e: std::io::Error) -> ArtifactServiceError {
+    error!("Error while writing artifact: {:?}", e);
+    ArtifactServiceError::StorageError
+}
@@ -0,0 +1,19 @@
+pub mod artifact_controller;
+pub mod artifact_repository;
+pub mod artifact_service;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// A file or a directory kept from an action that completed, stored by the controller as a tar archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub id: i64,
+    pub action_id: i64,
+    /// Path of the file or directory in the repository, as matched by the artifacts of the action
+    pub path: String,
+    /// Size of the archive, in bytes
+    pub size: i64,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
@@ -1,12 +1,20 @@
 use actix_web::{get, HttpResponse, Responder};
 use scalar_doc::scalar_actix::ActixDocumentation;
 
+use crate::parser::manifest;
+
 #[get("/openapi")]
 pub async fn openapi() -> impl Responder {
     let open = include_str!("../../../api/openapi/controller/controller.openapi.yaml");
     HttpResponse::Ok().body(open)
 }
 
+/// JSON Schema of the pipeline manifests, e.g. for `# yaml-language-server: $schema=<controller>/manifest/schema`.
+#[get("/manifest/schema")]
+pub async fn manifest_schema() -> impl Responder {
+    HttpResponse::Ok().json(manifest::json_schema())
+}
+
 #[get("/docs")]
 pub async fn doc() -> impl Responder {
     ActixDocumentation::new("SealCI - Open API", "/openapi")
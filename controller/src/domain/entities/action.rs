@@ -41,6 +41,9 @@ pub enum ActionStatus {
     Running,
     Completed,
     Error,
+    Cancelled,
+    TimedOut,
+    Skipped,
 }
 
 impl fmt::Display for ActionStatus {
@@ -50,6 +53,9 @@ impl fmt::Display for ActionStatus {
             ActionStatus::Running => "Scheduled",
             ActionStatus::Completed => "Completed",
             ActionStatus::Error => "Error",
+            ActionStatus::Cancelled => "Cancelled",
+            ActionStatus::TimedOut => "TimedOut",
+            ActionStatus::Skipped => "Skipped",
         };
 
         write!(f, "{}", s)
@@ -66,6 +72,9 @@ impl FromStr for ActionStatus {
             "Running" => Ok(ActionStatus::Running),
             "Completed" => Ok(ActionStatus::Completed),
             "Error" => Ok(ActionStatus::Error),
+            "Cancelled" => Ok(ActionStatus::Cancelled),
+            "TimedOut" => Ok(ActionStatus::TimedOut),
+            "Skipped" => Ok(ActionStatus::Skipped),
             _ => Err(()),
         }
     }
@@ -83,12 +92,26 @@ pub struct ActionRequest {
     pub commands: Vec<String>,
     pub context: ExecutionContext,
     pub repo_url: String,
+    pub timeout: Option<u32>,
+    pub artifacts: Vec<String>,
+    pub input_artifacts: Vec<ArtifactReference>,
+}
+
+/// An artifact of another action, unpacked in the repository before the commands run.
+#[derive(Debug, Clone)]
+pub struct ArtifactReference {
+    pub path: String,
+    pub url: String,
 }
 
 #[derive(Debug, Clone)]
 pub struct ExecutionContext {
     pub r#type: i32,
     pub container_image: Option<String>,
+    pub labels: Vec<String>,
+    pub shell: Option<String>,
+    pub env: std::collections::HashMap<String, String>,
+    pub secrets: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
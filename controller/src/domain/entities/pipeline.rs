@@ -22,29 +22,6 @@ impl Pipeline {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ManifestPipeline {
-    pub name: String,
-    pub actions: ActionsMap,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ActionsMap {
-    #[serde(flatten)]
-    pub actions: std::collections::HashMap<String, ActionManifest>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ActionManifest {
-    pub configuration: Configuration,
-    pub commands: Vec<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Configuration {
-    pub container: String,
-}
-
 #[derive(Debug, Error)]
 pub enum CreatePipelineError {
     #[error("Error while creating pipeline: {0}")]
@@ -8,7 +8,7 @@ use crate::domain::entities::action::{
 use crate::domain::services::scheduler_client::SchedulerClient;
 use crate::grpc_scheduler::controller_client::ControllerClient;
 use crate::grpc_scheduler::{
-    ActionRequest, ActionResponse, ActionResult, ExecutionContext, RunnerType,
+    ActionRequest, ActionResponse, ActionResult, ArtifactReference, ExecutionContext, RunnerType,
 };
 use futures::lock::Mutex;
 use futures::{Stream, StreamExt};
@@ -42,6 +42,9 @@ impl DomainActionStatus {
             1 => DomainActionStatus::Running,
             2 => DomainActionStatus::Completed,
             3 => DomainActionStatus::Error,
+            5 => DomainActionStatus::Cancelled,
+            6 => DomainActionStatus::TimedOut,
+            7 => DomainActionStatus::Skipped,
             _ => DomainActionStatus::Error, // Valeur par défaut en cas d'invalidité
         }
     }
@@ -54,9 +57,23 @@ impl From<DomainActionRequest> for ActionRequest {
             context: Some(ExecutionContext {
                 r#type: RunnerType::Docker as i32,
                 container_image: domain_request.context.container_image.clone(),
+                labels: domain_request.context.labels.clone(),
+                shell: domain_request.context.shell.clone(),
+                env: domain_request.context.env.clone(),
+                secrets: domain_request.context.secrets.clone(),
             }),
             commands: domain_request.commands.clone(),
             repo_url: domain_request.repo_url.clone(),
+            timeout: domain_request.timeout,
+            artifacts: domain_request.artifacts.clone(),
+            input_artifacts: domain_request
+                .input_artifacts
+                .iter()
+                .map(|artifact| ArtifactReference {
+                    path: artifact.path.clone(),
+                    url: artifact.url.clone(),
+                })
+                .collect(),
         }
     }
 }
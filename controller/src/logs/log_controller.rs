@@ -0,0 +1,63 @@
+use actix_web::{
+    get,
+    web::{self, Bytes},
+    HttpRequest, HttpResponse, Responder,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use super::{
+    log_hub::LogEvent,
+    log_service::{LogService, LogServiceError},
+};
+
+#[derive(Deserialize)]
+struct ActionByIDQuery {
+    id: i64,
+    action_id: i64,
+}
+
+/// Stream the logs of an action as Server-Sent Events. A client reconnecting with `Last-Event-ID` only receives the
+/// lines it missed.
+#[get("/pipeline/{id}/actions/{action_id}/logs/stream")]
+pub async fn stream_logs(
+    path: web::Path<ActionByIDQuery>,
+    request: HttpRequest,
+    log_service: web::Data<Arc<LogService>>,
+) -> impl Responder {
+    let last_id = request
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    info!(
+        "Streaming logs of action {} of pipeline {} after line {}",
+        path.action_id, path.id, last_id
+    );
+
+    match log_service.follow(path.id, path.action_id, last_id).await {
+        Ok(events) => HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(("Cache-Control", "no-cache"))
+            .streaming(events.map(|event| Ok::<_, actix_web::Error>(to_server_sent_event(event)))),
+        Err(LogServiceError::ActionNotFound) => HttpResponse::NotFound().finish(),
+        Err(LogServiceError::DatabaseError) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+fn to_server_sent_event(event: LogEvent) -> Bytes {
+    match event {
+        // The line is sent as JSON, which escapes its line breaks, so it fits in a single data field
+        LogEvent::Line(log) => match serde_json::to_string(&log) {
+            Ok(data) => Bytes::from(format!("id: {}\nevent: log\ndata: {}\n\n", log.id, data)),
+            Err(e) => {
+                error!("Error while serializing log {}: {:?}", log.id, e);
+                Bytes::new()
+            }
+        },
+        LogEvent::End => Bytes::from_static(b"event: end\ndata:\n\n"),
+    }
+}
@@ -0,0 +1,65 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tokio::sync::broadcast;
+
+use super::Log;
+
+/// Lines kept for a client that is slower than the action. A client lagging further behind reads the missed lines
+/// back from the database.
+const CAPACITY: usize = 256;
+
+/// An event of the log stream of an action.
+#[derive(Debug, Clone)]
+pub enum LogEvent {
+    /// A log line, once it is stored
+    Line(Log),
+    /// The action is over, no more lines will come
+    End,
+}
+
+/// Broadcasts the log lines of the running actions to the clients following them. A channel only exists while an
+/// action is followed, so lines of actions nobody follows are not kept in memory.
+#[derive(Default)]
+pub struct LogHub {
+    channels: Mutex<HashMap<i64, broadcast::Sender<LogEvent>>>,
+}
+
+impl LogHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow the lines of the action published from now on.
+    pub fn subscribe(&self, action_id: i64) -> broadcast::Receiver<LogEvent> {
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(action_id)
+            .or_insert_with(|| broadcast::channel(CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, action_id: i64, log: Log) {
+        if let Some(sender) = self.channels.lock().unwrap().get(&action_id) {
+            // Fails only when every client is gone, they no longer need the line
+            let _ = sender.send(LogEvent::Line(log));
+        }
+    }
+
+    /// End the streams of the action, once it is over.
+    pub fn close(&self, action_id: i64) {
+        if let Some(sender) = self.channels.lock().unwrap().remove(&action_id) {
+            let _ = sender.send(LogEvent::End);
+        }
+    }
+
+    /// Drop the channel of the action if nobody follows it anymore.
+    pub fn release(&self, action_id: i64) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&action_id) {
+            if sender.receiver_count() == 0 {
+                channels.remove(&action_id);
+            }
+        }
+    }
+}
@@ -1,16 +1,35 @@
 use std::sync::Arc;
 
 use sqlx::PgPool;
+use time::OffsetDateTime;
 
-use super::Log;
+use super::{stream_name, Log, LogLine};
 
 #[derive(Debug, Clone)]
 pub struct LogDTO {
-    #[allow(dead_code)]
     pub id: Option<i64>,
     #[allow(dead_code)]
     pub action_id: i64,
     pub data: String,
+    pub sequence: Option<i64>,
+    pub timestamp: OffsetDateTime,
+    pub step: Option<i32>,
+    pub stream: String,
+    pub attempt: i32,
+}
+
+impl From<LogDTO> for Log {
+    fn from(log: LogDTO) -> Self {
+        Log {
+            id: log.id.unwrap_or_default(),
+            sequence: log.sequence,
+            timestamp: log.timestamp,
+            step: log.step,
+            stream: log.stream,
+            message: log.data,
+            attempt: log.attempt,
+        }
+    }
 }
 
 pub struct LogRepository {
@@ -22,30 +41,92 @@ impl LogRepository {
         Self { pool }
     }
 
-    pub async fn create(&self, action_id: i64, data: &String) -> Result<Log, sqlx::Error> {
+    /// Store a line about an attempt of the action, written by the controller.
+    pub async fn create(
+        &self,
+        action_id: i64,
+        attempt: i32,
+        data: &str,
+    ) -> Result<Log, sqlx::Error> {
+        self.create_line(action_id, attempt, &LogLine::system(data.to_string()))
+            .await
+    }
+
+    pub async fn create_line(
+        &self,
+        action_id: i64,
+        attempt: i32,
+        line: &LogLine,
+    ) -> Result<Log, sqlx::Error> {
         let log_row = sqlx::query_as!(
             LogDTO,
-            r#"INSERT INTO logs (action_id, data) VALUES ($1, $2) RETURNING *"#,
+            r#"INSERT INTO logs (action_id, attempt, data, sequence, timestamp, step, stream) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *"#,
             action_id,
-            data
+            attempt,
+            line.message,
+            line.sequence,
+            line.timestamp,
+            line.step,
+            stream_name(line.stream)
         )
         .fetch_one(self.pool.as_ref())
         .await?;
 
-        Ok(Log {
-            message: log_row.data,
-        })
+        Ok(log_row.into())
+    }
+
+    /// Store the lines of an attempt of the action in a single insert. The lines are returned in the order they were
+    /// given.
+    pub async fn create_lines(
+        &self,
+        action_id: i64,
+        attempt: i32,
+        lines: &[LogLine],
+    ) -> Result<Vec<Log>, sqlx::Error> {
+        let mut messages = Vec::with_capacity(lines.len());
+        let mut sequences = Vec::with_capacity(lines.len());
+        let mut timestamps = Vec::with_capacity(lines.len());
+        let mut steps = Vec::with_capacity(lines.len());
+        let mut streams = Vec::with_capacity(lines.len());
+        for line in lines {
+            messages.push(line.message.clone());
+            sequences.push(line.sequence);
+            timestamps.push(line.timestamp);
+            steps.push(line.step);
+            streams.push(stream_name(line.stream).to_string());
+        }
+
+        // The ids are given in the order of the arrays, so sorting by id restores the order of the lines
+        let mut log_rows = sqlx::query_as!(
+            LogDTO,
+            r#"INSERT INTO logs (action_id, attempt, data, sequence, timestamp, step, stream)
+            SELECT $1, $2, * FROM UNNEST($3::TEXT[], $4::BIGINT[], $5::TIMESTAMPTZ[], $6::INTEGER[], $7::VARCHAR[])
+            RETURNING *"#,
+            action_id,
+            attempt,
+            &messages,
+            &sequences as &[Option<i64>],
+            &timestamps,
+            &steps as &[Option<i32>],
+            &streams
+        )
+        .fetch_all(self.pool.as_ref())
+        .await?;
+        log_rows.sort_by_key(|log| log.id);
+
+        Ok(log_rows.into_iter().map(Log::from).collect())
     }
 
+    /// The lines of the action, in the order they were received.
     pub async fn find_by_action_id(&self, action_id: i64) -> Result<Vec<Log>, sqlx::Error> {
         let logs = sqlx::query_as!(
             LogDTO,
-            r#"SELECT * FROM logs WHERE action_id = $1"#,
+            r#"SELECT * FROM logs WHERE action_id = $1 ORDER BY id"#,
             action_id
         )
         .fetch_all(self.pool.as_ref())
         .await?;
 
-        Ok(logs.into_iter().map(|log| Log { message: log.data }).collect())
+        Ok(logs.into_iter().map(Log::from).collect())
     }
 }
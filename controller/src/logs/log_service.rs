@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use futures::Stream;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
+
+use crate::action::action_service::{is_finished, ActionService};
+
+use super::{
+    log_hub::{LogEvent, LogHub},
+    log_repository::LogRepository,
+};
+
+#[derive(Debug)]
+pub enum LogServiceError {
+    ActionNotFound,
+    DatabaseError,
+}
+
+pub struct LogService {
+    repository: Arc<LogRepository>,
+    hub: Arc<LogHub>,
+    action_service: Arc<ActionService>,
+}
+
+impl LogService {
+    pub fn new(
+        repository: Arc<LogRepository>,
+        hub: Arc<LogHub>,
+        action_service: Arc<ActionService>,
+    ) -> Self {
+        Self {
+            repository,
+            hub,
+            action_service,
+        }
+    }
+
+    /// Follow the logs of an action: the lines stored after `last_id` are replayed, then the new lines are streamed
+    /// as they come, until the action is over.
+    pub async fn follow(
+        &self,
+        pipeline_id: i64,
+        action_id: i64,
+        last_id: i64,
+    ) -> Result<impl Stream<Item = LogEvent>, LogServiceError> {
+        // Subscribed before the stored lines are read, so that no line is missed in between
+        let mut receiver = self.hub.subscribe(action_id);
+        let action = self.action_service.find(action_id).await.map_err(|e| {
+            self.hub.release(action_id);
+            match e {
+                sqlx::Error::RowNotFound => LogServiceError::ActionNotFound,
+                e => {
+                    error!("Error while fetching action {}: {:?}", action_id, e);
+                    LogServiceError::DatabaseError
+                }
+            }
+        })?;
+        if action.pipeline_id != pipeline_id {
+            self.hub.release(action_id);
+            return Err(LogServiceError::ActionNotFound);
+        }
+        let finished = is_finished(&action.status);
+        let stored = self
+            .repository
+            .find_by_action_id(action_id)
+            .await
+            .map_err(|e| {
+                error!("Error while fetching logs of action {}: {:?}", action_id, e);
+                LogServiceError::DatabaseError
+            })?;
+
+        let repository = Arc::clone(&self.repository);
+        let hub = Arc::clone(&self.hub);
+        Ok(async_stream::stream! {
+            let mut last_id = last_id;
+            for log in stored {
+                if log.id > last_id {
+                    last_id = log.id;
+                    yield LogEvent::Line(log);
+                }
+            }
+            if finished {
+                drop(receiver);
+                hub.release(action_id);
+                yield LogEvent::End;
+                return;
+            }
+
+            loop {
+                match receiver.recv().await {
+                    // The replayed lines may be published again
+                    Ok(LogEvent::Line(log)) if log.id <= last_id => {}
+                    Ok(LogEvent::Line(log)) => {
+                        last_id = log.id;
+                        yield LogEvent::Line(log);
+                    }
+                    Ok(LogEvent::End) | Err(RecvError::Closed) => {
+                        yield LogEvent::End;
+                        break;
+                    }
+                    // This client missed lines, they are read back from the database
+                    Err(RecvError::Lagged(_)) => match repository.find_by_action_id(action_id).await {
+                        Ok(logs) => {
+                            for log in logs {
+                                if log.id > last_id {
+                                    last_id = log.id;
+                                    yield LogEvent::Line(log);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!("Error while fetching logs of action {}: {:?}", action_id, e);
+                            break;
+                        }
+                    },
+                }
+            }
+        })
+    }
+}
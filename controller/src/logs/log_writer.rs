@@ -0,0 +1,76 @@
+use std::{future, sync::Arc};
+
+use tokio::time::{self, Duration, Instant};
+
+use super::{log_hub::LogHub, log_repository::LogRepository, LogLine};
+
+/// Lines stored in a single insert, at most
+const BATCH_SIZE: usize = 1000;
+/// Time a line waits before it is stored, at most, so that the followers of the action still see it live
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Buffers the log lines of an attempt of an action to store them in batches. The lines are published to the followers of the
+/// action once stored, since their ids are given by the database.
+pub struct LogWriter {
+    repository: Arc<LogRepository>,
+    hub: Arc<LogHub>,
+    action_id: i64,
+    attempt: i32,
+    buffer: Vec<LogLine>,
+    /// When the oldest buffered line must be stored
+    deadline: Option<Instant>,
+}
+
+impl LogWriter {
+    pub fn new(
+        repository: Arc<LogRepository>,
+        hub: Arc<LogHub>,
+        action_id: i64,
+        attempt: i32,
+    ) -> Self {
+        Self {
+            repository,
+            hub,
+            action_id,
+            attempt,
+            buffer: Vec::with_capacity(BATCH_SIZE),
+            deadline: None,
+        }
+    }
+
+    /// Buffer a line, storing the buffered lines once a batch is full.
+    pub async fn write(&mut self, line: LogLine) -> Result<(), sqlx::Error> {
+        self.deadline
+            .get_or_insert_with(|| Instant::now() + FLUSH_INTERVAL);
+        self.buffer.push(line);
+        if self.buffer.len() >= BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Resolves once the buffered lines waited long enough, never while there are none.
+    pub async fn deadline(&self) {
+        match self.deadline {
+            Some(deadline) => time::sleep_until(deadline).await,
+            None => future::pending().await,
+        }
+    }
+
+    /// Store the buffered lines.
+    pub async fn flush(&mut self) -> Result<(), sqlx::Error> {
+        self.deadline = None;
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let lines = std::mem::replace(&mut self.buffer, Vec::with_capacity(BATCH_SIZE));
+        for log in self
+            .repository
+            .create_lines(self.action_id, self.attempt, &lines)
+            .await?
+        {
+            self.hub.publish(self.action_id, log);
+        }
+        Ok(())
+    }
+}
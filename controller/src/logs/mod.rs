@@ -1,5 +1,61 @@
+pub mod log_controller;
+pub mod log_hub;
 pub mod log_repository;
+pub mod log_service;
+pub mod log_writer;
 
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::grpc_scheduler::LogStream;
+
+/// A line of the logs of an action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Log {
+    pub id: i64,
+    /// Position of the line among the lines sent by the agent for the action, starting at 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<i64>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    /// Index of the command that wrote the line, none for the lines about the whole action
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step: Option<i32>,
+    /// `stdout` or `stderr` for the output of a command, `system` for the lines about the action
+    pub stream: String,
+    pub message: String,
+    /// Number of the attempt of the action that wrote the line, starting at 1
+    pub attempt: i32,
+}
+
+/// A line to store, before it gets an id.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub sequence: Option<i64>,
+    pub timestamp: OffsetDateTime,
+    pub step: Option<i32>,
+    pub stream: LogStream,
     pub message: String,
 }
+
+impl LogLine {
+    /// A line about the action, written by the controller.
+    pub fn system(message: String) -> Self {
+        Self {
+            sequence: None,
+            timestamp: OffsetDateTime::now_utc(),
+            step: None,
+            stream: LogStream::System,
+            message,
+        }
+    }
+}
+
+/// The name under which the lines of the stream are stored.
+pub fn stream_name(stream: LogStream) -> &'static str {
+    match stream {
+        LogStream::System => "system",
+        LogStream::Stdout => "stdout",
+        LogStream::Stderr => "stderr",
+    }
+}
@@ -1,7 +1,10 @@
 use action::action_service::ActionService;
+use artifact::{
+    artifact_controller, artifact_repository::ArtifactRepository, artifact_service::ArtifactService,
+};
 use clap::Parser;
 use command::command_service::CommandService;
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 use tokio::sync::Mutex;
 
 use crate::database::database::Database;
@@ -9,7 +12,9 @@ use actix_cors::Cors;
 use actix_web::{web::Data, App, HttpServer};
 use dotenv::dotenv;
 use parser::pipe_parser::PipeParser;
+use logs::{log_controller, log_hub::LogHub, log_repository::LogRepository, log_service::LogService};
 use pipeline::pipeline_controller;
+use secret::{secret_cipher::SecretCipher, secret_controller, secret_service::SecretService};
 use tracing::info;
 
 pub mod grpc_scheduler {
@@ -17,6 +22,7 @@ pub mod grpc_scheduler {
 }
 
 mod action;
+mod artifact;
 mod command;
 mod database;
 mod docs;
@@ -26,8 +32,11 @@ mod infrastructure;
 mod logs;
 pub mod parser;
 mod pipeline;
+mod run;
 pub mod scheduler;
+mod secret;
 mod tests;
+mod timing;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -39,6 +48,18 @@ struct Args {
 
     #[clap(env, long)]
     pub grpc: String,
+
+    /// Master key encrypting the secrets, as 32 hex encoded bytes
+    #[clap(env, long)]
+    pub secrets_key: String,
+
+    /// Directory where the artifacts of the actions are stored
+    #[clap(env, long, default_value = "artifacts")]
+    pub artifacts_dir: PathBuf,
+
+    /// URL the agents reach the controller at, to download the artifacts of other actions, `http://<HTTP>` if absent
+    #[clap(env, long)]
+    pub public_url: Option<String>,
 }
 
 #[actix_web::main]
@@ -46,11 +67,19 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     let args = Args::parse();
 
-    println!("${:?}", args);
+    let secret_cipher = SecretCipher::new(&args.secrets_key).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "SECRETS_KEY must be 32 hex encoded bytes, e.g. generated with `openssl rand -hex 32`",
+        )
+    })?;
     let database = Database::new(&args.database_url).await;
 
     let pool = Arc::new(database.pool);
 
+    let public_url = args
+        .public_url
+        .unwrap_or_else(|| format!("http://{}", args.http));
     let addr_in = args.http;
     let grpc_scheduler = args.grpc;
 
@@ -69,10 +98,31 @@ async fn main() -> std::io::Result<()> {
         Arc::clone(&command_service),
     ));
 
+    let secret_service = Arc::new(SecretService::new(Arc::clone(&pool), secret_cipher));
+
+    let log_repository = Arc::new(LogRepository::new(Arc::clone(&pool)));
+    let log_hub = Arc::new(LogHub::new());
+    let log_service = Arc::new(LogService::new(
+        Arc::clone(&log_repository),
+        Arc::clone(&log_hub),
+        Arc::clone(&action_service),
+    ));
+
+    let artifact_service = Arc::new(ArtifactService::new(
+        Arc::new(ArtifactRepository::new(Arc::clone(&pool))),
+        Arc::clone(&action_service),
+        args.artifacts_dir,
+        &public_url,
+    ));
+
     let scheduler_service = Arc::new(scheduler::SchedulerService::new(
         client.clone(),
-        Arc::new(logs::log_repository::LogRepository::new(Arc::clone(&pool))),
+        log_repository,
         Arc::clone(&action_service),
+        Arc::clone(&secret_service),
+        Arc::new(run::run_repository::RunRepository::new(Arc::clone(&pool))),
+        Arc::clone(&log_hub),
+        Arc::clone(&artifact_service),
     ));
 
     let parser_service = Arc::new(PipeParser {});
@@ -82,6 +132,7 @@ async fn main() -> std::io::Result<()> {
         parser_service.clone(),
         Arc::clone(&pool),
         Arc::clone(&action_service),
+        log_hub,
     ));
 
     info!("Listenning on {}", addr_in);
@@ -98,11 +149,28 @@ async fn main() -> std::io::Result<()> {
             .wrap(actix_web::middleware::Logger::default())
             .app_data(Data::new(pipeline_service.clone())) // TODO: replace this implementation by the real parser
             .app_data(Data::new(Arc::clone(&action_service)))
+            .app_data(Data::new(Arc::clone(&secret_service)))
+            .app_data(Data::new(Arc::clone(&log_service)))
+            .app_data(Data::new(Arc::clone(&artifact_service)))
             .service(pipeline_controller::create_pipeline)
+            .service(pipeline_controller::validate_pipeline)
             .service(pipeline_controller::get_pipelines)
             .service(pipeline_controller::get_pipeline)
+            .service(pipeline_controller::cancel_pipeline)
+            .service(pipeline_controller::cancel_action)
+            .service(pipeline_controller::get_runs)
+            .service(pipeline_controller::get_run)
+            .service(pipeline_controller::rerun_pipeline)
+            .service(log_controller::stream_logs)
+            .service(artifact_controller::get_artifacts)
+            .service(artifact_controller::download_artifact)
+            .service(secret_controller::get_secrets)
+            .service(secret_controller::create_secret)
+            .service(secret_controller::update_secret)
+            .service(secret_controller::delete_secret)
             .service(docs::doc)
             .service(docs::openapi)
+            .service(docs::manifest_schema)
             .route(
                 "/health",
                 actix_web::web::get().to(health::handlers::health_check),
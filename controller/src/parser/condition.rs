@@ -0,0 +1,256 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// The `if` of an action, e.g. `failure() && branch == 'main'`, telling whether it runs once the actions it needs are
+/// over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// `success()`: every action the action needs completed successfully
+    Success,
+    /// `failure()`: an action the action needs, directly or not, failed or timed out
+    Failure,
+    /// `always()`: whatever the actions the action needs became
+    Always,
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Equals(Operand, Operand),
+    NotEquals(Operand, Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Variable(Variable),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variable {
+    /// Branch the run is for, empty if unknown
+    Branch,
+    /// What started the run, e.g. `commit`
+    Event,
+}
+
+/// What a condition is evaluated against, once the actions the action needs are over.
+#[derive(Debug, Clone, Copy)]
+pub struct ConditionContext<'a> {
+    /// Whether every action the action needs completed successfully
+    pub success: bool,
+    /// Whether an action the action needs, directly or not, failed or timed out
+    pub failure: bool,
+    pub event: &'a str,
+    pub branch: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Literal(String),
+    OpeningParenthesis,
+    ClosingParenthesis,
+    Not,
+    And,
+    Or,
+    Equals,
+    NotEquals,
+}
+
+impl Condition {
+    /// Parse a condition, or tell why it cannot be.
+    pub fn parse(expression: &str) -> Result<Condition, String> {
+        let mut tokens = tokenize(expression)?.into_iter().peekable();
+        let condition = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(condition),
+            Some(token) => Err(format!("unexpected {}", describe(&token))),
+        }
+    }
+
+    /// Whether the action runs. A condition that checks none of the statuses of the needed actions only holds when
+    /// they all completed successfully, as an action without condition.
+    pub fn evaluate(&self, context: &ConditionContext) -> bool {
+        if !self.checks_status() && !context.success {
+            return false;
+        }
+        self.holds(context)
+    }
+
+    fn holds(&self, context: &ConditionContext) -> bool {
+        match self {
+            Condition::Success => context.success,
+            Condition::Failure => context.failure,
+            Condition::Always => true,
+            Condition::Not(condition) => !condition.holds(context),
+            Condition::And(left, right) => left.holds(context) && right.holds(context),
+            Condition::Or(left, right) => left.holds(context) || right.holds(context),
+            Condition::Equals(left, right) => left.value(context) == right.value(context),
+            Condition::NotEquals(left, right) => left.value(context) != right.value(context),
+        }
+    }
+
+    fn checks_status(&self) -> bool {
+        match self {
+            Condition::Success | Condition::Failure | Condition::Always => true,
+            Condition::Not(condition) => condition.checks_status(),
+            Condition::And(left, right) | Condition::Or(left, right) => {
+                left.checks_status() || right.checks_status()
+            }
+            Condition::Equals(..) | Condition::NotEquals(..) => false,
+        }
+    }
+}
+
+impl Operand {
+    fn value<'a>(&'a self, context: &ConditionContext<'a>) -> &'a str {
+        match self {
+            Operand::Variable(Variable::Branch) => context.branch,
+            Operand::Variable(Variable::Event) => context.event,
+            Operand::Literal(value) => value,
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpeningParenthesis,
+            ')' => Token::ClosingParenthesis,
+            '&' if next_is(&mut chars, '&') => Token::And,
+            '|' if next_is(&mut chars, '|') => Token::Or,
+            '=' if next_is(&mut chars, '=') => Token::Equals,
+            '!' if next_is(&mut chars, '=') => Token::NotEquals,
+            '!' => Token::Not,
+            '\'' | '"' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, end)) if end == c => break,
+                        Some((_, other)) => literal.push(other),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                Token::Literal(literal)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, next)) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    end = index + next.len_utf8();
+                    chars.next();
+                }
+                Token::Identifier(expression[start..end].to_string())
+            }
+            c => return Err(format!("unexpected `{}`", c)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn next_is(chars: &mut Peekable<CharIndices>, expected: char) -> bool {
+    chars.next_if(|&(_, c)| c == expected).is_some()
+}
+
+type Tokens = Peekable<std::vec::IntoIter<Token>>;
+
+fn parse_or(tokens: &mut Tokens) -> Result<Condition, String> {
+    let mut condition = parse_and(tokens)?;
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        condition = Condition::Or(Box::new(condition), Box::new(parse_and(tokens)?));
+    }
+    Ok(condition)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<Condition, String> {
+    let mut condition = parse_not(tokens)?;
+    while tokens.next_if_eq(&Token::And).is_some() {
+        condition = Condition::And(Box::new(condition), Box::new(parse_not(tokens)?));
+    }
+    Ok(condition)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<Condition, String> {
+    match tokens.next() {
+        Some(Token::Not) => Ok(Condition::Not(Box::new(parse_not(tokens)?))),
+        Some(Token::OpeningParenthesis) => {
+            let condition = parse_or(tokens)?;
+            expect(tokens, Token::ClosingParenthesis)?;
+            Ok(condition)
+        }
+        Some(Token::Identifier(name)) => {
+            if tokens.next_if_eq(&Token::OpeningParenthesis).is_none() {
+                return parse_comparison(variable(&name)?, tokens);
+            }
+            expect(tokens, Token::ClosingParenthesis)?;
+            match name.as_str() {
+                "success" => Ok(Condition::Success),
+                "failure" => Ok(Condition::Failure),
+                "always" => Ok(Condition::Always),
+                _ => Err(format!("unknown function `{}()`", name)),
+            }
+        }
+        Some(Token::Literal(literal)) => parse_comparison(Operand::Literal(literal), tokens),
+        Some(token) => Err(format!("unexpected {}", describe(&token))),
+        None => Err("unexpected end of the condition".to_string()),
+    }
+}
+
+fn parse_comparison(left: Operand, tokens: &mut Tokens) -> Result<Condition, String> {
+    let equals = match tokens.next() {
+        Some(Token::Equals) => true,
+        Some(Token::NotEquals) => false,
+        Some(token) => return Err(format!("expected `==` or `!=`, found {}", describe(&token))),
+        None => return Err("expected `==` or `!=`".to_string()),
+    };
+    let right = match tokens.next() {
+        Some(Token::Identifier(name)) => variable(&name)?,
+        Some(Token::Literal(literal)) => Operand::Literal(literal),
+        Some(token) => return Err(format!("unexpected {}", describe(&token))),
+        None => return Err("unexpected end of the condition".to_string()),
+    };
+    Ok(if equals {
+        Condition::Equals(left, right)
+    } else {
+        Condition::NotEquals(left, right)
+    })
+}
+
+fn variable(name: &str) -> Result<Operand, String> {
+    match name {
+        "branch" => Ok(Operand::Variable(Variable::Branch)),
+        "event" => Ok(Operand::Variable(Variable::Event)),
+        _ => Err(format!("unknown variable `{}`", name)),
+    }
+}
+
+fn expect(tokens: &mut Tokens, expected: Token) -> Result<(), String> {
+    match tokens.next() {
+        Some(token) if token == expected => Ok(()),
+        Some(token) => Err(format!(
+            "expected {}, found {}",
+            describe(&expected),
+            describe(&token)
+        )),
+        None => Err(format!("expected {}", describe(&expected))),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Identifier(name) => format!("`{}`", name),
+        Token::Literal(literal) => format!("'{}'", literal),
+        Token::OpeningParenthesis => "`(`".to_string(),
+        Token::ClosingParenthesis => "`)`".to_string(),
+        Token::Not => "`!`".to_string(),
+        Token::And => "`&&`".to_string(),
+        Token::Or => "`||`".to_string(),
+        Token::Equals => "`==`".to_string(),
+        Token::NotEquals => "`!=`".to_string(),
+    }
+}
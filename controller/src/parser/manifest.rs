@@ -0,0 +1,207 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+use schemars::{generate::SchemaSettings, json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+
+/// Version of the manifest format described here.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// A pipeline, made of actions run in containers.
+// The required fields are optional here and checked once deserialized, so that a missing one is reported with the
+// action and the place it is about.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(title = "SealCI pipeline manifest", deny_unknown_fields)]
+pub struct Manifest {
+    /// Version of the manifest format, 1 if absent
+    #[schemars(extend("enum" = [1]))]
+    pub version: Option<u32>,
+    /// Name of the pipeline
+    #[schemars(required)]
+    pub name: Option<String>,
+    /// Timeout of the actions that do not declare their own, in seconds or as a duration such as `90s`, `10m` or `2h`
+    pub default_timeout: Option<Timeout>,
+    /// Default shell running the commands of the actions, e.g. `bash -eo pipefail`
+    pub shell: Option<String>,
+    /// Environment variables shared by all the actions
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+    /// Actions of the pipeline, by name, which may also come from included manifests
+    pub actions: Option<IndexMap<String, ActionDefinition>>,
+    /// Actions to extend, by name, that are not run themselves
+    #[serde(default)]
+    pub templates: IndexMap<String, Template>,
+    /// Manifests of the repository whose templates and actions are part of the pipeline, by path from the directory
+    /// of the manifest, e.g. `ci/lint.yml`
+    pub include: Option<Include>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct ActionDefinition {
+    /// Template whose fields the action takes when it does not set them
+    pub extends: Option<String>,
+    /// Where the action runs
+    #[schemars(required)]
+    pub configuration: Option<Configuration>,
+    /// Scripts run one after the other by the shell of the action
+    #[schemars(required, length(min = 1))]
+    pub commands: Option<Vec<Command>>,
+    /// Time after which the action is stopped, in seconds or as a duration such as `90s`, `10m` or `2h`
+    pub timeout: Option<Timeout>,
+    /// Shell running the commands, e.g. `bash -eo pipefail`
+    pub shell: Option<String>,
+    /// Environment variables of the action, overriding the pipeline ones
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+    /// Actions that must be over before this one starts, and complete successfully unless `if` tells otherwise
+    pub needs: Option<Needs>,
+    /// Expression telling whether the action runs once the actions it needs are over, e.g. `failure()`,
+    /// `always()` or `success() && branch == 'main'`
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+    /// When and how often the action runs again after it failed
+    pub retry: Option<Retry>,
+    /// Files and directories of the repository kept once the commands succeeded, as paths or shell glob patterns such
+    /// as `target/release/app` or `dist/*.tar.gz`
+    #[schemars(length(min = 1))]
+    pub artifacts: Option<Vec<String>>,
+    /// Actions it needs whose artifacts are unpacked in the repository, where they were produced, before the commands
+    /// run
+    pub artifacts_from: Option<Needs>,
+    /// Values to run the action with, by name: the action is run once per combination of values, each one given as
+    /// an environment variable and as `${{ matrix.NAME }}` in the container image and the commands
+    pub matrix: Option<IndexMap<String, Vec<EnvValue>>>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Configuration {
+    /// Image of the container the action runs in, e.g. `rust:1.81`
+    #[schemars(required)]
+    pub container: Option<String>,
+    /// Labels an agent must advertise to run the action
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Timeout {
+    Seconds(i64),
+    /// A number followed by `s`, `m` or `h`
+    Duration(#[schemars(regex(pattern = r"^[0-9]+[smh]$"))] String),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Command {
+    Script(String),
+    Block {
+        /// Script run by the shell
+        run: String,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Needs {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Retry {
+    /// Number of times the action runs at most, the first one included
+    #[schemars(required, range(min = 1, max = 10))]
+    pub max_attempts: Option<u32>,
+    /// Time waited before the second attempt and doubled before each next one, in seconds or as a duration such as
+    /// `30s` or `5m`, 10 seconds if absent
+    pub backoff: Option<Timeout>,
+    /// Failures after which the action runs again, `[infrastructure]` if absent
+    #[schemars(length(min = 1))]
+    pub on: Option<Vec<FailureClass>>,
+}
+
+/// What made an attempt of an action fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    /// The commands could not run to their end, e.g. the repository could not be cloned, the image could not be
+    /// pulled or the agent was lost
+    Infrastructure,
+    /// A command exited with a non-zero code
+    ExitCode,
+}
+
+impl FailureClass {
+    /// The name of the failure class in manifests, under which it is also stored.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::Infrastructure => "infrastructure",
+            FailureClass::ExitCode => "exit_code",
+        }
+    }
+}
+
+impl FromStr for FailureClass {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "infrastructure" => Ok(FailureClass::Infrastructure),
+            "exit_code" => Ok(FailureClass::ExitCode),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Value of an environment variable. Numbers and booleans are accepted, and kept as written.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct EnvValue(pub String);
+
+impl JsonSchema for EnvValue {
+    fn schema_name() -> Cow<'static, str> {
+        "EnvValue".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({ "type": ["string", "number", "boolean"] })
+    }
+}
+
+/// An action that is extended rather than run, so none of its fields is required.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct Template(pub ActionDefinition);
+
+impl JsonSchema for Template {
+    fn schema_name() -> Cow<'static, str> {
+        "Template".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let mut schema = ActionDefinition::json_schema(generator);
+        schema.remove("required");
+        schema
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Include {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// JSON Schema of the manifest, for editors to complete and check `.sealci.yml` files.
+pub fn json_schema() -> Schema {
+    // Draft 7 is the one editors support best
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<Manifest>()
+}
@@ -1 +1,3 @@
+pub mod condition;
+pub mod manifest;
 pub mod pipe_parser;
@@ -1,14 +1,34 @@
 use core::fmt;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::str::FromStr;
+use std::time::Duration;
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use yaml_rust::yaml::Yaml;
-use yaml_rust::YamlLoader;
+use serde_path_to_error::Segment;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+use super::condition::Condition;
+use super::manifest::{
+    ActionDefinition, Command, Configuration, EnvValue, FailureClass, Include, Manifest, Needs,
+    Retry, Template, Timeout, MANIFEST_VERSION,
+};
+use crate::secret::secret_reference::{is_valid_name, references_in, substitute_in};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ManifestPipeline {
     pub name: String,
     pub actions: Vec<ManifestAction>,
+    /// Timeout of the actions that do not declare their own, in seconds
+    #[serde(default)]
+    pub default_timeout: Option<u32>,
+    /// Default shell running the commands of the actions
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Environment variables shared by all the actions
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,6 +37,57 @@ pub struct ManifestAction {
     pub commands: Vec<String>,
     pub configuration_type: Type,
     pub configuration_version: String,
+    /// Labels an agent must advertise to run the action
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Seconds after which the action is stopped, falling back to the default timeout of the pipeline
+    #[serde(default)]
+    pub timeout: Option<u32>,
+    /// Shell running the commands, e.g. `bash -eo pipefail`, falling back to the pipeline shell
+    #[serde(default)]
+    pub shell: Option<String>,
+    /// Environment variables of the action, including the pipeline ones it does not override
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Names of the actions that must be over before this one starts
+    #[serde(default)]
+    pub needs: Vec<String>,
+    /// Expression telling whether the action runs once the actions it needs are over
+    #[serde(default)]
+    pub condition: Option<String>,
+    /// When and how often the action runs again after it failed
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Paths or shell glob patterns of the files of the repository kept once the commands succeeded
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Names of the actions it needs whose artifacts are unpacked in the repository before the commands run
+    #[serde(default)]
+    pub artifacts_from: Vec<String>,
+}
+
+/// When and how often a failed action runs again.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of times the action runs at most, the first one included
+    pub max_attempts: u32,
+    /// Seconds waited before the second attempt, doubled before each next one up to an hour
+    pub backoff: u32,
+    /// Failures after which the action runs again
+    pub on: Vec<FailureClass>,
+}
+
+impl RetryPolicy {
+    /// Whether the action runs again after the attempt of this number, starting at 1, failed this way.
+    pub fn retries(&self, attempt: u32, failure: FailureClass) -> bool {
+        attempt < self.max_attempts && self.on.contains(&failure)
+    }
+
+    /// Time waited after the attempt of this number failed, before the next one starts.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.clamp(1, MAX_RETRY_ATTEMPTS).saturating_sub(1);
+        Duration::from_secs((u64::from(self.backoff) * factor).min(MAX_RETRY_DELAY))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -49,16 +120,40 @@ impl From<String> for Type {
     }
 }
 
+/// Manifests the main manifest can include, by path from its directory, e.g. `ci/lint.yml`.
+pub type IncludedFiles = HashMap<String, String>;
+
 pub trait ManifestParser: Sync + Send {
     /// .
     ///
     /// # Errors
     ///
     /// This function will return an error if the yaml is not compliant with the expected format.
-    fn parse(&self, yaml: String) -> Result<ManifestPipeline, ParsingError>;
+    fn parse(&self, yaml: String) -> Result<ManifestPipeline, ManifestError> {
+        self.parse_with_includes(yaml, &IncludedFiles::new())
+    }
+
+    /// Parse the manifest, and point out what is valid but likely a mistake.
+    fn validate(&self, yaml: String) -> Result<Validation, ManifestError> {
+        self.validate_with_includes(yaml, &IncludedFiles::new())
+    }
+
+    /// Parse the manifest, along with the manifests it includes.
+    fn parse_with_includes(
+        &self,
+        yaml: String,
+        includes: &IncludedFiles,
+    ) -> Result<ManifestPipeline, ManifestError>;
+
+    /// Validate the manifest, along with the manifests it includes.
+    fn validate_with_includes(
+        &self,
+        yaml: String,
+        includes: &IncludedFiles,
+    ) -> Result<Validation, ManifestError>;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
 pub enum ParsingError {
     InconsistentCommandIndentation,
     YamlNotCompliant,
@@ -67,125 +162,1299 @@ pub enum ParsingError {
     MissingActions,
     MissingConfiguration,
     MissingCommands,
-    MissingStepName,
+    InvalidTimeout,
+    InvalidShell,
+    InvalidEnv,
+    InvalidNeeds,
+    UnknownDependency,
+    DependencyCycle,
+    UnsupportedVersion,
+    InvalidMatrix,
+    UnknownMatrixValue,
+    DuplicateMatrixValue,
+    UnknownTemplate,
+    TemplateCycle,
+    DuplicateDefinition,
+    InvalidInclude,
+    IncludeNotFound,
+    IncludeCycle,
+    InvalidCondition,
+    InvalidRetry,
+    InvalidArtifacts,
+    InvalidArtifactsFrom,
+}
+
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsingError::InconsistentCommandIndentation => {
+                write!(f, "Commands are not indented consistently")
+            }
+            ParsingError::YamlNotCompliant => write!(f, "The manifest does not have the expected structure"),
+            ParsingError::InvalidActionName => write!(
+                f,
+                "Action names only contain letters, digits, spaces, `&`, `-` and `_`"
+            ),
+            ParsingError::MissingName => write!(f, "The pipeline has no name"),
+            ParsingError::MissingActions => write!(f, "The pipeline has no actions"),
+            ParsingError::MissingConfiguration => {
+                write!(f, "The action has no container configuration")
+            }
+            ParsingError::MissingCommands => write!(f, "The action has no commands"),
+            ParsingError::InvalidTimeout => write!(
+                f,
+                "Timeouts are a positive number of seconds, or a duration such as `90s`, `10m` or `2h`"
+            ),
+            ParsingError::InvalidShell => write!(f, "The shell is not a command line"),
+            ParsingError::InvalidEnv => write!(
+                f,
+                "Environment variables map names made of letters, digits and `_` to scalar values"
+            ),
+            ParsingError::InvalidNeeds => write!(f, "`needs` is an action name or a list of action names"),
+            ParsingError::UnknownDependency => {
+                write!(f, "The action needs an action that is not in the pipeline")
+            }
+            ParsingError::DependencyCycle => write!(f, "The action is part of a dependency cycle"),
+            ParsingError::InvalidMatrix => write!(
+                f,
+                "A matrix maps names made of letters, digits and `_` to non-empty lists of values, with at most {} combinations",
+                MAX_MATRIX_COMBINATIONS
+            ),
+            ParsingError::UnknownMatrixValue => {
+                write!(f, "The action references a matrix value it does not define")
+            }
+            ParsingError::DuplicateMatrixValue => {
+                write!(f, "A matrix value is listed twice, its actions would have the same name")
+            }
+            ParsingError::UnknownTemplate => {
+                write!(f, "`extends` names a template that is not defined")
+            }
+            ParsingError::TemplateCycle => write!(f, "The templates extend each other"),
+            ParsingError::DuplicateDefinition => write!(
+                f,
+                "An action or a template with this name is already defined"
+            ),
+            ParsingError::InvalidInclude => write!(
+                f,
+                "Included manifests only define templates, actions and includes, by a path inside the repository"
+            ),
+            ParsingError::IncludeNotFound => write!(f, "The included manifest was not sent"),
+            ParsingError::IncludeCycle => write!(f, "The manifests include each other"),
+            ParsingError::InvalidCondition => write!(f, "The `if` expression is not valid"),
+            ParsingError::InvalidRetry => write!(
+                f,
+                "`retry` sets `max_attempts` between 1 and {}, a `backoff` of at most an hour, and failures `on` among `infrastructure` and `exit_code`",
+                MAX_RETRY_ATTEMPTS
+            ),
+            ParsingError::InvalidArtifacts => write!(
+                f,
+                "Artifacts are paths or glob patterns inside the repository, e.g. `target/release/app` or `dist/*.tar.gz`"
+            ),
+            ParsingError::InvalidArtifactsFrom => write!(
+                f,
+                "`artifacts_from` names actions listed in `needs` that declare `artifacts`"
+            ),
+            ParsingError::UnsupportedVersion => write!(
+                f,
+                "Only version {} of the manifest format is supported",
+                MANIFEST_VERSION
+            ),
+        }
+    }
+}
+
+/// A position in the manifest, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<Marker> for Span {
+    fn from(marker: Marker) -> Self {
+        Span {
+            line: marker.line(),
+            column: marker.col() + 1,
+        }
+    }
+}
+
+/// Why a manifest was rejected, and where.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestError {
+    #[serde(rename = "code")]
+    pub kind: ParsingError,
+    pub message: String,
+    /// Action the error is in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// Manifests included one from the other, from the main manifest to the one the error is in
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include_chain: Vec<String>,
+}
+
+impl ManifestError {
+    pub fn new(kind: ParsingError, action: Option<&str>, span: Option<Span>) -> Self {
+        Self {
+            kind,
+            message: kind.to_string(),
+            action: action.map(String::from),
+            span,
+            include_chain: Vec::new(),
+        }
+    }
+
+    fn with_detail(mut self, detail: &str) -> Self {
+        self.message = format!("{}: {}", self.message, detail);
+        self
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(action) = &self.action {
+            write!(f, " (action {})", action)?;
+        }
+        if !self.include_chain.is_empty() {
+            write!(f, " in {}", self.include_chain.join(" > "))?;
+        }
+        if let Some(span) = self.span {
+            write!(f, " at line {} column {}", span.line, span.column)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum WarningKind {
+    /// The image has no tag, or the `latest` one, so the action may run on another image from a day to the next
+    UnpinnedImage,
+    /// The key is not part of the manifest format, it is ignored
+    UnknownKey,
+}
+
+/// Something valid in a manifest that is likely a mistake.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestWarning {
+    #[serde(rename = "code")]
+    pub kind: WarningKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// Manifests included one from the other, from the main manifest to the one the warning is about
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include_chain: Vec<String>,
+}
+
+/// A manifest that can be run, along with its warnings.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub pipeline: ManifestPipeline,
+    pub warnings: Vec<ManifestWarning>,
 }
 
 #[derive(Clone)]
 pub struct PipeParser {}
 
 impl ManifestParser for PipeParser {
-    fn parse(&self, yaml: String) -> Result<ManifestPipeline, ParsingError> {
-        check_command_indentation(&yaml)?;
-        let doc = parse_yaml(&yaml)?;
-        let name = parse_pipeline_name(&doc)?;
-        let actions = parse_actions(&doc)?;
+    fn parse_with_includes(
+        &self,
+        yaml: String,
+        includes: &IncludedFiles,
+    ) -> Result<ManifestPipeline, ManifestError> {
+        parse_manifest(&yaml, includes).map(|manifest| manifest.pipeline)
+    }
 
-        Ok(ManifestPipeline { name, actions })
+    fn validate_with_includes(
+        &self,
+        yaml: String,
+        includes: &IncludedFiles,
+    ) -> Result<Validation, ManifestError> {
+        let manifest = parse_manifest(&yaml, includes)?;
+        let mut warnings: Vec<ManifestWarning> =
+            manifest.files.iter().flat_map(unknown_keys).collect();
+        warnings.extend(unpinned_images(
+            &manifest.pipeline,
+            &manifest.origins,
+            &manifest.files,
+        ));
+        warnings.sort_by(|a, b| {
+            (
+                &a.include_chain,
+                a.span.map(|span| (span.line, span.column)),
+            )
+                .cmp(&(
+                    &b.include_chain,
+                    b.span.map(|span| (span.line, span.column)),
+                ))
+        });
+        Ok(Validation {
+            pipeline: manifest.pipeline,
+            warnings,
+        })
     }
 }
 
-fn parse_yaml(yaml: &str) -> Result<Yaml, ParsingError> {
-    let docs = YamlLoader::load_from_str(yaml).map_err(|_| ParsingError::YamlNotCompliant)?;
-    docs.get(0).cloned().ok_or(ParsingError::YamlNotCompliant)
+/// A parsed manifest, with what is needed to locate its parts.
+struct ParsedManifest {
+    pipeline: ManifestPipeline,
+    /// Name in the manifests of each action of the pipeline, which differs for the actions of a matrix, and the
+    /// index of the file it is in
+    origins: Vec<(String, usize)>,
+    files: Vec<ManifestFile>,
 }
 
-fn parse_pipeline_name(doc: &Yaml) -> Result<String, ParsingError> {
-    doc["name"]
-        .as_str()
-        .ok_or(ParsingError::MissingName)
-        .map(String::from)
+/// A file of the pipeline: the main manifest, or one of the manifests it includes.
+struct ManifestFile {
+    /// Manifests included one from the other to reach this one, empty for the main manifest
+    chain: Vec<String>,
+    manifest: Manifest,
+    /// Paths of the keys that are not part of the manifest format
+    unknown_keys: Vec<Vec<String>>,
+    source: SourceMap,
 }
 
-fn parse_actions(doc: &Yaml) -> Result<Vec<ManifestAction>, ParsingError> {
-    let actions_yaml = doc["actions"]
-        .as_hash()
-        .ok_or(ParsingError::MissingActions)?;
-    actions_yaml
+impl ManifestFile {
+    fn locate(&self, kind: ParsingError, action: Option<&str>, path: &str) -> ManifestError {
+        ManifestError {
+            include_chain: self.chain.clone(),
+            ..self.source.locate(kind, action, path)
+        }
+    }
+}
+
+/// Templates or actions by name, along with the index of the file defining them.
+type Definitions<'a> = IndexMap<&'a str, (usize, &'a ActionDefinition)>;
+
+fn parse_manifest(yaml: &str, includes: &IncludedFiles) -> Result<ParsedManifest, ManifestError> {
+    let includes: IncludedFiles = includes
         .iter()
-        .map(|(name, action)| parse_action(name, action))
-        .collect()
+        .filter_map(|(path, yaml)| Some((normalize_include(path)?, yaml.clone())))
+        .collect();
+    let mut files = Vec::new();
+    load_file(yaml, Vec::new(), &includes, &mut files)?;
+    let main = &files[0];
+
+    let name = main
+        .manifest
+        .name
+        .clone()
+        .ok_or_else(|| main.locate(ParsingError::MissingName, None, "name"))?;
+    let default_timeout = parse_timeout(main.manifest.default_timeout.as_ref())
+        .map_err(|e| main.locate(e, None, "default_timeout"))?;
+    let shell =
+        parse_shell(main.manifest.shell.clone()).map_err(|e| main.locate(e, None, "shell"))?;
+    let env = parse_env(main.manifest.env.clone()).map_err(|e| main.locate(e, None, "env"))?;
+    let defaults = ActionDefaults {
+        timeout: default_timeout,
+        shell: shell.as_deref(),
+        env: &env,
+    };
+
+    let templates = definitions(&files, "templates", |manifest| {
+        manifest
+            .templates
+            .iter()
+            .map(|(name, Template(template))| (name, template))
+            .collect()
+    })?;
+    for (name, (index, template)) in &templates {
+        resolve_extends((*template).clone(), &templates)
+            .map_err(|e| files[*index].locate(e, None, &format!("templates.{}.extends", name)))?;
+    }
+    let definitions = definitions(&files, "actions", |manifest| {
+        manifest.actions.iter().flatten().collect()
+    })?;
+    if definitions.is_empty() {
+        return Err(main.locate(ParsingError::MissingActions, None, "actions"));
+    }
+    let actions = definitions
+        .iter()
+        .map(|(name, (index, action))| {
+            let file = &files[*index];
+            let action = resolve_extends((*action).clone(), &templates)
+                .map_err(|e| file.locate(e, Some(name), &format!("actions.{}.extends", name)))?;
+            parse_action(name.to_string(), action, &defaults, file)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let manifest_actions: Vec<ManifestAction> =
+        actions.iter().map(|(action, _)| action.clone()).collect();
+    check_dependencies(&manifest_actions).map_err(|(e, name)| {
+        let file = &files[definitions[name].0];
+        file.locate(e, Some(name), &format!("actions.{}.needs", name))
+    })?;
+    check_artifact_sources(&manifest_actions).map_err(|name| {
+        let file = &files[definitions[name].0];
+        file.locate(
+            ParsingError::InvalidArtifactsFrom,
+            Some(name),
+            &format!("actions.{}.artifacts_from", name),
+        )
+    })?;
+    let (actions, origins) = expand_matrices(actions)
+        .into_iter()
+        .map(|(action, origin)| {
+            let index = definitions[origin.as_str()].0;
+            (action, (origin, index))
+        })
+        .unzip();
+
+    Ok(ParsedManifest {
+        pipeline: ManifestPipeline {
+            name,
+            actions,
+            default_timeout,
+            shell,
+            env,
+        },
+        origins,
+        files,
+    })
 }
 
-fn parse_action(name: &Yaml, action: &Yaml) -> Result<ManifestAction, ParsingError> {
-    let name = parse_action_name(name)?;
-    let configuration = parse_configuration(action)?;
-    let commands = parse_commands(action)?;
+/// Load a manifest and, depth first, the manifests it includes. Each manifest is loaded once, even when included by
+/// several others.
+fn load_file(
+    yaml: &str,
+    chain: Vec<String>,
+    includes: &IncludedFiles,
+    files: &mut Vec<ManifestFile>,
+) -> Result<(), ManifestError> {
+    let in_file = |e: ManifestError| ManifestError {
+        include_chain: chain.clone(),
+        ..e
+    };
+    check_command_indentation(yaml).map_err(in_file)?;
+    let source = SourceMap::load(yaml).map_err(in_file)?;
+    let (manifest, unknown_keys) = deserialize_manifest(yaml, &source).map_err(in_file)?;
+    let file = ManifestFile {
+        chain,
+        manifest,
+        unknown_keys,
+        source,
+    };
+    check_file(&file)?;
+
+    let included = match &file.manifest.include {
+        None => Vec::new(),
+        Some(Include::One(path)) => vec![path.clone()],
+        Some(Include::Many(paths)) => paths.clone(),
+    };
+    let chain = file.chain.clone();
+    let index = files.len();
+    files.push(file);
+    for (position, path) in included.iter().enumerate() {
+        let locate =
+            |kind: ParsingError| files[index].locate(kind, None, &format!("include.{}", position));
+        let path = normalize_include(path)
+            .ok_or_else(|| locate(ParsingError::InvalidInclude).with_detail(path))?;
+        if chain.contains(&path) {
+            let mut cycle = chain.clone();
+            cycle.push(path);
+            return Err(locate(ParsingError::IncludeCycle).with_detail(&cycle.join(" > ")));
+        }
+        if files.iter().any(|file| file.chain.last() == Some(&path)) {
+            continue;
+        }
+        let yaml = includes
+            .get(&path)
+            .ok_or_else(|| locate(ParsingError::IncludeNotFound).with_detail(&path))?;
+        let mut included_chain = chain.clone();
+        included_chain.push(path);
+        load_file(yaml, included_chain, includes, files)?;
+    }
+    Ok(())
+}
 
-    Ok(ManifestAction {
+/// Check what the actions of a single manifest do not tell.
+fn check_file(file: &ManifestFile) -> Result<(), ManifestError> {
+    // The container configuration is where a misspelled key changes where the action runs
+    if let Some(path) = file.unknown_keys.iter().find(|path| {
+        path.len() > 3
+            && (path[0] == "actions" || path[0] == "templates")
+            && path[2] == "configuration"
+    }) {
+        let action = (path[0] == "actions").then_some(path[1].as_str());
+        return Err(file.locate(ParsingError::YamlNotCompliant, action, &path.join(".")));
+    }
+    if file
+        .manifest
+        .version
+        .is_some_and(|version| version != MANIFEST_VERSION)
+    {
+        return Err(file.locate(ParsingError::UnsupportedVersion, None, "version"));
+    }
+    // The settings of the pipeline are those of the main manifest
+    if !file.chain.is_empty() {
+        let manifest = &file.manifest;
+        let pipeline_keys = [
+            ("name", manifest.name.is_some()),
+            ("default_timeout", manifest.default_timeout.is_some()),
+            ("shell", manifest.shell.is_some()),
+            ("env", !manifest.env.is_empty()),
+        ];
+        if let Some((key, _)) = pipeline_keys.iter().find(|(_, set)| *set) {
+            return Err(file.locate(ParsingError::InvalidInclude, None, key));
+        }
+    }
+    Ok(())
+}
+
+/// The path of an included manifest from the directory of the main manifest, unless it leaves the repository.
+fn normalize_include(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// Gather the templates or the actions of every manifest, which must not share a name.
+fn definitions<'a>(
+    files: &'a [ManifestFile],
+    key: &str,
+    list: impl Fn(&'a Manifest) -> Vec<(&'a String, &'a ActionDefinition)>,
+) -> Result<Definitions<'a>, ManifestError> {
+    let mut definitions = Definitions::new();
+    for (index, file) in files.iter().enumerate() {
+        for (name, definition) in list(&file.manifest) {
+            if definitions
+                .insert(name.as_str(), (index, definition))
+                .is_some()
+            {
+                let action = (key == "actions").then_some(name.as_str());
+                return Err(file.locate(
+                    ParsingError::DuplicateDefinition,
+                    action,
+                    &format!("{}.{}", key, name),
+                ));
+            }
+        }
+    }
+    Ok(definitions)
+}
+
+/// The action with the fields it does not set taken from the template it extends, and so on.
+fn resolve_extends(
+    mut action: ActionDefinition,
+    templates: &Definitions,
+) -> Result<ActionDefinition, ParsingError> {
+    let mut extended: Vec<String> = Vec::new();
+    while let Some(name) = action.extends.take() {
+        if extended.contains(&name) {
+            return Err(ParsingError::TemplateCycle);
+        }
+        let (_, template) = templates
+            .get(name.as_str())
+            .ok_or(ParsingError::UnknownTemplate)?;
+        action = extend(action, template);
+        extended.push(name);
+    }
+    Ok(action)
+}
+
+/// Fill the fields the action does not set with those of the template. The environment variables are merged, those
+/// of the action taking precedence.
+fn extend(action: ActionDefinition, template: &ActionDefinition) -> ActionDefinition {
+    let template = template.clone();
+    let configuration = match (action.configuration, template.configuration) {
+        (Some(action), Some(template)) => Some(Configuration {
+            container: action.container.or(template.container),
+            labels: if action.labels.is_empty() {
+                template.labels
+            } else {
+                action.labels
+            },
+        }),
+        (action, template) => action.or(template),
+    };
+    let mut env = template.env;
+    env.extend(action.env);
+    ActionDefinition {
+        extends: template.extends,
+        configuration,
+        commands: action.commands.or(template.commands),
+        timeout: action.timeout.or(template.timeout),
+        shell: action.shell.or(template.shell),
+        env,
+        needs: action.needs.or(template.needs),
+        condition: action.condition.or(template.condition),
+        retry: action.retry.or(template.retry),
+        artifacts: action.artifacts.or(template.artifacts),
+        artifacts_from: action.artifacts_from.or(template.artifacts_from),
+        matrix: action.matrix.or(template.matrix),
+    }
+}
+
+/// Deserialize the first document of the manifest, along with the paths of the keys that were ignored.
+fn deserialize_manifest(
+    yaml: &str,
+    source: &SourceMap,
+) -> Result<(Manifest, Vec<Vec<String>>), ManifestError> {
+    let document = serde_yaml::Deserializer::from_str(yaml)
+        .next()
+        .ok_or_else(|| ManifestError::new(ParsingError::YamlNotCompliant, None, None))?;
+    let mut ignored = Vec::new();
+    let mut ignore = |path: serde_ignored::Path| ignored.push(ignored_path(&path));
+    let manifest =
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(document, &mut ignore))
+            .map_err(|e| {
+            let path: Vec<String> = e
+                .path()
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Seq { index } => index.to_string(),
+                    Segment::Map { key } => key.clone(),
+                    Segment::Enum { variant } => variant.clone(),
+                    Segment::Unknown => "?".to_string(),
+                })
+                .collect();
+            let action = match path.as_slice() {
+                [actions, name, ..] if actions == "actions" => Some(name.as_str()),
+                _ => None,
+            };
+            source.locate(deserialization_error_kind(&path), action, &path.join("."))
+        })?;
+    Ok((manifest, ignored))
+}
+
+/// The keys leading to an ignored value, without the steps through optional values.
+fn ignored_path(path: &serde_ignored::Path) -> Vec<String> {
+    match path {
+        serde_ignored::Path::Root => Vec::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            let mut path = ignored_path(parent);
+            path.push(index.to_string());
+            path
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            let mut path = ignored_path(parent);
+            path.push(key.clone());
+            path
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+/// What is wrong with a value of the manifest that does not have the expected type, given its path.
+fn deserialization_error_kind(path: &[String]) -> ParsingError {
+    let mut path: Vec<&str> = path.iter().map(String::as_str).collect();
+    // Templates are checked as the actions they are for
+    if path.len() > 1 && path[0] == "templates" {
+        path[0] = "actions";
+    }
+    match path.as_slice() {
+        ["version"] => ParsingError::UnsupportedVersion,
+        ["name"] => ParsingError::MissingName,
+        ["actions"] => ParsingError::MissingActions,
+        ["actions", _]
+        | ["actions", _, "configuration"]
+        | ["actions", _, "configuration", "container"] => ParsingError::MissingConfiguration,
+        ["actions", _, "commands"] => ParsingError::MissingCommands,
+        ["default_timeout"] | ["actions", _, "timeout", ..] => ParsingError::InvalidTimeout,
+        ["shell"] | ["actions", _, "shell", ..] => ParsingError::InvalidShell,
+        ["env", ..] | ["actions", _, "env", ..] => ParsingError::InvalidEnv,
+        ["actions", _, "needs", ..] => ParsingError::InvalidNeeds,
+        ["actions", _, "matrix", ..] => ParsingError::InvalidMatrix,
+        ["actions", _, "if", ..] => ParsingError::InvalidCondition,
+        ["actions", _, "retry", ..] => ParsingError::InvalidRetry,
+        ["actions", _, "artifacts", ..] => ParsingError::InvalidArtifacts,
+        ["actions", _, "artifacts_from", ..] => ParsingError::InvalidArtifactsFrom,
+        ["include", ..] => ParsingError::InvalidInclude,
+        _ => ParsingError::YamlNotCompliant,
+    }
+}
+
+/// The pipeline-level settings applying to the actions that do not declare their own.
+struct ActionDefaults<'a> {
+    timeout: Option<u32>,
+    shell: Option<&'a str>,
+    env: &'a BTreeMap<String, String>,
+}
+
+fn parse_action(
+    name: String,
+    action: ActionDefinition,
+    defaults: &ActionDefaults,
+    file: &ManifestFile,
+) -> Result<(ManifestAction, Matrix), ManifestError> {
+    let path = format!("actions.{}", name);
+    let locate = |e: ParsingError, key: &str| file.locate(e, Some(&name), &join_path(&path, key));
+    if !is_valid_action_name(&name) {
+        return Err(file.locate(ParsingError::InvalidActionName, Some(&name), &path));
+    }
+    let configuration = action
+        .configuration
+        .ok_or_else(|| locate(ParsingError::MissingConfiguration, "configuration"))?;
+    let container = configuration
+        .container
+        .ok_or_else(|| locate(ParsingError::MissingConfiguration, "configuration"))?;
+    let commands = parse_commands(action.commands).map_err(|e| locate(e, "commands"))?;
+    let timeout = parse_timeout(action.timeout.as_ref())
+        .map_err(|e| locate(e, "timeout"))?
+        .or(defaults.timeout);
+    let shell = parse_shell(action.shell)
+        .map_err(|e| locate(e, "shell"))?
+        .or(defaults.shell.map(String::from));
+    let mut env = defaults.env.clone();
+    env.extend(parse_env(action.env).map_err(|e| locate(e, "env"))?);
+    let needs = parse_needs(action.needs);
+    if let Some(condition) = &action.condition {
+        Condition::parse(condition)
+            .map_err(|reason| locate(ParsingError::InvalidCondition, "if").with_detail(&reason))?;
+    }
+    let retry = parse_retry(action.retry).map_err(|e| locate(e, "retry"))?;
+    let artifacts = parse_artifacts(action.artifacts)
+        .map_err(|key| locate(ParsingError::InvalidArtifacts, &key))?;
+    let artifacts_from = parse_needs(action.artifacts_from);
+    let matrix = parse_matrix(action.matrix).map_err(|e| locate(e, "matrix"))?;
+    if let Some((value_name, index)) = duplicate_matrix_value(&matrix) {
+        return Err(locate(
+            ParsingError::DuplicateMatrixValue,
+            &format!("matrix.{}.{}", value_name, index),
+        ));
+    }
+    let is_defined = |name: &&str| matrix.iter().any(|(key, _)| key == name);
+    if !references_in(&container, MATRIX).iter().all(is_defined) {
+        return Err(locate(
+            ParsingError::UnknownMatrixValue,
+            "configuration.container",
+        ));
+    }
+    if let Some(index) = commands
+        .iter()
+        .position(|command| !references_in(command, MATRIX).iter().all(is_defined))
+    {
+        return Err(locate(
+            ParsingError::UnknownMatrixValue,
+            &format!("commands.{}", index),
+        ));
+    }
+
+    let action = ManifestAction {
         name,
         commands,
         configuration_type: Type::Container,
-        configuration_version: configuration,
-    })
+        configuration_version: container,
+        labels: configuration.labels,
+        timeout,
+        shell,
+        env,
+        needs,
+        condition: action.condition,
+        retry,
+        artifacts,
+        artifacts_from,
+    };
+    Ok((action, matrix))
 }
 
-fn parse_action_name(name: &Yaml) -> Result<String, ParsingError> {
-    let name = name
-        .as_str()
-        .ok_or(ParsingError::MissingStepName)?
-        .to_string();
-    if !is_valid_action_name(&name) {
-        return Err(ParsingError::InvalidActionName);
+/// Parse a timeout given either as a number of seconds or as a duration with a unit, e.g. `90s`, `10m` or `2h`.
+fn parse_timeout(timeout: Option<&Timeout>) -> Result<Option<u32>, ParsingError> {
+    let Some(timeout) = timeout else {
+        return Ok(None);
+    };
+    let seconds = parse_seconds(timeout).ok_or(ParsingError::InvalidTimeout)?;
+    // Timeouts are stored as a signed 32 bits integer
+    if seconds <= 0 || seconds > i64::from(i32::MAX) {
+        return Err(ParsingError::InvalidTimeout);
     }
-    Ok(name)
+    Ok(Some(seconds as u32))
 }
 
-fn parse_configuration(action: &Yaml) -> Result<String, ParsingError> {
-    let config = action["configuration"]
-        .as_hash()
-        .ok_or(ParsingError::MissingConfiguration)?;
-    if !config.keys().all(|k| k.as_str() == Some("container")) {
-        return Err(ParsingError::YamlNotCompliant);
+/// The number of seconds of a duration given either as a number or with a unit, e.g. `90s`, `10m` or `2h`.
+fn parse_seconds(duration: &Timeout) -> Option<i64> {
+    match duration {
+        Timeout::Seconds(seconds) => Some(*seconds),
+        Timeout::Duration(duration) => {
+            let split = duration.len().saturating_sub(1);
+            let (value, unit) = duration.split_at(split);
+            let multiplier = match unit {
+                "s" => 1,
+                "m" => 60,
+                "h" => 3600,
+                _ => return None,
+            };
+            value.parse::<i64>().ok()?.checked_mul(multiplier)
+        }
     }
-    config
-        .get(&Yaml::String("container".to_string()))
-        .and_then(|v| v.as_str())
-        .ok_or(ParsingError::MissingConfiguration)
-        .map(String::from)
 }
 
-fn parse_commands(action: &Yaml) -> Result<Vec<String>, ParsingError> {
-    let commands = action["commands"]
-        .as_vec()
-        .ok_or(ParsingError::MissingCommands)?;
-    if commands.is_empty() {
-        return Err(ParsingError::MissingCommands);
+/// Attempts an action can be given, at most
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+/// Seconds waited before the second attempt of an action, unless its retry policy tells otherwise
+const DEFAULT_RETRY_BACKOFF: i64 = 10;
+/// Seconds waited before the second attempt of an action, at most
+const MAX_RETRY_BACKOFF: i64 = 3600;
+/// Seconds waited between two attempts of an action, at most
+const MAX_RETRY_DELAY: u64 = 3600;
+
+/// Parse a retry policy. Only the failures of the infrastructure are retried, unless the policy tells otherwise.
+fn parse_retry(retry: Option<Retry>) -> Result<Option<RetryPolicy>, ParsingError> {
+    let Some(retry) = retry else {
+        return Ok(None);
+    };
+    let max_attempts = retry
+        .max_attempts
+        .filter(|attempts| (1..=MAX_RETRY_ATTEMPTS).contains(attempts))
+        .ok_or(ParsingError::InvalidRetry)?;
+    let backoff = match &retry.backoff {
+        Some(backoff) => parse_seconds(backoff)
+            .filter(|seconds| (0..=MAX_RETRY_BACKOFF).contains(seconds))
+            .ok_or(ParsingError::InvalidRetry)?,
+        None => DEFAULT_RETRY_BACKOFF,
+    };
+    let mut on = Vec::new();
+    for failure in retry
+        .on
+        .unwrap_or_else(|| vec![FailureClass::Infrastructure])
+    {
+        if !on.contains(&failure) {
+            on.push(failure);
+        }
+    }
+    if on.is_empty() {
+        return Err(ParsingError::InvalidRetry);
     }
-    commands
+    Ok(Some(RetryPolicy {
+        max_attempts,
+        backoff: backoff as u32,
+        on,
+    }))
+}
+
+/// Parse a shell command line, e.g. `bash -eo pipefail`.
+fn parse_shell(shell: Option<String>) -> Result<Option<String>, ParsingError> {
+    match shell {
+        None => Ok(None),
+        Some(shell) if !shell.trim().is_empty() => Ok(Some(shell.trim().to_string())),
+        Some(_) => Err(ParsingError::InvalidShell),
+    }
+}
+
+/// Check the names of environment variables.
+fn parse_env(env: BTreeMap<String, EnvValue>) -> Result<BTreeMap<String, String>, ParsingError> {
+    env.into_iter()
+        .map(|(key, EnvValue(value))| {
+            if !is_valid_env_name(&key) {
+                return Err(ParsingError::InvalidEnv);
+            }
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// The dependencies of an action, given either as a single action name or as a list of names.
+fn parse_needs(needs: Option<Needs>) -> Vec<String> {
+    let names = match needs {
+        None => return Vec::new(),
+        Some(Needs::One(name)) => vec![name],
+        Some(Needs::Many(names)) => names,
+    };
+    let mut needs: Vec<String> = Vec::new();
+    for name in names {
+        if !needs.contains(&name) {
+            needs.push(name);
+        }
+    }
+    needs
+}
+
+/// Check the artifacts of an action, which the agent matches from the repository with its shell: a pattern only holds
+/// the characters of paths and globs, and stays inside the repository. An invalid pattern is told by its key.
+fn parse_artifacts(artifacts: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    let Some(patterns) = artifacts else {
+        return Ok(Vec::new());
+    };
+    if patterns.is_empty() {
+        return Err("artifacts".to_string());
+    }
+    let mut artifacts: Vec<String> = Vec::new();
+    for (index, pattern) in patterns.into_iter().enumerate() {
+        let is_valid = !pattern.is_empty()
+            && !pattern.starts_with('/')
+            && pattern
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-/*?[]+@=,:".contains(c))
+            && pattern.split('/').all(|component| component != "..");
+        if !is_valid {
+            return Err(format!("artifacts.{}", index));
+        }
+        if !artifacts.contains(&pattern) {
+            artifacts.push(pattern);
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Namespace of the matrix values in the container image and the commands, e.g. `${{ matrix.NODE }}`
+const MATRIX: &str = "matrix";
+/// Actions a single matrix can expand into
+const MAX_MATRIX_COMBINATIONS: usize = 256;
+
+/// The values an action is run with, by name, in the order of the manifest.
+type Matrix = Vec<(String, Vec<String>)>;
+
+fn parse_matrix(matrix: Option<IndexMap<String, Vec<EnvValue>>>) -> Result<Matrix, ParsingError> {
+    let Some(matrix) = matrix else {
+        return Ok(Vec::new());
+    };
+    let matrix: Matrix = matrix
+        .into_iter()
+        .map(|(name, values)| {
+            (
+                name,
+                values.into_iter().map(|EnvValue(value)| value).collect(),
+            )
+        })
+        .collect();
+    let combinations = matrix.iter().try_fold(1usize, |combinations, (_, values)| {
+        combinations.checked_mul(values.len())
+    });
+    if matrix.is_empty()
+        || !matrix.iter().all(|(name, _)| is_valid_name(name))
+        || !matches!(combinations, Some(1..=MAX_MATRIX_COMBINATIONS))
+    {
+        return Err(ParsingError::InvalidMatrix);
+    }
+    Ok(matrix)
+}
+
+/// The name and index of the first value listed twice in the matrix.
+fn duplicate_matrix_value(matrix: &Matrix) -> Option<(&str, usize)> {
+    matrix.iter().find_map(|(name, values)| {
+        (1..values.len())
+            .find(|&index| values[..index].contains(&values[index]))
+            .map(|index| (name.as_str(), index))
+    })
+}
+
+/// Every combination of the values of the matrix, the first values changing the least often.
+fn matrix_combinations(matrix: &Matrix) -> Vec<Vec<(&str, &str)>> {
+    matrix
+        .iter()
+        .fold(vec![Vec::new()], |combinations, (name, values)| {
+            combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.as_str(), value.as_str()));
+                        combination
+                    })
+                })
+                .collect()
+        })
+}
+
+/// Replace each action with a matrix by one action per combination of its values, named after them, e.g.
+/// `test (node:20)`. The actions needing it need all of them. Each action comes with its name in the manifest.
+fn expand_matrices(actions: Vec<(ManifestAction, Matrix)>) -> Vec<(ManifestAction, String)> {
+    let expanded: Vec<Vec<ManifestAction>> = actions
+        .iter()
+        .map(|(action, matrix)| {
+            if matrix.is_empty() {
+                return vec![action.clone()];
+            }
+            matrix_combinations(matrix)
+                .into_iter()
+                .map(|combination| {
+                    let values: HashMap<String, String> = combination
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect();
+                    let mut action = action.clone();
+                    action.name = format!(
+                        "{} ({})",
+                        action.name,
+                        combination
+                            .iter()
+                            .map(|(_, value)| *value)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    action.configuration_version =
+                        substitute_in(&action.configuration_version, MATRIX, &values);
+                    for command in action.commands.iter_mut() {
+                        *command = substitute_in(command, MATRIX, &values);
+                    }
+                    action.env.extend(values);
+                    action
+                })
+                .collect()
+        })
+        .collect();
+    let names: HashMap<&str, Vec<String>> = actions
+        .iter()
+        .zip(&expanded)
+        .map(|((action, _), expanded)| {
+            let names = expanded.iter().map(|action| action.name.clone()).collect();
+            (action.name.as_str(), names)
+        })
+        .collect();
+
+    actions
         .iter()
-        .map(|cmd| {
-            cmd.as_str()
-                .ok_or(ParsingError::YamlNotCompliant)
-                .map(String::from)
+        .zip(expanded.iter().cloned())
+        .flat_map(|((action, _), expanded)| {
+            expanded.into_iter().map(|mut expanded| {
+                expanded.needs = expanded
+                    .needs
+                    .iter()
+                    .flat_map(|need| names.get(need.as_str()).into_iter().flatten().cloned())
+                    .collect();
+                expanded.artifacts_from = expanded
+                    .artifacts_from
+                    .iter()
+                    .flat_map(|source| names.get(source.as_str()).into_iter().flatten().cloned())
+                    .collect();
+                (expanded, action.name.clone())
+            })
         })
         .collect()
 }
 
+/// Check that the actions only need actions of the pipeline, and that their dependencies do not form a cycle. The
+/// error comes with the action it is about.
+fn check_dependencies(actions: &[ManifestAction]) -> Result<(), (ParsingError, &str)> {
+    let names: HashSet<&str> = actions.iter().map(|action| action.name.as_str()).collect();
+    if let Some(action) = actions.iter().find(|action| {
+        action
+            .needs
+            .iter()
+            .any(|need| !names.contains(need.as_str()))
+    }) {
+        return Err((ParsingError::UnknownDependency, action.name.as_str()));
+    }
+
+    // Repeatedly take out the actions whose dependencies were all taken out: what remains is a cycle
+    let mut remaining: Vec<&ManifestAction> = actions.iter().collect();
+    let mut ordered: HashSet<&str> = HashSet::new();
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|action| {
+            action
+                .needs
+                .iter()
+                .all(|need| ordered.contains(need.as_str()))
+        });
+        if let (true, Some(action)) = (ready.is_empty(), blocked.first()) {
+            return Err((ParsingError::DependencyCycle, action.name.as_str()));
+        }
+        ordered.extend(ready.iter().map(|action| action.name.as_str()));
+        remaining = blocked;
+    }
+    Ok(())
+}
+
+/// Check that the actions only take artifacts from actions they need, which declare artifacts, so that these are
+/// there once they run. The error comes with the action it is about.
+fn check_artifact_sources(actions: &[ManifestAction]) -> Result<(), &str> {
+    let producers: HashSet<&str> = actions
+        .iter()
+        .filter(|action| !action.artifacts.is_empty())
+        .map(|action| action.name.as_str())
+        .collect();
+    match actions.iter().find(|action| {
+        action
+            .artifacts_from
+            .iter()
+            .any(|source| !action.needs.contains(source) || !producers.contains(source.as_str()))
+    }) {
+        Some(action) => Err(action.name.as_str()),
+        None => Ok(()),
+    }
+}
+
+/// Parse the commands of an action. Each command is a script run by the shell: either a string,
+/// possibly spanning multiple lines, or a `run` block.
+fn parse_commands(commands: Option<Vec<Command>>) -> Result<Vec<String>, ParsingError> {
+    let commands = commands
+        .filter(|commands| !commands.is_empty())
+        .ok_or(ParsingError::MissingCommands)?;
+    Ok(commands
+        .into_iter()
+        .map(|command| match command {
+            Command::Script(script) | Command::Block { run: script } => script,
+        })
+        .collect())
+}
+
 fn is_valid_action_name(name: &str) -> bool {
     let valid_chars = |c: char| c.is_alphanumeric() || c == ' ' || c == '&' || c == '-' || c == '_';
     !name.is_empty() && name.chars().all(valid_chars)
 }
 
-fn check_command_indentation(yaml: &str) -> Result<(), ParsingError> {
-    let lines: Vec<&str> = yaml.lines().collect();
-    let mut in_commands = false;
-    let mut command_indent = None;
+fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check that the items of each block `commands` sequence share the same indentation. A command indented deeper than
+/// the previous one would otherwise be folded into it as a continuation line. The lines nested in a command, such as
+/// a block scalar or a `run` block, and the lines after the sequence are not commands.
+fn check_command_indentation(yaml: &str) -> Result<(), ManifestError> {
+    // Indentation of the `commands` key, of its items, and whether the last item holds nested lines
+    let mut commands: Option<(usize, Option<usize>, bool)> = None;
 
-    for line in lines {
-        if line.trim().starts_with("commands:") {
-            in_commands = true;
+    for (number, line) in yaml.lines().enumerate() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
             continue;
         }
-        if in_commands && line.trim().starts_with('-') {
-            let indent = line.chars().take_while(|&c| c == ' ').count();
-            if let Some(prev_indent) = command_indent {
-                if indent != prev_indent {
-                    return Err(ParsingError::InconsistentCommandIndentation);
+        let indent = line.len() - content.len();
+        if let Some((key_indent, item_indent, nested)) = commands.as_mut() {
+            let is_item = content.starts_with('-');
+            if indent < *key_indent || (indent == *key_indent && !is_item) {
+                commands = None;
+            } else if is_item {
+                match *item_indent {
+                    None => *item_indent = Some(indent),
+                    Some(item) if item == indent => {}
+                    // A line of the previous command, e.g. of a script
+                    Some(item) if *nested && indent > item => continue,
+                    Some(_) => {
+                        return Err(ManifestError::new(
+                            ParsingError::InconsistentCommandIndentation,
+                            None,
+                            Some(Span {
+                                line: number + 1,
+                                column: indent + 1,
+                            }),
+                        ))
+                    }
+                }
+                *nested = opens_nested_lines(content);
+            }
+        }
+        if commands.is_none() {
+            if let Some(value) = content.strip_prefix("commands:") {
+                let value = value.trim();
+                // A flow sequence holds the commands on the same line
+                if value.is_empty() || value.starts_with('#') {
+                    commands = Some((indent, None, false));
                 }
-            } else {
-                command_indent = Some(indent);
             }
         }
     }
     Ok(())
 }
+
+/// Whether a command is followed by lines of its own: a block scalar, e.g. `- |`, or a mapping, e.g. `- run: |`.
+fn opens_nested_lines(item: &str) -> bool {
+    let value = item.trim_start_matches('-').trim();
+    value.starts_with("run:") || value.starts_with(['|', '>'])
+}
+
+/// Warn about the keys the parser ignores, most likely misspelled.
+fn unknown_keys(file: &ManifestFile) -> Vec<ManifestWarning> {
+    file.unknown_keys
+        .iter()
+        .filter_map(|path| {
+            let key = path.last()?;
+            let action = match path.as_slice() {
+                [actions, name, _, ..] if actions == "actions" => Some(name.clone()),
+                _ => None,
+            };
+            Some(ManifestWarning {
+                kind: WarningKind::UnknownKey,
+                message: format!("Unknown key `{}` is ignored", key),
+                action,
+                span: file.source.find(&path.join(".")),
+                include_chain: file.chain.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Warn about the actions whose image is not pinned to a tag or a digest.
+fn unpinned_images(
+    pipeline: &ManifestPipeline,
+    origins: &[(String, usize)],
+    files: &[ManifestFile],
+) -> Vec<ManifestWarning> {
+    let mut warned = HashSet::new();
+    pipeline
+        .actions
+        .iter()
+        .zip(origins)
+        .filter(|(action, _)| !is_pinned_image(&action.configuration_version))
+        // The actions of a matrix share their image line, a single warning is enough
+        .filter(|(_, (origin, _))| warned.insert(origin.as_str()))
+        .map(|(action, (origin, index))| {
+            let file = &files[*index];
+            ManifestWarning {
+                kind: WarningKind::UnpinnedImage,
+                message: format!(
+                    "Image `{}` is not pinned to a version, the action may run on a different image from one run to the next",
+                    action.configuration_version
+                ),
+                action: Some(origin.clone()),
+                span: file
+                    .source
+                    .find(&format!("actions.{}.configuration.container", origin)),
+                include_chain: file.chain.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Whether the image is given by digest, or by a tag other than `latest`.
+fn is_pinned_image(image: &str) -> bool {
+    if image.contains('@') {
+        return true;
+    }
+    // The registry may have a port, so only the last part of the name can hold the tag
+    let name = image.rsplit('/').next().unwrap_or(image);
+    matches!(name.split_once(':'), Some((_, tag)) if !tag.is_empty() && tag != "latest")
+}
+
+/// Where the nodes of a manifest start, by their path from the root, e.g. `actions.build.commands.0`. The entries of a
+/// mapping start at their key.
+struct SourceMap {
+    spans: HashMap<String, Span>,
+}
+
+impl SourceMap {
+    /// Map the first document of the manifest, failing when it is not valid YAML.
+    fn load(yaml: &str) -> Result<Self, ManifestError> {
+        let mut builder = SourceMapBuilder::default();
+        Parser::new(yaml.chars())
+            .load(&mut builder, false)
+            .map_err(|e| {
+                let mut error = ManifestError::new(
+                    ParsingError::YamlNotCompliant,
+                    None,
+                    Some(Span::from(*e.marker())),
+                );
+                #[allow(deprecated)]
+                let reason = std::error::Error::description(&e).to_string();
+                error.message = format!("The manifest is not valid YAML: {}", reason);
+                error
+            })?;
+        Ok(SourceMap {
+            spans: builder.spans,
+        })
+    }
+
+    fn find(&self, path: &str) -> Option<Span> {
+        self.spans.get(path).copied()
+    }
+
+    /// An error about the node at this path, or about its closest parent when the node is missing.
+    fn locate(&self, kind: ParsingError, action: Option<&str>, path: &str) -> ManifestError {
+        let mut path = path;
+        let span = loop {
+            if let Some(span) = self.find(path) {
+                break Some(span);
+            }
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None if !path.is_empty() => path = "",
+                None => break None,
+            }
+        };
+        ManifestError::new(kind, action, span)
+    }
+}
+
+enum Node {
+    Mapping { path: String, key: Option<String> },
+    Sequence { path: String, index: usize },
+}
+
+#[derive(Default)]
+struct SourceMapBuilder {
+    spans: HashMap<String, Span>,
+    stack: Vec<Node>,
+}
+
+impl SourceMapBuilder {
+    /// Record where a value starts, returning its path.
+    fn enter_value(&mut self, marker: Marker) -> String {
+        match self.stack.last() {
+            Some(Node::Mapping {
+                path,
+                key: Some(key),
+            }) => join_path(path, key),
+            Some(Node::Sequence { path, index }) => {
+                let path = join_path(path, &index.to_string());
+                self.spans.insert(path.clone(), Span::from(marker));
+                path
+            }
+            // Complex keys are not used by manifests, they are not located
+            Some(Node::Mapping { path, key: None }) => join_path(path, "?"),
+            None => {
+                self.spans.insert(String::new(), Span::from(marker));
+                String::new()
+            }
+        }
+    }
+
+    fn leave_value(&mut self) {
+        match self.stack.last_mut() {
+            Some(Node::Mapping { key, .. }) => *key = None,
+            Some(Node::Sequence { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for SourceMapBuilder {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::Scalar(value, ..) => {
+                if let Some(Node::Mapping {
+                    path,
+                    key: key @ None,
+                }) = self.stack.last_mut()
+                {
+                    self.spans
+                        .insert(join_path(path, &value), Span::from(marker));
+                    *key = Some(value);
+                } else {
+                    self.enter_value(marker);
+                    self.leave_value();
+                }
+            }
+            Event::Alias(_) => {
+                self.enter_value(marker);
+                self.leave_value();
+            }
+            Event::MappingStart(_) => {
+                let path = self.enter_value(marker);
+                self.stack.push(Node::Mapping { path, key: None });
+            }
+            Event::SequenceStart(_) => {
+                let path = self.enter_value(marker);
+                self.stack.push(Node::Sequence { path, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.leave_value();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -1,7 +1,13 @@
-use crate::action::action_repository::Action;
+use crate::{
+    action::action_repository::Action,
+    run::{PipelineRun, RunStatus},
+    timing::Timing,
+};
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
 pub mod pipeline_controller;
+pub mod pipeline_executor;
 pub mod pipeline_repository;
 pub mod pipeline_service;
 
@@ -10,16 +16,62 @@ pub struct Pipeline {
     pub id: i64,
     pub repository_url: String,
     pub name: String,
+    /// Status of the latest run
+    pub status: String,
+    /// Creation of the pipeline, then start and end of its latest run
+    #[serde(flatten)]
+    pub timing: Timing,
+    /// Actions of the latest run
     pub actions: Vec<Action>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<PipelineRun>,
 }
 
 impl Pipeline {
-    pub fn new(id: i64, repository_url: String, name: String, actions: Vec<Action>) -> Self {
+    pub fn new(
+        id: i64,
+        repository_url: String,
+        name: String,
+        created_at: OffsetDateTime,
+        actions: Vec<Action>,
+    ) -> Self {
         return Pipeline {
             id,
             repository_url,
             name,
+            status: RunStatus::Pending.to_string(),
+            timing: Timing::new(Some(created_at), None, None),
             actions,
+            last_run: None,
         };
     }
+
+    /// Attach the latest run, which gives the pipeline its status and timing.
+    pub fn set_last_run(&mut self, run: PipelineRun) {
+        self.status = run.status.clone();
+        self.timing = Timing::new(self.timing.created_at, run.started_at, run.finished_at);
+        self.last_run = Some(run);
+    }
+}
+
+/// Which pipelines to list, and from where.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineFilter {
+    pub repository_url: Option<String>,
+    /// Status of the latest run
+    pub status: Option<RunStatus>,
+    pub created_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+    /// Only the pipelines older than this one are listed
+    pub cursor: Option<i64>,
+    pub limit: i64,
+}
+
+/// A page of pipelines, the newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelinePage {
+    pub pipelines: Vec<Pipeline>,
+    /// Cursor of the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
 }
@@ -4,17 +4,52 @@ use actix_web::{
     web::{self},
     HttpResponse, Responder,
 };
-use serde::Deserialize;
-use std::{io::Read, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{io::Read, str::FromStr, sync::Arc};
+use time::OffsetDateTime;
 use tracing::info;
 
-use crate::{parser::pipe_parser::ParsingError, pipeline::pipeline_service::PipelineService};
+use crate::{
+    parser::pipe_parser::{IncludedFiles, ManifestError, ManifestWarning},
+    pipeline::{
+        pipeline_service::{PipelineService, PipelineServiceError},
+        PipelineFilter,
+    },
+    run::{RunStatus, RunTrigger},
+};
+
+#[derive(Debug, MultipartForm)]
+struct ValidatePipelineForm {
+    #[multipart(rename = "body")]
+    file: TempFile,
+    /// Manifests the manifest includes, named by their path from its directory
+    #[multipart(rename = "include")]
+    includes: Vec<TempFile>,
+}
+
+/// Outcome of the validation of a manifest.
+#[derive(Debug, Serialize)]
+struct ValidationReport {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ManifestError>,
+    warnings: Vec<ManifestWarning>,
+}
 
 #[derive(Debug, MultipartForm)]
 struct UploadPipelineForm {
     #[multipart(rename = "body")]
     file: TempFile,
+    /// Manifests the manifest includes, named by their path from its directory
+    #[multipart(rename = "include")]
+    includes: Vec<TempFile>,
     repo_url: MpText<String>,
+    /// What started the run, `manual` if absent
+    trigger: Option<MpText<String>>,
+    /// Commit of the repository the run is for
+    commit_sha: Option<MpText<String>>,
+    /// Branch of the repository the run is for
+    branch: Option<MpText<String>>,
 }
 
 #[derive(Deserialize)]
@@ -22,19 +57,74 @@ struct PipelineByIDQuery {
     id: i64,
 }
 
+#[derive(Deserialize)]
+struct ActionByIDQuery {
+    id: i64,
+    action_id: i64,
+}
+
+#[derive(Deserialize)]
+struct RunByNumberQuery {
+    id: i64,
+    number: i32,
+}
+
 #[derive(Deserialize)]
 struct PipelineQueryParams {
     verbose: Option<bool>,
 }
 
+/// Pipelines listed per page when no limit is given
+const DEFAULT_PAGE_SIZE: i64 = 20;
+/// Pipelines listed per page, at most
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+struct PipelineListQueryParams {
+    verbose: Option<bool>,
+    repository_url: Option<String>,
+    /// Status of the latest run
+    status: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    created_after: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    created_before: Option<OffsetDateTime>,
+    /// `next_cursor` of the previous page
+    cursor: Option<i64>,
+    limit: Option<i64>,
+}
+
 #[get("/pipeline")]
 pub async fn get_pipelines(
     pipeline_service: web::Data<Arc<PipelineService>>,
-    query: web::Query<PipelineQueryParams>,
+    query: web::Query<PipelineListQueryParams>,
 ) -> impl Responder {
+    let query = query.into_inner();
     let verbose = query.verbose.unwrap_or(false);
-    let pipelines = pipeline_service.find_all(verbose).await;
-    HttpResponse::Ok().json(pipelines)
+    let status = match query.status {
+        Some(status) => match RunStatus::from_str(status.as_str()) {
+            Ok(status) => Some(status),
+            Err(_) => return HttpResponse::BadRequest().body("Invalid status"),
+        },
+        None => None,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return HttpResponse::BadRequest().body(format!("Limit must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    let filter = PipelineFilter {
+        repository_url: query.repository_url,
+        status,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        cursor: query.cursor,
+        limit,
+    };
+
+    match pipeline_service.find_all(&filter, verbose).await {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
 }
 
 #[get("/pipeline/{id}")]
@@ -64,48 +154,179 @@ pub async fn create_pipeline(
         form.repo_url.as_str()
     );
     let repo_url = form.repo_url.as_str();
-    let f = form.file;
-    let file_name = match f.file_name {
-        Some(file_name) => file_name,
-        None => return HttpResponse::UnprocessableEntity().body("Invalid file name"),
+    let trigger = match form.trigger {
+        Some(trigger) => match RunTrigger::from_str(trigger.as_str()) {
+            Ok(trigger) => trigger,
+            Err(_) => return HttpResponse::BadRequest().body("Invalid trigger"),
+        },
+        None => RunTrigger::Manual,
     };
-    let _path = format!("./tmp/{}", file_name);
-    let mut fd_manifest = match f.file.reopen() {
-        Ok(fd) => fd,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+    let commit_sha = form.commit_sha.map(|commit_sha| commit_sha.into_inner());
+    let branch = form.branch.map(|branch| branch.into_inner());
+    let buffer = match read_manifest(form.file) {
+        Ok(buffer) => buffer,
+        Err(response) => return response,
+    };
+    let includes = match read_includes(form.includes) {
+        Ok(includes) => includes,
+        Err(response) => return response,
     };
-    let mut buffer = String::new();
-    match fd_manifest.read_to_string(&mut buffer) {
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::InvalidData {
-                return HttpResponse::UnprocessableEntity().body("Invalid data");
-            }
-        }
-        Ok(_) => {
-            println!("Read {} bytes", buffer);
-        }
-    }
 
-    match pipeline_service.try_parse_pipeline(buffer) {
+    match pipeline_service.try_parse_pipeline(buffer, &includes) {
         Ok(workflow) => {
             if let Ok(pipeline) = pipeline_service
-                .create_pipeline_with_actions(workflow, repo_url.to_string())
+                .create_pipeline_with_actions(
+                    workflow,
+                    repo_url.to_string(),
+                    trigger,
+                    commit_sha,
+                    branch,
+                )
                 .await
             {
-                for action in &pipeline.actions {
-                    info!("Sending action: {:?}", action);
-                    pipeline_service
-                        .send_action(Arc::new(action.clone()), repo_url.to_string())
-                        .await
-                        .unwrap();
-                }
+                pipeline_service.execute_pipeline(&pipeline);
                 return HttpResponse::Ok().json(pipeline);
             } else {
                 info!("Error while creating pipeline");
                 return HttpResponse::InternalServerError().finish();
             }
         }
-        Err(ParsingError::YamlNotCompliant) => HttpResponse::BadRequest().body("Invalid yaml"),
-        Err(err) => HttpResponse::BadRequest().body(format!("{:?}", err)), //TODO: replace this by exhaustive match
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
+
+/// Check a manifest the way `POST /pipeline` does, without running anything.
+#[post("/pipeline/validate")]
+pub async fn validate_pipeline(
+    MultipartForm(form): MultipartForm<ValidatePipelineForm>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+) -> impl Responder {
+    info!("Validating manifest of size: {}", form.file.size);
+    let buffer = match read_manifest(form.file) {
+        Ok(buffer) => buffer,
+        Err(response) => return response,
+    };
+    let includes = match read_includes(form.includes) {
+        Ok(includes) => includes,
+        Err(response) => return response,
+    };
+
+    match pipeline_service.validate_pipeline(buffer, &includes) {
+        Ok(validation) => HttpResponse::Ok().json(ValidationReport {
+            valid: true,
+            error: None,
+            warnings: validation.warnings,
+        }),
+        Err(err) => HttpResponse::UnprocessableEntity().json(ValidationReport {
+            valid: false,
+            error: Some(err),
+            warnings: Vec::new(),
+        }),
+    }
+}
+
+/// Read the uploaded manifest, or the response to send back when it cannot be read.
+fn read_manifest(file: TempFile) -> Result<String, HttpResponse> {
+    if file.file_name.is_none() {
+        return Err(HttpResponse::UnprocessableEntity().body("Invalid file name"));
+    }
+    let mut fd_manifest = file
+        .file
+        .reopen()
+        .map_err(|_| HttpResponse::InternalServerError().finish())?;
+    let mut buffer = String::new();
+    if let Err(e) = fd_manifest.read_to_string(&mut buffer) {
+        if e.kind() == std::io::ErrorKind::InvalidData {
+            return Err(HttpResponse::UnprocessableEntity().body("Invalid data"));
+        }
+    }
+    Ok(buffer)
+}
+
+/// Read the uploaded included manifests by path, or the response to send back when one cannot be read.
+fn read_includes(files: Vec<TempFile>) -> Result<IncludedFiles, HttpResponse> {
+    let mut includes = IncludedFiles::new();
+    for file in files {
+        let Some(path) = file.file_name.clone() else {
+            return Err(HttpResponse::UnprocessableEntity().body("Invalid file name"));
+        };
+        includes.insert(path, read_manifest(file)?);
+    }
+    Ok(includes)
+}
+
+#[get("/pipeline/{id}/runs")]
+pub async fn get_runs(
+    path: web::Path<PipelineByIDQuery>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+) -> impl Responder {
+    match pipeline_service.find_runs(path.id).await {
+        Ok(runs) => HttpResponse::Ok().json(runs),
+        Err(PipelineServiceError::PipelineNotFound) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[get("/pipeline/{id}/runs/{number}")]
+pub async fn get_run(
+    path: web::Path<RunByNumberQuery>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+    query: web::Query<PipelineQueryParams>,
+) -> impl Responder {
+    let verbose = query.verbose.unwrap_or(false);
+    match pipeline_service.find_run(path.id, path.number, verbose).await {
+        Ok(run) => HttpResponse::Ok().json(run),
+        Err(PipelineServiceError::RunNotFound) => HttpResponse::NotFound().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[post("/pipeline/{id}/runs")]
+pub async fn rerun_pipeline(
+    path: web::Path<PipelineByIDQuery>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+) -> impl Responder {
+    info!("Re-running pipeline with id: {}", path.id);
+    match pipeline_service.rerun_pipeline(path.id).await {
+        Ok(pipeline) => {
+            pipeline_service.execute_pipeline(&pipeline);
+            HttpResponse::Created().json(pipeline)
+        }
+        Err(PipelineServiceError::PipelineNotFound) | Err(PipelineServiceError::RunNotFound) => {
+            HttpResponse::NotFound().finish()
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+#[post("/pipeline/{id}/cancel")]
+pub async fn cancel_pipeline(
+    path: web::Path<PipelineByIDQuery>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+) -> impl Responder {
+    info!("Cancelling pipeline with id: {}", path.id);
+    cancellation_response(pipeline_service.cancel_pipeline(path.id).await)
+}
+
+#[post("/pipeline/{id}/actions/{action_id}/cancel")]
+pub async fn cancel_action(
+    path: web::Path<ActionByIDQuery>,
+    pipeline_service: web::Data<Arc<PipelineService>>,
+) -> impl Responder {
+    info!("Cancelling action {} of pipeline {}", path.action_id, path.id);
+    cancellation_response(pipeline_service.cancel_action(path.id, path.action_id).await)
+}
+
+fn cancellation_response(result: Result<(), PipelineServiceError>) -> HttpResponse {
+    match result {
+        // The cancellation is asynchronous: the action status becomes cancelled once the scheduler confirms it.
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(PipelineServiceError::PipelineNotFound) | Err(PipelineServiceError::ActionNotFound) => {
+            HttpResponse::NotFound().finish()
+        }
+        Err(PipelineServiceError::ActionFinished) => {
+            HttpResponse::Conflict().body("Action is already finished")
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
     }
 }
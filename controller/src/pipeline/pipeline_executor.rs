@@ -0,0 +1,265 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use tokio::task::JoinSet;
+use tracing::{error, info};
+
+use crate::action::action_repository::Action;
+use crate::action::action_service::{is_finished, ActionService};
+use crate::grpc_scheduler::ActionStatus;
+use crate::logs::{log_hub::LogHub, log_repository::LogRepository};
+use crate::parser::condition::{Condition, ConditionContext};
+use crate::pipeline::pipeline_service::PipelineServiceError;
+use crate::run::run_repository::RunRepository;
+use crate::run::{PipelineRun, RunStatus};
+use crate::scheduler::SchedulerService;
+
+/// Runs the actions of a pipeline along their dependencies: once every action an action needs is over, the action is
+/// sent to the scheduler if its condition holds, and skipped otherwise. Without condition, an action runs when every
+/// action it needs completed successfully.
+pub struct PipelineExecutor {
+    client: Arc<SchedulerService>,
+    action_service: Arc<ActionService>,
+    logs_repository: Arc<LogRepository>,
+    run_repository: Arc<RunRepository>,
+    log_hub: Arc<LogHub>,
+}
+
+impl PipelineExecutor {
+    pub fn new(
+        client: Arc<SchedulerService>,
+        action_service: Arc<ActionService>,
+        logs_repository: Arc<LogRepository>,
+        run_repository: Arc<RunRepository>,
+        log_hub: Arc<LogHub>,
+    ) -> Self {
+        Self {
+            client,
+            action_service,
+            logs_repository,
+            run_repository,
+            log_hub,
+        }
+    }
+
+    /// Run the actions of a run until every one of them is over, then record the status of the run.
+    /// The dependencies are checked by the parser, so they only name actions of the run and do not form a cycle.
+    pub async fn execute(&self, run: PipelineRun, actions: Vec<Action>, repo_url: String) {
+        let run_id = run.id;
+        if let Err(e) = self
+            .run_repository
+            .update_status(run_id, RunStatus::Running)
+            .await
+        {
+            error!("Error while starting run {}: {:?}", run_id, e);
+        }
+        let mut progress = Progress::new(&actions);
+        let mut running = JoinSet::new();
+
+        loop {
+            while let Some(index) = progress.ready.pop_front() {
+                let action = &actions[index];
+                let status = match self.cancelled_status(action).await {
+                    Some(status) => status,
+                    None => match progress.skip_reason(index, &actions, &run) {
+                        Some(reason) => self.skip(action, &reason).await,
+                        None => {
+                            info!("Sending action: {:?}", action);
+                            let client = Arc::clone(&self.client);
+                            let action = Arc::new(action.clone());
+                            let repo_url = repo_url.clone();
+                            running.spawn(async move {
+                                // The action is sent from its own task, so that its index is kept even if it panics
+                                let sent = tokio::spawn(async move {
+                                    client.send_action(action, repo_url).await
+                                });
+                                let result = sent.await.unwrap_or_else(|e| {
+                                    error!("Error while running action: {:?}", e);
+                                    Err(PipelineServiceError::SchedulerError)
+                                });
+                                (index, result)
+                            });
+                            continue;
+                        }
+                    },
+                };
+                progress.finish(index, status);
+                self.log_hub.close(action.id);
+            }
+
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let (index, result) = match joined {
+                Ok(joined) => joined,
+                // Only reachable if the runtime shuts down, the action task does not panic
+                Err(e) => {
+                    error!("Error while running action: {:?}", e);
+                    continue;
+                }
+            };
+
+            let status = self.final_status(&actions[index], result).await;
+            progress.finish(index, status);
+            self.log_hub.close(actions[index].id);
+        }
+
+        let status = RunStatus::aggregate(progress.statuses.into_iter().flatten());
+        info!("Run {} is over: {}", run_id, status);
+        if let Err(e) = self.run_repository.update_status(run_id, status).await {
+            error!("Error while finishing run {}: {:?}", run_id, e);
+        }
+    }
+
+    /// The status of the action if it was cancelled while it waited for the actions it needs.
+    async fn cancelled_status(&self, action: &Action) -> Option<ActionStatus> {
+        match self.action_service.find(action.id).await {
+            Ok(action) if is_finished(&action.status) => {
+                ActionStatus::from_str_name(&action.status)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error!("Error while fetching action {}: {:?}", action.id, e);
+                None
+            }
+        }
+    }
+
+    /// The final status of the action, given the result of its execution.
+    async fn final_status(
+        &self,
+        action: &Action,
+        result: Result<ActionStatus, PipelineServiceError>,
+    ) -> ActionStatus {
+        match result {
+            Ok(status) if is_finished(status.as_str_name()) => return status,
+            Ok(status) => error!(
+                "Action {} ended without a final status, last status: {}",
+                action.id,
+                status.as_str_name()
+            ),
+            Err(e) => error!("Error while running action {}: {:?}", action.id, e),
+        }
+
+        // Otherwise the action would be left pending or running forever
+        if let Err(e) = self
+            .action_service
+            .update_status(action.id, &ActionStatus::Error)
+            .await
+        {
+            error!("Error while updating action status: {:?}", e);
+        }
+        ActionStatus::Error
+    }
+
+    /// Record that the action does not run, and why.
+    async fn skip(&self, action: &Action, reason: &str) -> ActionStatus {
+        let log = format!("Skipped because {}", reason);
+        info!("Action {}: {}", action.id, log);
+        match self.logs_repository.create(action.id, 1, &log).await {
+            Ok(log) => self.log_hub.publish(action.id, log),
+            Err(e) => error!("Error while storing log: {:?}", e),
+        }
+        if let Err(e) = self
+            .action_service
+            .update_status(action.id, &ActionStatus::Skipped)
+            .await
+        {
+            error!("Error while updating action status: {:?}", e);
+        }
+        ActionStatus::Skipped
+    }
+}
+
+/// Where the actions of a run are along their dependencies.
+struct Progress {
+    /// Indexes of the actions each action needs
+    needed: Vec<Vec<usize>>,
+    /// Indexes of the actions needing each action
+    dependents: Vec<Vec<usize>>,
+    /// Number of needed actions each action still waits for
+    waiting: Vec<usize>,
+    /// Actions no longer waiting for any other, to be sent or skipped
+    ready: VecDeque<usize>,
+    /// Final status of each action
+    statuses: Vec<Option<ActionStatus>>,
+    /// Whether each action, or one it needs directly or not, failed
+    failed: Vec<bool>,
+}
+
+impl Progress {
+    fn new(actions: &[Action]) -> Self {
+        let indexes: HashMap<&str, usize> = actions
+            .iter()
+            .enumerate()
+            .map(|(index, action)| (action.name.as_str(), index))
+            .collect();
+        let needed: Vec<Vec<usize>> = actions
+            .iter()
+            .map(|action| {
+                action
+                    .needs
+                    .iter()
+                    .filter_map(|need| indexes.get(need.as_str()).copied())
+                    .collect()
+            })
+            .collect();
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); actions.len()];
+        for (index, needs) in needed.iter().enumerate() {
+            for &need in needs {
+                dependents[need].push(index);
+            }
+        }
+        let waiting: Vec<usize> = needed.iter().map(Vec::len).collect();
+        let ready = (0..actions.len())
+            .filter(|&index| waiting[index] == 0)
+            .collect();
+        Self {
+            needed,
+            dependents,
+            waiting,
+            ready,
+            statuses: vec![None; actions.len()],
+            failed: vec![false; actions.len()],
+        }
+    }
+
+    /// Record the final status of an action, and make ready the actions needing it that no longer wait for any
+    /// other.
+    fn finish(&mut self, index: usize, status: ActionStatus) {
+        self.statuses[index] = Some(status);
+        self.failed[index] = matches!(status, ActionStatus::Error | ActionStatus::TimedOut)
+            || self.needed[index].iter().any(|&need| self.failed[need]);
+        for &dependent in &self.dependents[index] {
+            self.waiting[dependent] -= 1;
+            if self.waiting[dependent] == 0 {
+                self.ready.push_back(dependent);
+            }
+        }
+    }
+
+    /// Why the ready action does not run, if it does not.
+    fn skip_reason(&self, index: usize, actions: &[Action], run: &PipelineRun) -> Option<String> {
+        let not_completed = self.needed[index]
+            .iter()
+            .find(|&&need| self.statuses[need] != Some(ActionStatus::Completed));
+        let context = ConditionContext {
+            success: not_completed.is_none(),
+            failure: self.needed[index].iter().any(|&need| self.failed[need]),
+            event: &run.trigger,
+            branch: run.branch.as_deref().unwrap_or_default(),
+        };
+        let Some(condition) = &actions[index].condition else {
+            return not_completed
+                .map(|&need| format!("{} did not complete successfully", actions[need].name));
+        };
+        match Condition::parse(condition) {
+            Ok(parsed) if parsed.evaluate(&context) => None,
+            Ok(_) => Some(format!("its condition `{}` is false", condition)),
+            // The parser checks the conditions, so this one was stored by another version of the controller
+            Err(e) => Some(format!("its condition `{}` is not valid: {}", condition, e)),
+        }
+    }
+}
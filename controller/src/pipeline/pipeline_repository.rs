@@ -1,35 +1,67 @@
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::{collections::HashMap, str::FromStr, sync::Arc};
+use time::OffsetDateTime;
 
-use crate::{action::action_repository::Action, parser::pipe_parser::Type};
+use crate::{
+    action::{action_repository::Action, action_service::ActionDTO},
+    parser::pipe_parser::Type,
+    run::run_repository::PipelineRunDTO,
+};
 
-use super::Pipeline;
+use super::{Pipeline, PipelineFilter};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineDTO {
     pub id: i64,
     pub name: String,
     pub repository_url: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
 }
 
 pub struct PipelineDetailDTO {
     pub pipeline_id: i64,
     pub pipeline_name: String,
     pub repository_url: String,
+    pub pipeline_created_at: OffsetDateTime,
     pub action_id: i64,
+    pub action_run_id: i64,
     pub action_name: String,
     pub action_container_uri: String,
     pub action_status: String,
     pub action_type: String,
+    pub action_labels: Vec<String>,
+    pub action_timeout: Option<i32>,
+    pub action_shell: Option<String>,
+    pub action_env: Vec<String>,
+    pub action_needs: Vec<String>,
+    pub action_condition: Option<String>,
+    pub action_retry_max_attempts: i32,
+    pub action_retry_backoff: i32,
+    pub action_retry_on: Vec<String>,
+    pub action_artifacts: Vec<String>,
+    pub action_artifacts_from: Vec<String>,
+    pub action_created_at: OffsetDateTime,
+    pub action_started_at: Option<OffsetDateTime>,
+    pub action_finished_at: Option<OffsetDateTime>,
     pub command: String,
 }
 
-struct ActionDetail {
-    repository_url: String,
-    pipeline_id: i64,
-    pipeline_name: String,
-    action: Action,
+/// A pipeline along with its latest run, if it has one.
+pub struct PipelineSummaryDTO {
+    pub id: i64,
+    pub name: String,
+    pub repository_url: String,
+    pub created_at: OffsetDateTime,
+    pub run_id: Option<i64>,
+    pub run_number: Option<i32>,
+    pub run_trigger: Option<String>,
+    pub run_commit_sha: Option<String>,
+    pub run_branch: Option<String>,
+    pub run_status: Option<String>,
+    pub run_started_at: Option<OffsetDateTime>,
+    pub run_finished_at: Option<OffsetDateTime>,
 }
 
 pub struct PipelineRepository {
@@ -41,79 +73,113 @@ impl PipelineRepository {
         Self { pool }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<Pipeline>, sqlx::Error> {
+    /// A page of pipelines, the newest first, with the actions of their latest run. Loading the commands of the
+    /// actions is left to the callers that need them.
+    pub async fn find_all(
+        &self,
+        filter: &PipelineFilter,
+        with_commands: bool,
+    ) -> Result<Vec<Pipeline>, sqlx::Error> {
         let rows = sqlx::query_as!(
-            PipelineDetailDTO,
-            r#"SELECT pipelines.id as pipeline_id,
-               pipelines.name as pipeline_name,
-               pipelines.repository_url as repository_url,
-               a.id as action_id,
-               a.name as action_name,
-               a.container_uri as action_container_uri,
-               a.status as action_status,
-               a.type as action_type,
-               c.command as command
-        FROM pipelines
-                 JOIN actions a on pipelines.id = a.pipeline_id
-                 JOIN commands c on c.action_id = a.id
-            ORDER BY pipelines.id, a.id;"#
+            PipelineSummaryDTO,
+            r#"SELECT p.id, p.name, p.repository_url, p.created_at,
+               r.id as "run_id?",
+               r.number as "run_number?",
+               r.trigger as "run_trigger?",
+               r.commit_sha as "run_commit_sha?",
+               r.branch as "run_branch?",
+               r.status as "run_status?",
+               r.started_at as "run_started_at?",
+               r.finished_at as "run_finished_at?"
+        FROM pipelines p
+                 LEFT JOIN LATERAL (SELECT * FROM pipeline_runs WHERE pipeline_id = p.id ORDER BY number DESC LIMIT 1) r ON TRUE
+        WHERE ($1::BIGINT IS NULL OR p.id < $1)
+          AND ($2::VARCHAR IS NULL OR p.repository_url = $2)
+          AND ($3::VARCHAR IS NULL OR r.status = $3)
+          AND ($4::TIMESTAMPTZ IS NULL OR p.created_at >= $4)
+          AND ($5::TIMESTAMPTZ IS NULL OR p.created_at < $5)
+        ORDER BY p.id DESC
+        LIMIT $6"#,
+            filter.cursor,
+            filter.repository_url,
+            filter.status.map(|status| status.to_string()),
+            filter.created_after,
+            filter.created_before,
+            filter.limit
         )
         .fetch_all(&*self.pool)
         .await?;
 
-        let mut pipelines: Vec<Pipeline> = Vec::new();
-        let mut actions: HashMap<i64, ActionDetail> = HashMap::new();
-        let mut pipelines_map: HashMap<i64, Pipeline> = HashMap::new();
-        for row in rows {
-            let command = row.command;
-            let action_id = row.action_id;
-            if actions.contains_key(&action_id) {
-                let action = actions.get_mut(&action_id).unwrap();
-                action.action.commands.push(command);
-            } else {
-                let action = Action::new(
-                    row.action_id,
-                    row.pipeline_id,
-                    row.action_name,
-                    row.action_container_uri,
-                    vec![command],
-                    Type::from_str(row.action_type.as_str()).unwrap(),
-                    row.action_status,
-                )
-                .unwrap();
-                actions.insert(
-                    action_id,
-                    ActionDetail {
-                        repository_url: row.repository_url.clone(),
-                        pipeline_id: row.pipeline_id,
-                        pipeline_name: row.pipeline_name.clone(),
-                        action,
-                    },
-                );
-            }
-        }
+        let run_ids: Vec<i64> = rows.iter().filter_map(|row| row.run_id).collect();
+        let action_rows = sqlx::query_as!(
+            ActionDTO,
+            r#"SELECT * FROM actions WHERE run_id = ANY($1) ORDER BY id"#,
+            &run_ids
+        )
+        .fetch_all(&*self.pool)
+        .await?;
 
-        for (_, action_detail) in actions.iter() {
-            let pipeline_id = action_detail.pipeline_id;
-            if pipelines_map.contains_key(&pipeline_id) {
-                let pipeline = pipelines_map.get_mut(&pipeline_id).unwrap();
-                pipeline.actions.push(action_detail.action.clone());
-            } else {
-                let pipeline = Pipeline::new(
-                    pipeline_id,
-                    action_detail.repository_url.clone(),
-                    action_detail.pipeline_name.clone(),
-                    vec![action_detail.action.clone()],
-                );
-                pipelines_map.insert(pipeline_id, pipeline);
+        let mut commands: HashMap<i64, Vec<String>> = HashMap::new();
+        if with_commands {
+            let action_ids: Vec<i64> = action_rows.iter().filter_map(|action| action.id).collect();
+            let command_rows = sqlx::query!(
+                r#"SELECT action_id, command FROM commands WHERE action_id = ANY($1) ORDER BY id"#,
+                &action_ids
+            )
+            .fetch_all(&*self.pool)
+            .await?;
+            for row in command_rows {
+                commands.entry(row.action_id).or_default().push(row.command);
             }
         }
 
-        for (_, pipeline) in pipelines_map.iter() {
-            pipelines.push(pipeline.clone());
+        let mut actions: HashMap<i64, Vec<Action>> = HashMap::new();
+        for row in action_rows {
+            let action_commands = row
+                .id
+                .and_then(|id| commands.remove(&id))
+                .unwrap_or_default();
+            actions
+                .entry(row.run_id)
+                .or_default()
+                .push(action_from_dto(row, action_commands)?);
         }
 
-        Ok(pipelines)
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let run_actions = row
+                    .run_id
+                    .and_then(|run_id| actions.remove(&run_id))
+                    .unwrap_or_default();
+                let mut pipeline = Pipeline::new(
+                    row.id,
+                    row.repository_url,
+                    row.name,
+                    row.created_at,
+                    run_actions,
+                );
+                if let (Some(id), Some(number), Some(trigger), Some(status)) =
+                    (row.run_id, row.run_number, row.run_trigger, row.run_status)
+                {
+                    pipeline.set_last_run(
+                        PipelineRunDTO {
+                            id,
+                            pipeline_id: row.id,
+                            number,
+                            trigger,
+                            commit_sha: row.run_commit_sha,
+                            branch: row.run_branch,
+                            status,
+                            started_at: row.run_started_at,
+                            finished_at: row.run_finished_at,
+                        }
+                        .into(),
+                    );
+                }
+                pipeline
+            })
+            .collect())
     }
 
     pub async fn find_by_id(&self, id: i64) -> Result<Pipeline, sqlx::Error> {
@@ -122,66 +188,59 @@ impl PipelineRepository {
             r#"SELECT pipelines.id as pipeline_id,
                pipelines.name as pipeline_name,
                pipelines.repository_url as repository_url,
+               pipelines.created_at as pipeline_created_at,
                a.id as action_id,
+               a.run_id as action_run_id,
                a.name as action_name,
                a.container_uri as action_container_uri,
                a.status as action_status,
                a.type as action_type,
+               a.labels as action_labels,
+               a.timeout as action_timeout,
+               a.shell as action_shell,
+               a.env as action_env,
+               a.needs as action_needs,
+               a.condition as action_condition,
+               a.retry_max_attempts as action_retry_max_attempts,
+               a.retry_backoff as action_retry_backoff,
+               a.retry_on as action_retry_on,
+               a.artifacts as action_artifacts,
+               a.artifacts_from as action_artifacts_from,
+               a.created_at as action_created_at,
+               a.started_at as action_started_at,
+               a.finished_at as action_finished_at,
                c.command as command
         FROM pipelines
                  JOIN actions a on pipelines.id = a.pipeline_id
                  JOIN commands c on c.action_id = a.id
         WHERE pipelines.id = $1
-        ORDER BY pipelines.id, a.id;"#,
+          AND a.run_id = (SELECT MAX(r.id) FROM pipeline_runs r WHERE r.pipeline_id = pipelines.id)
+        ORDER BY a.id, c.id;"#,
             id
         )
         .fetch_all(&*self.pool)
         .await?;
 
-        let mut actions_map: HashMap<i64, ActionDetail> = HashMap::new();
-
         let mut pipeline_id: i64 = 0;
         let mut pipeline_name: String = String::new();
         let mut repository_url: String = String::new();
+        let mut created_at = OffsetDateTime::UNIX_EPOCH;
 
+        let mut actions: Vec<Action> = Vec::new();
         for row in rows {
             pipeline_id = row.pipeline_id;
             pipeline_name = row.pipeline_name.clone();
             repository_url = row.repository_url.clone();
+            created_at = row.pipeline_created_at;
 
-            let command = row.command;
-            let action_id = row.action_id;
-            if actions_map.contains_key(&action_id) {
-                let action = actions_map.get_mut(&action_id).unwrap();
-                action.action.commands.push(command);
-            } else {
-                let action = Action::new(
-                    row.action_id,
-                    row.pipeline_id,
-                    row.action_name,
-                    row.action_container_uri,
-                    vec![command],
-                    Type::from_str(row.action_type.as_str()).unwrap(),
-                    row.action_status,
-                )
-                .unwrap();
-                actions_map.insert(
-                    action_id,
-                    ActionDetail {
-                        repository_url: row.repository_url.clone(),
-                        pipeline_id: row.pipeline_id,
-                        pipeline_name: row.pipeline_name.clone(),
-                        action,
-                    },
-                );
+            match actions.last_mut() {
+                Some(action) if action.id == row.action_id => action.commands.push(row.command),
+                _ => {
+                    actions.push(action_from_row(row)?);
+                }
             }
         }
 
-        let mut actions = Vec::new();
-        for (_, action) in actions_map.iter() {
-            actions.push(action.action.clone());
-        }
-
         if pipeline_id == 0 {
             return Err(sqlx::Error::RowNotFound); // no result found since there can not be a pipeline with id 0
         }
@@ -190,10 +249,88 @@ impl PipelineRepository {
             pipeline_id,
             repository_url,
             pipeline_name,
+            created_at,
             actions,
         ))
     }
 
+    /// The actions of a run, with their commands, in their creation order.
+    pub async fn find_run_actions(&self, run_id: i64) -> Result<Vec<Action>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            PipelineDetailDTO,
+            r#"SELECT pipelines.id as pipeline_id,
+               pipelines.name as pipeline_name,
+               pipelines.repository_url as repository_url,
+               pipelines.created_at as pipeline_created_at,
+               a.id as action_id,
+               a.run_id as action_run_id,
+               a.name as action_name,
+               a.container_uri as action_container_uri,
+               a.status as action_status,
+               a.type as action_type,
+               a.labels as action_labels,
+               a.timeout as action_timeout,
+               a.shell as action_shell,
+               a.env as action_env,
+               a.needs as action_needs,
+               a.condition as action_condition,
+               a.retry_max_attempts as action_retry_max_attempts,
+               a.retry_backoff as action_retry_backoff,
+               a.retry_on as action_retry_on,
+               a.artifacts as action_artifacts,
+               a.artifacts_from as action_artifacts_from,
+               a.created_at as action_created_at,
+               a.started_at as action_started_at,
+               a.finished_at as action_finished_at,
+               c.command as command
+        FROM pipelines
+                 JOIN actions a on pipelines.id = a.pipeline_id
+                 JOIN commands c on c.action_id = a.id
+        WHERE a.run_id = $1
+        ORDER BY a.id, c.id;"#,
+            run_id
+        )
+        .fetch_all(&*self.pool)
+        .await?;
+
+        let mut actions: Vec<Action> = Vec::new();
+        for row in rows {
+            match actions.last_mut() {
+                Some(action) if action.id == row.action_id => action.commands.push(row.command),
+                _ => {
+                    actions.push(action_from_row(row)?);
+                }
+            }
+        }
+        Ok(actions)
+    }
+
+    pub async fn find_pipeline(&self, id: i64) -> Result<Option<PipelineDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineDTO,
+            r#"SELECT * FROM pipelines WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
+    /// The latest pipeline of this name for the repository, new runs are attached to it.
+    pub async fn find_by_repository_and_name(
+        &self,
+        repository_url: &String,
+        name: &String,
+    ) -> Result<Option<PipelineDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineDTO,
+            r#"SELECT * FROM pipelines WHERE repository_url = $1 AND name = $2 ORDER BY id DESC LIMIT 1"#,
+            repository_url,
+            name
+        )
+        .fetch_optional(&*self.pool)
+        .await
+    }
+
     pub async fn create(
         &self,
         repository_url: &String,
@@ -211,3 +348,37 @@ impl PipelineRepository {
         Ok(row)
     }
 }
+
+fn action_from_dto(row: ActionDTO, commands: Vec<String>) -> Result<Action, sqlx::Error> {
+    let id = row.id.unwrap_or_default();
+    Action::new(row, commands)
+        .map_err(|e| sqlx::Error::Decode(format!("Invalid action {}: {:?}", id, e).into()))
+}
+
+/// Build the action of a joined row, with the command of the row as its first one.
+fn action_from_row(row: PipelineDetailDTO) -> Result<Action, sqlx::Error> {
+    let dto = ActionDTO {
+        id: Some(row.action_id),
+        pipeline_id: row.pipeline_id,
+        run_id: row.action_run_id,
+        name: row.action_name,
+        container_uri: row.action_container_uri,
+        r#type: Type::from_str(row.action_type.as_str()).unwrap(),
+        status: row.action_status,
+        labels: row.action_labels,
+        timeout: row.action_timeout,
+        shell: row.action_shell,
+        env: row.action_env,
+        needs: row.action_needs,
+        condition: row.action_condition,
+        retry_max_attempts: row.action_retry_max_attempts,
+        retry_backoff: row.action_retry_backoff,
+        retry_on: row.action_retry_on,
+        artifacts: row.action_artifacts,
+        artifacts_from: row.action_artifacts_from,
+        created_at: Some(row.action_created_at),
+        started_at: row.action_started_at,
+        finished_at: row.action_finished_at,
+    };
+    action_from_dto(dto, vec![row.command])
+}
@@ -4,18 +4,23 @@ use std::sync::Arc;
 use tokio::task;
 use tracing::{error, info};
 
-use crate::action::action_repository::Action;
-use crate::action::action_service::{ActionDTO, ActionService};
+use crate::action::action_repository::{env_to_entries, retry_to_columns, Action};
+use crate::action::action_service::{is_finished, ActionDTO, ActionService};
 use crate::grpc_scheduler::ActionStatus;
-use crate::logs::log_repository::LogRepository;
+use crate::logs::{log_hub::LogHub, log_repository::LogRepository};
+use crate::pipeline::pipeline_executor::PipelineExecutor;
 use crate::pipeline::pipeline_repository::PipelineRepository;
+use crate::run::run_repository::RunRepository;
+use crate::run::{PipelineRun, RunTrigger};
 use crate::{
-    parser::pipe_parser::{ManifestParser, ManifestPipeline, ParsingError},
+    parser::pipe_parser::{
+        IncludedFiles, ManifestError, ManifestParser, ManifestPipeline, Validation,
+    },
     scheduler::SchedulerService,
 };
 
 use super::pipeline_repository::PipelineDTO;
-use super::Pipeline;
+use super::{Pipeline, PipelineFilter, PipelinePage};
 
 pub struct PipelineService {
     client: Arc<SchedulerService>,
@@ -23,13 +28,21 @@ pub struct PipelineService {
     repository: Arc<PipelineRepository>,
     logs_repository: Arc<LogRepository>,
     action_service: Arc<ActionService>,
+    run_repository: Arc<RunRepository>,
+    executor: Arc<PipelineExecutor>,
 }
 
 #[derive(Debug)]
 pub enum PipelineServiceError {
-    ParsingError(ParsingError),
+    ParsingError(ManifestError),
     SchedulerError,
     StoringLogError,
+    PipelineNotFound,
+    ActionNotFound,
+    ActionFinished,
+    DatabaseError,
+    SecretError,
+    RunNotFound,
 }
 
 impl PipelineService {
@@ -38,44 +51,72 @@ impl PipelineService {
         parser: Arc<dyn ManifestParser>,
         pool: Arc<PgPool>,
         action_service: Arc<ActionService>,
+        log_hub: Arc<LogHub>,
     ) -> Self {
         let repository = Arc::new(PipelineRepository::new(pool.clone()));
         let logs_repository = Arc::new(LogRepository::new(pool.clone()));
+        let run_repository = Arc::new(RunRepository::new(pool.clone()));
+        let executor = Arc::new(PipelineExecutor::new(
+            Arc::clone(&client),
+            Arc::clone(&action_service),
+            Arc::clone(&logs_repository),
+            Arc::clone(&run_repository),
+            log_hub,
+        ));
         Self {
             client,
             parser,
             repository,
             logs_repository,
             action_service,
+            run_repository,
+            executor,
         }
     }
 
-    pub async fn find_all(&self, verbose: bool) -> Vec<Pipeline> {
-        match self.repository.find_all().await {
-            Ok(mut pipelines) => {
-                if verbose {
-                    for pipeline in &mut pipelines {
-                        if let Err(e) = self.add_verbose_details(pipeline).await {
-                            error!("Error while fetching verbose details for pipeline id {}: {:?}", pipeline.id, e);
-                        } else {
-                            info!("Verbose details added for pipeline id: {}", pipeline.id);
-                        }
-                    }
+    /// A page of pipelines, the newest first. Their actions come with their commands and logs when verbose.
+    pub async fn find_all(
+        &self,
+        filter: &PipelineFilter,
+        verbose: bool,
+    ) -> Result<PipelinePage, PipelineServiceError> {
+        // One more pipeline than asked for tells whether there is a next page
+        let mut pipelines = self
+            .repository
+            .find_all(
+                &PipelineFilter {
+                    limit: filter.limit + 1,
+                    ..filter.clone()
+                },
+                verbose,
+            )
+            .await
+            .map_err(database_error)?;
+        let next_cursor = if pipelines.len() as i64 > filter.limit {
+            pipelines.truncate(filter.limit as usize);
+            pipelines.last().map(|pipeline| pipeline.id)
+        } else {
+            None
+        };
+        if verbose {
+            for pipeline in &mut pipelines {
+                if let Err(e) = self.add_verbose_details(&mut pipeline.actions).await {
+                    error!("Error while fetching verbose details for pipeline id {}: {:?}", pipeline.id, e);
                 }
-                pipelines
-            }
-            Err(e) => {
-                info!("Error while fetching pipelines: {:?}", e);
-                vec![]
             }
         }
+        Ok(PipelinePage {
+            pipelines,
+            next_cursor,
+        })
     }
 
     pub async fn find(&self, id: i64, verbose: bool) -> Option<Pipeline> {
         match self.repository.find_by_id(id).await {
             Ok(mut pipeline) => {
+                self.add_last_run(&mut pipeline).await;
                 if verbose {
-                    if let Err(e) = self.add_verbose_details(&mut pipeline).await {
+                    if let Err(e) = self.add_verbose_details(&mut pipeline.actions).await {
                         error!("Error while fetching verbose details for pipeline: {:?}", e);
                     } else {
                         info!("Verbose details added for pipeline id: {}", id);
@@ -91,13 +132,38 @@ impl PipelineService {
         }
     }
 
-    async fn add_verbose_details(&self, pipeline: &mut Pipeline) -> Result<(), String> {
-        for action in &mut pipeline.actions {
+    async fn add_last_run(&self, pipeline: &mut Pipeline) {
+        match self.run_repository.find_latest(pipeline.id).await {
+            Ok(Some(run)) => pipeline.set_last_run(run.into()),
+            Ok(None) => {}
+            Err(e) => error!("Error while fetching last run of pipeline {}: {:?}", pipeline.id, e),
+        }
+    }
+
+    async fn add_verbose_details(&self, actions: &mut [Action]) -> Result<(), String> {
+        self.add_logs(actions).await?;
+        self.add_attempts(actions).await
+    }
+
+    async fn add_attempts(&self, actions: &mut [Action]) -> Result<(), String> {
+        for action in actions {
+            match self.action_service.find_attempts(action.id).await {
+                Ok(attempts) => action.attempts = Some(attempts),
+                Err(e) => {
+                    return Err(format!("Error fetching attempts for action {}: {}", action.name, e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn add_logs(&self, actions: &mut [Action]) -> Result<(), String> {
+        for action in actions {
             info!("Fetching verbose details for action: {:?}", action);
     
             match self.logs_repository.find_by_action_id(action.id).await {
                 Ok(logs) => {
-                    action.logs = Some(logs.into_iter().map(|log| log.message).collect());
+                    action.logs = Some(logs);
                 }
                 Err(e) => {
                     return Err(format!("Error fetching logs for action {}: {}", action.name, e));
@@ -126,25 +192,65 @@ impl PipelineService {
         }
     }
 
+    /// Start a new run of the pipeline defined by the manifest. A pipeline is created the first time a manifest of
+    /// this name is submitted for the repository, later submissions add runs to it.
     pub async fn create_pipeline_with_actions(
         &self,
         manifest: ManifestPipeline,
         repo_url: String,
+        trigger: RunTrigger,
+        commit_sha: Option<String>,
+        branch: Option<String>,
     ) -> Result<Pipeline, Box<dyn std::error::Error>> {
-        let pipeline = self.create_pipeline(&repo_url, &manifest.name).await?;
+        let pipeline = match self
+            .repository
+            .find_by_repository_and_name(&repo_url, &manifest.name)
+            .await?
+        {
+            Some(pipeline) => pipeline,
+            None => self.create_pipeline(&repo_url, &manifest.name).await?,
+        };
+        let run = self
+            .run_repository
+            .create(
+                pipeline.id,
+                trigger,
+                commit_sha.as_deref(),
+                branch.as_deref(),
+            )
+            .await?;
+        info!("Created run {} of pipeline {}", run.number, pipeline.id);
         let mut actions = Vec::new();
         for action in manifest.actions {
             info!("Creating action: {:?}", action);
+            let (retry_max_attempts, retry_backoff, retry_on) =
+                retry_to_columns(action.retry.as_ref());
             let action = self
                 .action_service
                 .create(
                     &ActionDTO {
                         name: action.name,
                         pipeline_id: pipeline.id,
+                        run_id: run.id,
                         container_uri: action.configuration_version,
                         status: ActionStatus::Pending.as_str_name().to_string(),
                         r#type: action.configuration_type,
                         id: None,
+                        labels: action.labels,
+                        // The parser bounds timeouts to the range of i32
+                        timeout: action.timeout.map(|timeout| timeout as i32),
+                        shell: action.shell,
+                        env: env_to_entries(&action.env),
+                        needs: action.needs,
+                        condition: action.condition,
+                        retry_max_attempts,
+                        retry_backoff,
+                        retry_on,
+                        artifacts: action.artifacts,
+                        artifacts_from: action.artifacts_from,
+                        created_at: None,
+                        started_at: None,
+                        finished_at: None,
                     },
                     action.commands,
                 )
@@ -153,30 +259,244 @@ impl PipelineService {
             actions.push(action);
         }
 
-        Ok(Pipeline::new(
+        let mut pipeline = Pipeline::new(
             pipeline.id,
             pipeline.repository_url,
             pipeline.name,
+            pipeline.created_at,
             actions,
-        ))
+        );
+        pipeline.set_last_run(run.into());
+        Ok(pipeline)
     }
 
-    pub fn try_parse_pipeline(&self, manifest: String) -> Result<ManifestPipeline, ParsingError> {
-        self.parser.parse(manifest)
+    /// Start a new run of the pipeline, with the same actions as its latest run.
+    pub async fn rerun_pipeline(&self, pipeline_id: i64) -> Result<Pipeline, PipelineServiceError> {
+        let pipeline = self
+            .repository
+            .find_pipeline(pipeline_id)
+            .await
+            .map_err(database_error)?
+            .ok_or(PipelineServiceError::PipelineNotFound)?;
+        let previous = self
+            .run_repository
+            .find_latest(pipeline_id)
+            .await
+            .map_err(database_error)?
+            .ok_or(PipelineServiceError::RunNotFound)?;
+        let previous_actions = self
+            .repository
+            .find_run_actions(previous.id)
+            .await
+            .map_err(database_error)?;
+
+        let run = self
+            .run_repository
+            .create(
+                pipeline_id,
+                RunTrigger::Rerun,
+                previous.commit_sha.as_deref(),
+                previous.branch.as_deref(),
+            )
+            .await
+            .map_err(database_error)?;
+        info!("Created run {} of pipeline {}", run.number, pipeline_id);
+        let mut actions = Vec::new();
+        for action in previous_actions {
+            let (retry_max_attempts, retry_backoff, retry_on) =
+                retry_to_columns(action.retry.as_ref());
+            let action = self
+                .action_service
+                .create(
+                    &ActionDTO {
+                        id: None,
+                        pipeline_id,
+                        run_id: run.id,
+                        name: action.name,
+                        container_uri: action.container_uri,
+                        r#type: action.r#type,
+                        status: ActionStatus::Pending.as_str_name().to_string(),
+                        labels: action.labels,
+                        timeout: action.timeout,
+                        shell: action.shell,
+                        env: env_to_entries(&action.env),
+                        needs: action.needs,
+                        condition: action.condition,
+                        retry_max_attempts,
+                        retry_backoff,
+                        retry_on,
+                        artifacts: action.artifacts,
+                        artifacts_from: action.artifacts_from,
+                        created_at: None,
+                        started_at: None,
+                        finished_at: None,
+                    },
+                    action.commands,
+                )
+                .await
+                .map_err(|e| {
+                    error!("Error while creating action: {:?}", e);
+                    PipelineServiceError::DatabaseError
+                })?;
+            actions.push(action);
+        }
+
+        let mut pipeline = Pipeline::new(
+            pipeline.id,
+            pipeline.repository_url,
+            pipeline.name,
+            pipeline.created_at,
+            actions,
+        );
+        pipeline.set_last_run(run.into());
+        Ok(pipeline)
     }
 
-    pub async fn send_action(
+    /// The runs of the pipeline, the latest first.
+    pub async fn find_runs(&self, pipeline_id: i64) -> Result<Vec<PipelineRun>, PipelineServiceError> {
+        self.repository
+            .find_pipeline(pipeline_id)
+            .await
+            .map_err(database_error)?
+            .ok_or(PipelineServiceError::PipelineNotFound)?;
+        let runs = self
+            .run_repository
+            .find_by_pipeline_id(pipeline_id)
+            .await
+            .map_err(database_error)?;
+        Ok(runs.into_iter().map(PipelineRun::from).collect())
+    }
+
+    /// A run of the pipeline with its actions.
+    pub async fn find_run(
         &self,
-        action: Arc<Action>,
-        repo_url: String,
-    ) -> Result<(), PipelineServiceError> {
-        let client = Arc::clone(&self.client);
-        task::spawn(async move {
-            match client.send_action(action, repo_url).await {
-                Ok(_) => info!("Action sent successfully"),
-                Err(err) => error!("Error sending action: {:?}", err), //needs to store the error in database
+        pipeline_id: i64,
+        number: i32,
+        verbose: bool,
+    ) -> Result<PipelineRun, PipelineServiceError> {
+        let run = self
+            .run_repository
+            .find_by_number(pipeline_id, number)
+            .await
+            .map_err(database_error)?
+            .ok_or(PipelineServiceError::RunNotFound)?;
+        let mut actions = self
+            .repository
+            .find_run_actions(run.id)
+            .await
+            .map_err(database_error)?;
+        if verbose {
+            if let Err(e) = self.add_verbose_details(&mut actions).await {
+                error!("Error while fetching verbose details for run {}: {:?}", run.id, e);
             }
+        }
+
+        let mut run = PipelineRun::from(run);
+        run.actions = Some(actions);
+        Ok(run)
+    }
+
+    pub fn try_parse_pipeline(
+        &self,
+        manifest: String,
+        includes: &IncludedFiles,
+    ) -> Result<ManifestPipeline, ManifestError> {
+        self.parser.parse_with_includes(manifest, includes)
+    }
+
+    /// Check a manifest without creating a pipeline from it.
+    pub fn validate_pipeline(
+        &self,
+        manifest: String,
+        includes: &IncludedFiles,
+    ) -> Result<Validation, ManifestError> {
+        self.parser.validate_with_includes(manifest, includes)
+    }
+
+    /// Run the actions of the latest run of the pipeline in the background, each one once the actions it needs are
+    /// over.
+    pub fn execute_pipeline(&self, pipeline: &Pipeline) {
+        let Some(run) = &pipeline.last_run else {
+            error!("Pipeline {} has no run to execute", pipeline.id);
+            return;
+        };
+        let executor = Arc::clone(&self.executor);
+        let run = run.clone();
+        let actions = pipeline.actions.clone();
+        let repo_url = pipeline.repository_url.clone();
+        task::spawn(async move {
+            executor.execute(run, actions, repo_url).await;
+            info!("Pipeline executed");
         });
+    }
+
+    /// Cancel every action of the pipeline that is not over yet.
+    pub async fn cancel_pipeline(&self, pipeline_id: i64) -> Result<(), PipelineServiceError> {
+        let actions = self
+            .action_service
+            .find_by_pipeline_id(pipeline_id)
+            .await
+            .map_err(|e| {
+                error!("Error while fetching actions of pipeline {}: {:?}", pipeline_id, e);
+                PipelineServiceError::DatabaseError
+            })?;
+        if actions.is_empty() {
+            return Err(PipelineServiceError::PipelineNotFound);
+        }
+
+        for action in actions.iter().filter(|action| !is_finished(&action.status)) {
+            if let Some(action_id) = action.id {
+                self.cancel_running_action(action_id).await?;
+            }
+        }
         Ok(())
     }
+
+    /// Cancel a single action of the pipeline, which must not be over yet.
+    pub async fn cancel_action(
+        &self,
+        pipeline_id: i64,
+        action_id: i64,
+    ) -> Result<(), PipelineServiceError> {
+        let action = self
+            .action_service
+            .find(action_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => PipelineServiceError::ActionNotFound,
+                e => {
+                    error!("Error while fetching action {}: {:?}", action_id, e);
+                    PipelineServiceError::DatabaseError
+                }
+            })?;
+        if action.pipeline_id != pipeline_id {
+            return Err(PipelineServiceError::ActionNotFound);
+        }
+        if is_finished(&action.status) {
+            return Err(PipelineServiceError::ActionFinished);
+        }
+
+        self.cancel_running_action(action_id).await
+    }
+
+    async fn cancel_running_action(&self, action_id: i64) -> Result<(), PipelineServiceError> {
+        info!("Cancelling action {}", action_id);
+        if self.client.cancel_action(action_id).await? {
+            return Ok(());
+        }
+
+        // The scheduler no longer knows the action, so no cancelled status will come from it.
+        self.action_service
+            .update_status(action_id, &ActionStatus::Cancelled)
+            .await
+            .map_err(|e| {
+                error!("Error while updating action status: {:?}", e);
+                PipelineServiceError::DatabaseError
+            })
+    }
+}
+
+fn database_error(e: sqlx::Error) -> PipelineServiceError {
+    error!("Database error: {:?}", e);
+    PipelineServiceError::DatabaseError
 }
@@ -0,0 +1,146 @@
+use core::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::action::action_repository::Action;
+use crate::action::action_service::is_finished;
+use crate::grpc_scheduler::ActionStatus;
+
+pub mod run_repository;
+
+/// What started a run of a pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunTrigger {
+    /// The manifest was submitted by hand
+    Manual,
+    /// A commit was pushed to the repository
+    Commit,
+    /// A pull request was opened on the repository
+    PullRequest,
+    /// A previous run was started again
+    Rerun,
+}
+
+impl fmt::Display for RunTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunTrigger::Manual => write!(f, "manual"),
+            RunTrigger::Commit => write!(f, "commit"),
+            RunTrigger::PullRequest => write!(f, "pull_request"),
+            RunTrigger::Rerun => write!(f, "rerun"),
+        }
+    }
+}
+
+impl FromStr for RunTrigger {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(RunTrigger::Manual),
+            "commit" => Ok(RunTrigger::Commit),
+            "pull_request" => Ok(RunTrigger::PullRequest),
+            "rerun" => Ok(RunTrigger::Rerun),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The overall status of a run, computed from the statuses of its actions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    /// Every action of the run was skipped, so nothing ran
+    Skipped,
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunStatus::Pending => write!(f, "pending"),
+            RunStatus::Running => write!(f, "running"),
+            RunStatus::Succeeded => write!(f, "succeeded"),
+            RunStatus::Failed => write!(f, "failed"),
+            RunStatus::Cancelled => write!(f, "cancelled"),
+            RunStatus::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+impl FromStr for RunStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(RunStatus::Pending),
+            "running" => Ok(RunStatus::Running),
+            "succeeded" => Ok(RunStatus::Succeeded),
+            "failed" => Ok(RunStatus::Failed),
+            "cancelled" => Ok(RunStatus::Cancelled),
+            "skipped" => Ok(RunStatus::Skipped),
+            _ => Err(()),
+        }
+    }
+}
+
+impl RunStatus {
+    /// The status of a run from the statuses of its actions: it is running as long as one of them is not over. The
+    /// skipped actions are left out, as they did not run, and a run whose actions were all skipped is skipped.
+    pub fn aggregate(statuses: impl IntoIterator<Item = ActionStatus>) -> RunStatus {
+        let statuses: Vec<ActionStatus> = statuses.into_iter().collect();
+        if !statuses.is_empty() && statuses.iter().all(|status| *status == ActionStatus::Skipped) {
+            return RunStatus::Skipped;
+        }
+        let statuses: Vec<ActionStatus> = statuses
+            .into_iter()
+            .filter(|status| *status != ActionStatus::Skipped)
+            .collect();
+        if !statuses
+            .iter()
+            .all(|status| is_finished(status.as_str_name()))
+        {
+            RunStatus::Running
+        } else if statuses.contains(&ActionStatus::Cancelled) {
+            RunStatus::Cancelled
+        } else if statuses.iter().all(|status| *status == ActionStatus::Completed) {
+            RunStatus::Succeeded
+        } else {
+            RunStatus::Failed
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            RunStatus::Succeeded | RunStatus::Failed | RunStatus::Cancelled | RunStatus::Skipped
+        )
+    }
+}
+
+/// An execution of a pipeline. Each run holds its own actions, with their statuses and logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRun {
+    pub id: i64,
+    pub pipeline_id: i64,
+    /// Position of the run in the history of the pipeline, starting at 1
+    pub number: i32,
+    pub trigger: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+    /// Branch of the repository the run is for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub status: String,
+    #[serde(with = "time::serde::rfc3339::option", skip_serializing_if = "Option::is_none", default)]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", skip_serializing_if = "Option::is_none", default)]
+    pub finished_at: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<Action>>,
+}
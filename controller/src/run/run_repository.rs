@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use time::OffsetDateTime;
+
+use super::{PipelineRun, RunStatus, RunTrigger};
+
+#[derive(Debug, Clone)]
+pub struct PipelineRunDTO {
+    pub id: i64,
+    pub pipeline_id: i64,
+    pub number: i32,
+    pub trigger: String,
+    pub commit_sha: Option<String>,
+    pub branch: Option<String>,
+    pub status: String,
+    pub started_at: Option<OffsetDateTime>,
+    pub finished_at: Option<OffsetDateTime>,
+}
+
+impl From<PipelineRunDTO> for PipelineRun {
+    fn from(run: PipelineRunDTO) -> Self {
+        PipelineRun {
+            id: run.id,
+            pipeline_id: run.pipeline_id,
+            number: run.number,
+            trigger: run.trigger,
+            commit_sha: run.commit_sha,
+            branch: run.branch,
+            status: run.status,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+            actions: None,
+        }
+    }
+}
+
+pub struct RunRepository {
+    pool: Arc<PgPool>,
+}
+
+impl RunRepository {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Create the next run of the pipeline, numbered after the last one.
+    pub async fn create(
+        &self,
+        pipeline_id: i64,
+        trigger: RunTrigger,
+        commit_sha: Option<&str>,
+        branch: Option<&str>,
+    ) -> Result<PipelineRunDTO, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineRunDTO,
+            r#"INSERT INTO pipeline_runs (pipeline_id, number, trigger, commit_sha, branch, status)
+               SELECT $1, COALESCE(MAX(number), 0) + 1, $2, $3, $4, $5 FROM pipeline_runs WHERE pipeline_id = $1
+               RETURNING id, pipeline_id, number, trigger, commit_sha, branch, status, started_at, finished_at"#,
+            pipeline_id,
+            trigger.to_string(),
+            commit_sha,
+            branch,
+            RunStatus::Pending.to_string()
+        )
+        .fetch_one(self.pool.as_ref())
+        .await
+    }
+
+    /// The runs of the pipeline, the latest first.
+    pub async fn find_by_pipeline_id(
+        &self,
+        pipeline_id: i64,
+    ) -> Result<Vec<PipelineRunDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineRunDTO,
+            r#"SELECT id, pipeline_id, number, trigger, commit_sha, branch, status, started_at, finished_at
+               FROM pipeline_runs WHERE pipeline_id = $1 ORDER BY number DESC"#,
+            pipeline_id
+        )
+        .fetch_all(self.pool.as_ref())
+        .await
+    }
+
+    pub async fn find_by_number(
+        &self,
+        pipeline_id: i64,
+        number: i32,
+    ) -> Result<Option<PipelineRunDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineRunDTO,
+            r#"SELECT id, pipeline_id, number, trigger, commit_sha, branch, status, started_at, finished_at
+               FROM pipeline_runs WHERE pipeline_id = $1 AND number = $2"#,
+            pipeline_id,
+            number
+        )
+        .fetch_optional(self.pool.as_ref())
+        .await
+    }
+
+    pub async fn find_latest(&self, pipeline_id: i64) -> Result<Option<PipelineRunDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            PipelineRunDTO,
+            r#"SELECT id, pipeline_id, number, trigger, commit_sha, branch, status, started_at, finished_at
+               FROM pipeline_runs WHERE pipeline_id = $1 ORDER BY number DESC LIMIT 1"#,
+            pipeline_id
+        )
+        .fetch_optional(self.pool.as_ref())
+        .await
+    }
+
+    /// Update the status of a run that is not over yet, and record when it started and finished.
+    pub async fn update_status(&self, id: i64, status: RunStatus) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"UPDATE pipeline_runs SET status = $1,
+               started_at = COALESCE(started_at, NOW()),
+               finished_at = CASE WHEN $2 THEN NOW() ELSE NULL END
+               WHERE id = $3 AND finished_at IS NULL"#,
+            status.to_string(),
+            status.is_finished(),
+            id
+        )
+        .execute(self.pool.as_ref())
+        .await?;
+        Ok(())
+    }
+}
@@ -1,23 +1,57 @@
 use std::sync::Arc;
 
-use tokio::sync::Mutex;
+use tokio::{
+    sync::Mutex,
+    time::{sleep_until, Duration, Instant},
+};
 
-use tonic::{transport::Channel, Request};
-use tracing::{error, info};
+use time::OffsetDateTime;
+use tonic::{transport::Channel, Code, Request};
+use tracing::{debug, error, info};
 
 use crate::{
-    action::{action_repository::Action, action_service::ActionService},
+    action::{
+        action_repository::Action,
+        action_service::{is_finished, ActionService},
+    },
+    artifact::{
+        artifact_service::{ArtifactService, ArtifactServiceError},
+        Artifact,
+    },
     grpc_scheduler::{
-        self, controller_client::ControllerClient, ActionStatus, ExecutionContext, RunnerType,
+        self, controller_client::ControllerClient, ActionResponse, ActionStatus, ArtifactsRequest,
+        CancelActionRequest, ExecutionContext, LogStream, RunnerType,
     },
-    logs::log_repository::LogRepository,
+    logs::{log_hub::LogHub, log_repository::LogRepository, log_writer::LogWriter, LogLine},
+    parser::manifest::FailureClass,
     pipeline::pipeline_service::PipelineServiceError,
+    run::{run_repository::RunRepository, RunStatus},
+    secret::{
+        secret_reference::substitute,
+        secret_service::{SecretService, SecretServiceError},
+    },
 };
 
+/// Time between two checks of whether an action waiting to run again was cancelled
+const CANCELLATION_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How an attempt of an action ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttemptResult {
+    /// Last status received, which is not final if the attempt ended early
+    pub status: ActionStatus,
+    /// Exit code of the command that ended the attempt, if one ran
+    pub exit_code: Option<i32>,
+}
+
 pub struct SchedulerService {
     client: Arc<Mutex<ControllerClient<Channel>>>,
     log_repository: Arc<LogRepository>,
     action_service: Arc<ActionService>,
+    secret_service: Arc<SecretService>,
+    run_repository: Arc<RunRepository>,
+    log_hub: Arc<LogHub>,
+    artifact_service: Arc<ArtifactService>,
 }
 
 impl SchedulerService {
@@ -25,37 +59,190 @@ impl SchedulerService {
         client: Arc<Mutex<ControllerClient<Channel>>>,
         log_repository: Arc<LogRepository>,
         action_service: Arc<ActionService>,
+        secret_service: Arc<SecretService>,
+        run_repository: Arc<RunRepository>,
+        log_hub: Arc<LogHub>,
+        artifact_service: Arc<ArtifactService>,
     ) -> Self {
         Self {
             client,
             log_repository,
             action_service,
+            secret_service,
+            run_repository,
+            log_hub,
+            artifact_service,
         }
     }
 
+    /// Send an action to the scheduler and follow it until it is over, returning its final status. A failed attempt
+    /// is followed by another one, after a backoff, as long as the retry policy of the action allows it.
     pub async fn send_action(
         &self,
         action: Arc<Action>,
         repo_url: String,
+    ) -> Result<ActionStatus, PipelineServiceError> {
+        let mut attempt = 1;
+        loop {
+            let started_at = OffsetDateTime::now_utc();
+            let result = self.send_attempt(&action, &repo_url, attempt).await;
+            let status = match &result {
+                Ok(result) if is_finished(result.status.as_str_name()) => result.status,
+                _ => ActionStatus::Error,
+            };
+            let exit_code = result.as_ref().ok().and_then(|result| result.exit_code);
+            if let Err(e) = self
+                .action_service
+                .record_attempt(action.id, attempt, &status, exit_code, started_at)
+                .await
+            {
+                error!(
+                    "Error while recording attempt {} of action {}: {:?}",
+                    attempt, action.id, e
+                );
+            }
+
+            let retry = action.retry.as_ref().zip(failure_class(&result));
+            let Some((retry, failure)) =
+                retry.filter(|(retry, failure)| retry.retries(attempt as u32, *failure))
+            else {
+                if let Ok(result) = &result {
+                    // The artifacts are there once the action is reported as completed
+                    if result.status == ActionStatus::Completed && !action.artifacts.is_empty() {
+                        self.collect_artifacts(&action, attempt).await;
+                    }
+                    if is_finished(result.status.as_str_name()) {
+                        self.set_status(&action, &result.status).await?;
+                    }
+                }
+                return result.map(|result| result.status);
+            };
+
+            let delay = retry.delay(attempt as u32);
+            let log = format!(
+                "Attempt {} failed ({}), retrying in {}s",
+                attempt,
+                failure.as_str(),
+                delay.as_secs()
+            );
+            info!("Action {}: {}", action.id, log);
+            match self.log_repository.create(action.id, attempt, &log).await {
+                Ok(log) => self.log_hub.publish(action.id, log),
+                Err(e) => error!("Error while storing log: {:?}", e),
+            }
+            self.set_status(&action, &ActionStatus::Pending).await?;
+            if let Some(status) = self.wait_for_retry(action.id, delay).await {
+                info!(
+                    "Action {} was not retried, as it is {}",
+                    action.id,
+                    status.as_str_name()
+                );
+                return Ok(status);
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Wait before the next attempt of an action, returning early with the final status of the action if it gets
+    /// cancelled meanwhile.
+    async fn wait_for_retry(&self, action_id: i64, delay: Duration) -> Option<ActionStatus> {
+        let deadline = Instant::now() + delay;
+        loop {
+            match self.action_service.find(action_id).await {
+                Ok(action) if is_finished(&action.status) => {
+                    return ActionStatus::from_str_name(&action.status);
+                }
+                Ok(_) => {}
+                Err(e) => error!("Error while fetching action {}: {:?}", action_id, e),
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            sleep_until(deadline.min(Instant::now() + CANCELLATION_CHECK_INTERVAL)).await;
+        }
+    }
+
+    async fn set_status(
+        &self,
+        action: &Action,
+        status: &ActionStatus,
     ) -> Result<(), PipelineServiceError> {
+        info!("[SCHEDULER] STATUS={:?}", status.as_str_name());
+        self.action_service
+            .update_status(action.id, status)
+            .await
+            .map_err(|e| {
+                error!("Error while updating action status: {:?}", e);
+                PipelineServiceError::SchedulerError
+            })?;
+        self.refresh_run_status(action.run_id).await;
+        Ok(())
+    }
+
+    /// Send an attempt of an action to the scheduler and follow it until it is over. The statuses it goes through are
+    /// recorded, except the final one, which depends on whether the action runs again.
+    async fn send_attempt(
+        &self,
+        action: &Action,
+        repo_url: &str,
+        attempt: i32,
+    ) -> Result<AttemptResult, PipelineServiceError> {
         let id: Result<u32, _> = action.id.try_into();
+        // Secrets are resolved at the last moment, so their values are never stored along with the action
+        let secrets = match self
+            .secret_service
+            .resolve(action.commands.iter().chain(action.env.values()))
+            .await
+        {
+            Ok(secrets) => secrets,
+            Err(e) => return self.fail_action(action, attempt, e).await,
+        };
+        let input_artifacts = self
+            .artifact_service
+            .inputs(action)
+            .await
+            .map_err(|_| PipelineServiceError::DatabaseError)?;
+        if input_artifacts.is_empty() && !action.artifacts_from.is_empty() {
+            let log = "No artifact was kept by the actions it takes artifacts from";
+            match self.log_repository.create(action.id, attempt, log).await {
+                Ok(log) => self.log_hub.publish(action.id, log),
+                Err(e) => error!("Error while storing log: {:?}", e),
+            }
+        }
         let action_request = grpc_scheduler::ActionRequest {
             context: Some(ExecutionContext {
                 r#type: RunnerType::Docker.into(), //for now we only support container actions
                 container_image: Some(action.container_uri.clone()),
+                labels: action.labels.clone(),
+                shell: action.shell.clone(),
+                env: action
+                    .env
+                    .iter()
+                    .map(|(key, value)| (key.clone(), substitute(value, &secrets)))
+                    .collect(),
+                secrets: secrets.values().cloned().collect(),
             }),
             action_id: id.map_err(|e| {
                 error!("Error while converting action id: {:?}", e);
                 PipelineServiceError::SchedulerError
             })?,
-            commands: action.commands.clone(),
-            repo_url: repo_url.clone(),
+            commands: action
+                .commands
+                .iter()
+                .map(|command| substitute(command, &secrets))
+                .collect(),
+            repo_url: repo_url.to_string(),
+            timeout: action.timeout.map(|timeout| timeout as u32),
+            artifacts: action.artifacts.clone(),
+            input_artifacts,
         };
 
         let request = Request::new(action_request);
-        let mut client = self.client.lock().await;
-
-        let mut stream = client
+        // The client is only locked while the action is sent, so that other requests (e.g. cancellations) are not blocked by the response stream.
+        let mut stream = self
+            .client
+            .lock()
+            .await
             .schedule_action(request)
             .await
             .map_err(|_err| {
@@ -64,36 +251,220 @@ impl SchedulerService {
             })?
             .into_inner();
 
-        while let Some(response) = stream.message().await.map_err(|_err| {
-            error!("Error while receiving message from scheduler : {:?}", _err);
-            PipelineServiceError::SchedulerError
-        })? {
-            info!("[SCHEDULER] RESPONSE={:?}", response);
-            self.log_repository
-                .create(i64::from(response.action_id), &response.log)
+        let mut writer = LogWriter::new(
+            Arc::clone(&self.log_repository),
+            Arc::clone(&self.log_hub),
+            action.id,
+            attempt,
+        );
+        let mut status = ActionStatus::Pending;
+        let mut exit_code = None;
+        loop {
+            let response = tokio::select! {
+                response = stream.message() => response,
+                // The lines are stored while the action is quiet, so that its followers do not wait for a full batch
+                _ = writer.deadline() => {
+                    writer.flush().await.map_err(storing_log_error)?;
+                    continue;
+                }
+            };
+            let response = match response {
+                Ok(Some(response)) => response,
+                Ok(None) => break,
+                Err(_err) => {
+                    error!("Error while receiving message from scheduler : {:?}", _err);
+                    // The lines received so far are kept
+                    writer.flush().await.map_err(storing_log_error)?;
+                    return Err(PipelineServiceError::SchedulerError);
+                }
+            };
+            debug!("[SCHEDULER] RESPONSE={:?}", response);
+            writer
+                .write(log_line(&response))
                 .await
-                .map_err(|e| {
-                    error!("Error while storing log: {:?}", e);
-                    PipelineServiceError::StoringLogError
-                })?;
-
-            let status = ActionStatus::as_str_name(&response.result.unwrap().completion()); //TODO: for now we are going to unwrap all cast probable errors. Though we should handle them properly by sending a Error message through gRPC to the Scheduler
-
-            info!("[SCHEDULER] STATUS={:?}", status);
-            self.action_service
-                .update_status(
-                    i64::from(response.action_id),
-                    &ActionStatus::from_str_name(status).unwrap(), //We can unwrap here because we are sure that the status is a valid one
-                )
-                .await
-                .map_err(|e| {
-                    error!("Error while updating action status: {:?}", e);
-                    PipelineServiceError::SchedulerError
-                })?; //same here we should be sending an error status to the scheduler
+                .map_err(storing_log_error)?;
+
+            let Some(result) = response.result.as_ref() else {
+                error!("Response without result for action {}", response.action_id);
+                // The lines received so far are kept
+                writer.flush().await.map_err(storing_log_error)?;
+                return Err(PipelineServiceError::SchedulerError);
+            };
+            exit_code = result.exit_code.or(exit_code);
+            let new_status = result.completion();
+            if new_status == status {
+                continue;
+            }
+            status = new_status;
+            // The lines are stored before the status changes, so that they are all there once the action is over
+            writer.flush().await.map_err(storing_log_error)?;
 
-            info!("[SCHEDULER] RESPONSE={:?}", response);
+            if !is_finished(status.as_str_name()) {
+                self.set_status(action, &status).await?; //same here we should be sending an error status to the scheduler
+            }
         }
+        writer.flush().await.map_err(storing_log_error)?;
 
-        Ok(())
+        Ok(AttemptResult { status, exit_code })
+    }
+
+    /// Download the artifacts the agent kept from an action that completed. An action whose artifacts cannot be stored
+    /// still completes, which its logs tell.
+    async fn collect_artifacts(&self, action: &Action, attempt: i32) {
+        let log = match self.download_artifacts(action.id).await {
+            Ok(artifacts) if artifacts.is_empty() => "No artifact was kept".to_string(),
+            Ok(artifacts) => format!("{} artifact(s) stored", artifacts.len()),
+            Err(e) => {
+                error!(
+                    "Error while storing artifacts of action {}: {:?}",
+                    action.id, e
+                );
+                "Artifacts could not be stored".to_string()
+            }
+        };
+        match self.log_repository.create(action.id, attempt, &log).await {
+            Ok(log) => self.log_hub.publish(action.id, log),
+            Err(e) => error!("Error while storing log: {:?}", e),
+        }
+    }
+
+    async fn download_artifacts(
+        &self,
+        action_id: i64,
+    ) -> Result<Vec<Artifact>, ArtifactServiceError> {
+        let id: u32 = action_id.try_into().map_err(|e| {
+            error!("Error while converting action id: {:?}", e);
+            ArtifactServiceError::TransferError
+        })?;
+        let request = Request::new(ArtifactsRequest { action_id: id });
+        let chunks = self
+            .client
+            .lock()
+            .await
+            .download_artifacts(request)
+            .await
+            .map_err(|e| {
+                error!(
+                    "Error while downloading artifacts of action {}: {:?}",
+                    action_id, e
+                );
+                ArtifactServiceError::TransferError
+            })?
+            .into_inner();
+        self.artifact_service.store(action_id, chunks).await
+    }
+
+    /// Report an action whose secrets could not be resolved as failed, without sending it to the scheduler.
+    async fn fail_action(
+        &self,
+        action: &Action,
+        attempt: i32,
+        error: SecretServiceError,
+    ) -> Result<AttemptResult, PipelineServiceError> {
+        let action_id = action.id;
+        error!("Error while resolving secrets of action {}: {:?}", action_id, error);
+        let log = match error {
+            SecretServiceError::SecretNotFound(name) => format!("Secret {} is not defined", name),
+            _ => "Secrets could not be resolved".to_string(),
+        };
+        let log = self
+            .log_repository
+            .create(action_id, attempt, &log)
+            .await
+            .map_err(|e| {
+                error!("Error while storing log: {:?}", e);
+                PipelineServiceError::StoringLogError
+            })?;
+        self.log_hub.publish(action_id, log);
+        self.action_service
+            .update_status(action_id, &ActionStatus::Error)
+            .await
+            .map_err(|e| {
+                error!("Error while updating action status: {:?}", e);
+                PipelineServiceError::DatabaseError
+            })?;
+        self.refresh_run_status(action.run_id).await;
+        Err(PipelineServiceError::SecretError)
+    }
+
+    /// Compute the status of the run, which is the status of its pipeline, from the statuses of its actions.
+    async fn refresh_run_status(&self, run_id: i64) {
+        let actions = match self.action_service.find_by_run_id(run_id).await {
+            Ok(actions) => actions,
+            Err(e) => {
+                error!("Error while fetching actions of run {}: {:?}", run_id, e);
+                return;
+            }
+        };
+        let status = RunStatus::aggregate(actions.iter().map(|action| {
+            ActionStatus::from_str_name(&action.status).unwrap_or(ActionStatus::Pending)
+        }));
+        if let Err(e) = self.run_repository.update_status(run_id, status).await {
+            error!("Error while updating status of run {}: {:?}", run_id, e);
+        }
+    }
+
+    /// Ask the scheduler to cancel an action. The cancelled status is then received through the action's response stream.
+    /// Returns false if the scheduler does not know the action, i.e. it is neither queued nor running.
+    pub async fn cancel_action(&self, action_id: i64) -> Result<bool, PipelineServiceError> {
+        let action_id: u32 = action_id.try_into().map_err(|e| {
+            error!("Error while converting action id: {:?}", e);
+            PipelineServiceError::SchedulerError
+        })?;
+        let request = Request::new(CancelActionRequest { action_id });
+
+        match self.client.lock().await.cancel_action(request).await {
+            Ok(_) => Ok(true),
+            Err(status) if status.code() == Code::NotFound => Ok(false),
+            Err(status) => {
+                error!("Error while cancelling action {}: {:?}", action_id, status);
+                Err(PipelineServiceError::SchedulerError)
+            }
+        }
+    }
+}
+
+/// What made an attempt of an action fail, if it failed in a way that can be retried. An attempt that ended without a
+/// final status, or without a command exiting, could not run to its end.
+pub fn failure_class(result: &Result<AttemptResult, PipelineServiceError>) -> Option<FailureClass> {
+    match result {
+        Ok(AttemptResult {
+            status: ActionStatus::Error,
+            exit_code: Some(exit_code),
+        }) if *exit_code != 0 => Some(FailureClass::ExitCode),
+        Ok(AttemptResult {
+            status: ActionStatus::Error,
+            ..
+        }) => Some(FailureClass::Infrastructure),
+        Ok(result) if !is_finished(result.status.as_str_name()) => {
+            Some(FailureClass::Infrastructure)
+        }
+        Ok(_) => None,
+        Err(PipelineServiceError::SchedulerError) => Some(FailureClass::Infrastructure),
+        Err(_) => None,
+    }
+}
+
+fn storing_log_error(e: sqlx::Error) -> PipelineServiceError {
+    error!("Error while storing log: {:?}", e);
+    PipelineServiceError::StoringLogError
+}
+
+/// The line carried by a response of the scheduler. The status updates of the scheduler are not numbered, unlike the
+/// lines of the agent.
+fn log_line(response: &ActionResponse) -> LogLine {
+    let timestamp =
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(response.timestamp) * 1_000_000)
+            .ok()
+            .filter(|_| response.timestamp > 0)
+            .unwrap_or_else(OffsetDateTime::now_utc);
+    LogLine {
+        sequence: i64::try_from(response.sequence)
+            .ok()
+            .filter(|&sequence| sequence > 0),
+        timestamp,
+        step: response.step.and_then(|step| i32::try_from(step).ok()),
+        stream: LogStream::try_from(response.stream).unwrap_or(LogStream::System),
+        message: response.log.clone(),
     }
 }
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+pub mod secret_cipher;
+pub mod secret_controller;
+pub mod secret_reference;
+pub mod secret_repository;
+pub mod secret_service;
+
+/// A secret as exposed by the API: its value never leaves the controller, except to run an action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Secret {
+    pub name: String,
+}
+
+impl Secret {
+    pub fn new(name: String) -> Self {
+        Secret { name }
+    }
+}
@@ -0,0 +1,79 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+
+/// Size in bytes of the master key.
+const KEY_SIZE: usize = 32;
+
+/// Size in bytes of the nonce stored with each secret.
+const NONCE_SIZE: usize = 12;
+
+#[derive(Debug)]
+pub enum SecretCipherError {
+    InvalidKey,
+    EncryptionError,
+    DecryptionError,
+}
+
+/// Encrypts the secrets at rest with AES-256-GCM, using the master key of the controller.
+/// The secret name is authenticated along with its value, so an encrypted value cannot be moved to another secret.
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    /// Build the cipher from the hex encoded master key, e.g. generated with `openssl rand -hex 32`.
+    pub fn new(key: &str) -> Result<Self, SecretCipherError> {
+        let key = hex::decode(key.trim()).map_err(|_| SecretCipherError::InvalidKey)?;
+        if key.len() != KEY_SIZE {
+            return Err(SecretCipherError::InvalidKey);
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    /// Encrypt the value of a secret, returning the nonce and the ciphertext.
+    pub fn encrypt(
+        &self,
+        name: &str,
+        value: &str,
+    ) -> Result<(Vec<u8>, Vec<u8>), SecretCipherError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.as_bytes(),
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| SecretCipherError::EncryptionError)?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    /// Decrypt the value of a secret from its nonce and ciphertext.
+    pub fn decrypt(
+        &self,
+        name: &str,
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<String, SecretCipherError> {
+        if nonce.len() != NONCE_SIZE {
+            return Err(SecretCipherError::DecryptionError);
+        }
+        let value = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: name.as_bytes(),
+                },
+            )
+            .map_err(|_| SecretCipherError::DecryptionError)?;
+        String::from_utf8(value).map_err(|_| SecretCipherError::DecryptionError)
+    }
+}
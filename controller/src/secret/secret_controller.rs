@@ -0,0 +1,86 @@
+use actix_web::{
+    delete, get, post, put,
+    web::{self},
+    HttpResponse, Responder,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::secret::secret_service::{SecretService, SecretServiceError};
+
+#[derive(Deserialize)]
+struct CreateSecretBody {
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateSecretBody {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct SecretByNameQuery {
+    name: String,
+}
+
+#[get("/secrets")]
+pub async fn get_secrets(secret_service: web::Data<Arc<SecretService>>) -> impl Responder {
+    match secret_service.find_all().await {
+        Ok(secrets) => HttpResponse::Ok().json(secrets),
+        Err(e) => error_response(e),
+    }
+}
+
+#[post("/secrets")]
+pub async fn create_secret(
+    body: web::Json<CreateSecretBody>,
+    secret_service: web::Data<Arc<SecretService>>,
+) -> impl Responder {
+    info!("Creating secret {}", body.name);
+    match secret_service.create(&body.name, &body.value).await {
+        Ok(secret) => HttpResponse::Created().json(secret),
+        Err(e) => error_response(e),
+    }
+}
+
+#[put("/secrets/{name}")]
+pub async fn update_secret(
+    path: web::Path<SecretByNameQuery>,
+    body: web::Json<UpdateSecretBody>,
+    secret_service: web::Data<Arc<SecretService>>,
+) -> impl Responder {
+    info!("Updating secret {}", path.name);
+    match secret_service.update(&path.name, &body.value).await {
+        Ok(secret) => HttpResponse::Ok().json(secret),
+        Err(e) => error_response(e),
+    }
+}
+
+#[delete("/secrets/{name}")]
+pub async fn delete_secret(
+    path: web::Path<SecretByNameQuery>,
+    secret_service: web::Data<Arc<SecretService>>,
+) -> impl Responder {
+    info!("Deleting secret {}", path.name);
+    match secret_service.delete(&path.name).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => error_response(e),
+    }
+}
+
+fn error_response(error: SecretServiceError) -> HttpResponse {
+    match error {
+        SecretServiceError::InvalidName => HttpResponse::UnprocessableEntity().body(
+            "Secret names only contain letters, digits and '_', and do not start with a digit",
+        ),
+        SecretServiceError::AlreadyExists => {
+            HttpResponse::Conflict().body("A secret with this name already exists")
+        }
+        SecretServiceError::SecretNotFound(_) => HttpResponse::NotFound().finish(),
+        SecretServiceError::CipherError(_) | SecretServiceError::DatabaseError => {
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+const OPENING: &str = "${{";
+const CLOSING: &str = "}}";
+const SECRETS: &str = "secrets";
+
+/// A part of a manifest text: either plain text or a reference such as `${{ secrets.NAME }}`.
+enum Segment<'a> {
+    Text(&'a str),
+    Reference { name: &'a str, reference: &'a str },
+}
+
+/// Whether the name can be given to a secret: letters, digits and `_`, not starting with a digit.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// The value named by an expression in the namespace, e.g. `NAME` for ` secrets.NAME `.
+fn referenced_name<'a>(expression: &'a str, namespace: &str) -> Option<&'a str> {
+    let name = expression
+        .trim()
+        .strip_prefix(namespace)?
+        .strip_prefix('.')?;
+    is_valid_name(name).then_some(name)
+}
+
+fn segments<'a>(text: &'a str, namespace: &str) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(OPENING) {
+        let inner = start + OPENING.len();
+        let Some(length) = rest[inner..].find(CLOSING) else {
+            break;
+        };
+        let end = inner + length + CLOSING.len();
+        match referenced_name(&rest[inner..inner + length], namespace) {
+            Some(name) => {
+                segments.push(Segment::Text(&rest[..start]));
+                segments.push(Segment::Reference {
+                    name,
+                    reference: &rest[start..end],
+                });
+            }
+            // Other expressions are left as is
+            None => segments.push(Segment::Text(&rest[..end])),
+        }
+        rest = &rest[end..];
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+/// The names of the secrets referenced in the text.
+pub fn references(text: &str) -> Vec<&str> {
+    references_in(text, SECRETS)
+}
+
+/// Replace the secret references of the text by their values. Unknown secrets are left as is.
+pub fn substitute(text: &str, secrets: &HashMap<String, String>) -> String {
+    substitute_in(text, SECRETS, secrets)
+}
+
+/// The names referenced in the text within the namespace, e.g. `NAME` for `${{ matrix.NAME }}` in `matrix`.
+pub fn references_in<'a>(text: &'a str, namespace: &str) -> Vec<&'a str> {
+    segments(text, namespace)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Reference { name, .. } => Some(name),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+/// Replace the references of the text within the namespace by their values. Unknown names are left as is.
+pub fn substitute_in(text: &str, namespace: &str, values: &HashMap<String, String>) -> String {
+    segments(text, namespace)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text,
+            Segment::Reference { name, reference } => {
+                values.get(name).map(String::as_str).unwrap_or(reference)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use sqlx::PgPool;
+
+#[derive(Debug, Clone)]
+pub struct SecretDTO {
+    #[allow(dead_code)]
+    pub id: i64,
+    pub name: String,
+    pub nonce: Vec<u8>,
+    /// The encrypted value
+    pub value: Vec<u8>,
+}
+
+pub struct SecretRepository {
+    pool: Arc<PgPool>,
+}
+
+impl SecretRepository {
+    pub fn new(pool: Arc<PgPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Create a secret, returns None if a secret with this name already exists.
+    pub async fn create(
+        &self,
+        name: &str,
+        nonce: &[u8],
+        value: &[u8],
+    ) -> Result<Option<SecretDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            SecretDTO,
+            r#"INSERT INTO secrets (name, nonce, value) VALUES ($1, $2, $3) ON CONFLICT (name) DO NOTHING RETURNING *"#,
+            name,
+            nonce,
+            value
+        )
+        .fetch_optional(self.pool.as_ref())
+        .await
+    }
+
+    /// Replace the value of a secret, returns None if there is no secret with this name.
+    pub async fn update(
+        &self,
+        name: &str,
+        nonce: &[u8],
+        value: &[u8],
+    ) -> Result<Option<SecretDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            SecretDTO,
+            r#"UPDATE secrets SET nonce = $2, value = $3 WHERE name = $1 RETURNING *"#,
+            name,
+            nonce,
+            value
+        )
+        .fetch_optional(self.pool.as_ref())
+        .await
+    }
+
+    /// Delete a secret, returns false if there is no secret with this name.
+    pub async fn delete(&self, name: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(r#"DELETE FROM secrets WHERE name = $1"#, name)
+            .execute(self.pool.as_ref())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn find_all_names(&self) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!(r#"SELECT name FROM secrets ORDER BY name"#)
+            .fetch_all(self.pool.as_ref())
+            .await?;
+        Ok(rows.into_iter().map(|row| row.name).collect())
+    }
+
+    pub async fn find_by_names(&self, names: &[String]) -> Result<Vec<SecretDTO>, sqlx::Error> {
+        sqlx::query_as!(
+            SecretDTO,
+            r#"SELECT * FROM secrets WHERE name = ANY($1)"#,
+            names
+        )
+        .fetch_all(self.pool.as_ref())
+        .await
+    }
+}
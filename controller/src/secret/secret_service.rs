@@ -0,0 +1,123 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use sqlx::PgPool;
+use tracing::error;
+
+use super::{
+    secret_cipher::{SecretCipher, SecretCipherError},
+    secret_reference::{is_valid_name, references},
+    secret_repository::SecretRepository,
+    Secret,
+};
+
+#[derive(Debug)]
+pub enum SecretServiceError {
+    InvalidName,
+    AlreadyExists,
+    SecretNotFound(String),
+    CipherError(SecretCipherError),
+    DatabaseError,
+}
+
+pub struct SecretService {
+    repository: Arc<SecretRepository>,
+    cipher: SecretCipher,
+}
+
+fn database_error(e: sqlx::Error) -> SecretServiceError {
+    error!("Error while accessing secrets: {:?}", e);
+    SecretServiceError::DatabaseError
+}
+
+impl SecretService {
+    pub fn new(pool: Arc<PgPool>, cipher: SecretCipher) -> Self {
+        let repository = Arc::new(SecretRepository::new(pool));
+        Self { repository, cipher }
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<Secret>, SecretServiceError> {
+        let names = self
+            .repository
+            .find_all_names()
+            .await
+            .map_err(database_error)?;
+        Ok(names.into_iter().map(Secret::new).collect())
+    }
+
+    pub async fn create(&self, name: &str, value: &str) -> Result<Secret, SecretServiceError> {
+        if !is_valid_name(name) {
+            return Err(SecretServiceError::InvalidName);
+        }
+        let (nonce, ciphertext) = self
+            .cipher
+            .encrypt(name, value)
+            .map_err(SecretServiceError::CipherError)?;
+        let secret = self
+            .repository
+            .create(name, &nonce, &ciphertext)
+            .await
+            .map_err(database_error)?
+            .ok_or(SecretServiceError::AlreadyExists)?;
+        Ok(Secret::new(secret.name))
+    }
+
+    pub async fn update(&self, name: &str, value: &str) -> Result<Secret, SecretServiceError> {
+        let (nonce, ciphertext) = self
+            .cipher
+            .encrypt(name, value)
+            .map_err(SecretServiceError::CipherError)?;
+        let secret = self
+            .repository
+            .update(name, &nonce, &ciphertext)
+            .await
+            .map_err(database_error)?
+            .ok_or_else(|| SecretServiceError::SecretNotFound(name.to_string()))?;
+        Ok(Secret::new(secret.name))
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), SecretServiceError> {
+        if !self.repository.delete(name).await.map_err(database_error)? {
+            return Err(SecretServiceError::SecretNotFound(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Decrypt the secrets referenced in the texts, by name.
+    /// Fails with the name of the first referenced secret that does not exist.
+    pub async fn resolve<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a String>,
+    ) -> Result<HashMap<String, String>, SecretServiceError> {
+        let names: BTreeSet<String> = texts
+            .into_iter()
+            .flat_map(|text| references(text))
+            .map(String::from)
+            .collect();
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let names: Vec<String> = names.into_iter().collect();
+        let mut secrets = HashMap::new();
+        for secret in self
+            .repository
+            .find_by_names(&names)
+            .await
+            .map_err(database_error)?
+        {
+            let value = self
+                .cipher
+                .decrypt(&secret.name, &secret.nonce, &secret.value)
+                .map_err(SecretServiceError::CipherError)?;
+            secrets.insert(secret.name, value);
+        }
+
+        match names.into_iter().find(|name| !secrets.contains_key(name)) {
+            Some(name) => Err(SecretServiceError::SecretNotFound(name)),
+            None => Ok(secrets),
+        }
+    }
+}
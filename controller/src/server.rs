@@ -2,11 +2,12 @@
 
 use std::thread;
 
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use scheduler::{
     controller_server::{Controller, ControllerServer},
-    ActionRequest, ActionResponse,
+    ActionRequest, ActionResponse, ArtifactChunk, ArtifactsRequest, CancelActionRequest,
+    CancelActionResponse,
 };
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -19,15 +20,23 @@ pub mod scheduler {
 #[derive(Debug)]
 pub struct MockSchedulerService {}
 
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
 #[async_trait]
 impl Controller for MockSchedulerService {
     type ScheduleActionStream = ReceiverStream<Result<ActionResponse, Status>>;
+    type DownloadArtifactsStream = ReceiverStream<Result<ArtifactChunk, Status>>;
 
     async fn schedule_action(
         &self,
         _request: Request<ActionRequest>,
     ) -> Result<Response<Self::ScheduleActionStream>, Status> {
-        let (tx, rx) = mpsc::channel(10);
+        let (tx, rx) = mpsc::channel(11);
 
         println!(
             "{}",
@@ -41,6 +50,10 @@ impl Controller for MockSchedulerService {
                 .unwrap()
         );
 
+        for artifact in &_request.get_ref().input_artifacts {
+            println!("INFO: input artifact {} at {}", artifact.path, artifact.url);
+        }
+
         for _i in 0..10 {
             println!("INFO: scheduled");
             tx.send(Ok(ActionResponse {
@@ -50,15 +63,62 @@ impl Controller for MockSchedulerService {
                     completion: scheduler::ActionStatus::Scheduled as i32,
                     exit_code: Some(1),
                 }),
+                sequence: _i + 1,
+                timestamp: now_millis(),
+                step: Some(0),
+                stream: scheduler::LogStream::Stdout as i32,
             }))
             .await
             .expect("should be sent");
 
             thread::sleep(Duration::from_millis(500));
         }
+        // End the action, so that the actions needing it can run
+        tx.send(Ok(ActionResponse {
+            action_id: _request.get_ref().action_id,
+            log: "INFO: completed".to_string(),
+            result: Some(scheduler::ActionResult {
+                completion: scheduler::ActionStatus::Completed as i32,
+                exit_code: Some(0),
+            }),
+            sequence: 11,
+            timestamp: now_millis(),
+            step: None,
+            stream: scheduler::LogStream::System as i32,
+        }))
+        .await
+        .expect("should be sent");
 
         Ok(Response::new(ReceiverStream::new(rx)))
     }
+
+    async fn cancel_action(
+        &self,
+        request: Request<CancelActionRequest>,
+    ) -> Result<Response<CancelActionResponse>, Status> {
+        println!("INFO: cancelled {}", request.get_ref().action_id);
+        Ok(Response::new(CancelActionResponse {
+            action_id: request.get_ref().action_id,
+        }))
+    }
+
+    async fn download_artifacts(
+        &self,
+        request: Request<ArtifactsRequest>,
+    ) -> Result<Response<Self::DownloadArtifactsStream>, Status> {
+        println!("INFO: artifacts of {}", request.get_ref().action_id);
+        let (tx, rx) = mpsc::channel(2);
+        // A single artifact, in two chunks
+        for data in ["mock ", "artifact"] {
+            tx.send(Ok(ArtifactChunk {
+                path: "artifact.txt".to_string(),
+                data: data.as_bytes().to_vec(),
+            }))
+            .await
+            .expect("should be sent");
+        }
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
 }
 
 #[tokio::main]
@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use futures::stream;
+
+    use crate::artifact::{
+        artifact_controller::archive_name,
+        artifact_service::{receive_archives, ArtifactServiceError, ReceivedArchive},
+    };
+    use crate::grpc_scheduler::ArtifactChunk;
+
+    fn chunk(path: &str, data: &str) -> ArtifactChunk {
+        ArtifactChunk {
+            path: path.to_string(),
+            data: data.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receive_archives_splits_chunks_by_path() {
+        let directory = std::env::temp_dir().join("sealci-controller-artifacts-split");
+        let chunks = stream::iter(vec![
+            Ok(chunk("target/release/app", "app ")),
+            Ok(chunk("target/release/app", "binary")),
+            Ok(chunk("dist/app.tar.gz", "tarball")),
+        ]);
+
+        let archives = receive_archives(&directory, chunks).await.unwrap();
+
+        assert_eq!(
+            archives,
+            vec![
+                ReceivedArchive {
+                    path: "target/release/app".to_string(),
+                    file: directory.join("0.part"),
+                    size: 10,
+                },
+                ReceivedArchive {
+                    path: "dist/app.tar.gz".to_string(),
+                    file: directory.join("1.part"),
+                    size: 7,
+                },
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&archives[0].file).unwrap(),
+            "app binary"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&archives[1].file).unwrap(),
+            "tarball"
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_receive_archives_fails_with_transfer() {
+        let directory = std::env::temp_dir().join("sealci-controller-artifacts-failed");
+        let chunks = stream::iter(vec![
+            Ok(chunk("target/release/app", "app ")),
+            Err(tonic::Status::unavailable("Agent unreachable")),
+        ]);
+
+        let error = receive_archives(&directory, chunks).await.unwrap_err();
+
+        assert!(matches!(error, ArtifactServiceError::TransferError));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_archive_name() {
+        assert_eq!(archive_name("target/release/app"), "app.tar");
+        assert_eq!(archive_name("docs/"), "docs.tar");
+        assert_eq!(archive_name("."), "artifact.tar");
+        assert_eq!(archive_name("weird\"name"), "weird_name.tar");
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use crate::parser::condition::{Condition, ConditionContext};
+
+    fn context(success: bool, failure: bool) -> ConditionContext<'static> {
+        ConditionContext {
+            success,
+            failure,
+            event: "commit",
+            branch: "main",
+        }
+    }
+
+    #[test]
+    fn test_condition_status_functions() {
+        let failure = Condition::parse("failure()").unwrap();
+        assert!(failure.evaluate(&context(false, true)));
+        assert!(!failure.evaluate(&context(true, false)));
+
+        let always = Condition::parse("always()").unwrap();
+        assert!(always.evaluate(&context(false, false)));
+
+        let not_success = Condition::parse("!success()").unwrap();
+        assert!(not_success.evaluate(&context(false, false)));
+    }
+
+    #[test]
+    fn test_condition_comparisons() {
+        let on_main = Condition::parse("branch == 'main' && event != \"pull_request\"").unwrap();
+        assert!(on_main.evaluate(&context(true, false)));
+        // Without status function, the needed actions must have completed successfully
+        assert!(!on_main.evaluate(&context(false, true)));
+
+        let cleanup =
+            Condition::parse("always() && (branch == 'release' || event == 'commit')").unwrap();
+        assert!(cleanup.evaluate(&context(false, true)));
+
+        let elsewhere = Condition::parse("'main' != branch").unwrap();
+        assert!(!elsewhere.evaluate(&context(true, false)));
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        for (condition, reason) in [
+            ("failed()", "unknown function `failed()`"),
+            ("tag == 'v1'", "unknown variable `tag`"),
+            ("branch = 'main'", "unexpected `=`"),
+            ("branch", "expected `==` or `!=`"),
+            ("(always()", "expected `)`"),
+            ("always() success()", "unexpected `success`"),
+            ("branch == 'main", "unterminated string"),
+            ("", "unexpected end of the condition"),
+        ] {
+            assert_eq!(Condition::parse(condition), Err(reason.to_string()));
+        }
+    }
+}
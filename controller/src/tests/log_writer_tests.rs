@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use time::OffsetDateTime;
+
+    use crate::{
+        database::database::Database,
+        grpc_scheduler::LogStream,
+        logs::{log_hub::LogHub, log_repository::LogRepository, log_writer::LogWriter, LogLine},
+    };
+
+    /// Lines written by a chatty build
+    const LINES: usize = 100_000;
+
+    fn line(sequence: usize) -> LogLine {
+        LogLine {
+            sequence: Some(sequence as i64),
+            timestamp: OffsetDateTime::now_utc(),
+            step: Some(0),
+            stream: LogStream::Stdout,
+            message: format!("   Compiling crate-{} v0.1.0", sequence),
+        }
+    }
+
+    /// Create an action to write the lines of, returning the ids of its pipeline and of itself.
+    async fn create_action(database: &Database) -> (i64, i64) {
+        let pipeline_id: i64 = sqlx::query_scalar(
+            "INSERT INTO pipelines (repository_url, name) VALUES ('https://example.com/bench.git', 'log-bench') RETURNING id",
+        )
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+        let run_id: i64 = sqlx::query_scalar(
+            "INSERT INTO pipeline_runs (pipeline_id, number, trigger, status) VALUES ($1, 1, 'manual', 'running') RETURNING id",
+        )
+        .bind(pipeline_id)
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+        let action_id: i64 = sqlx::query_scalar(
+            "INSERT INTO actions (pipeline_id, run_id, name, status, type, container_uri) VALUES ($1, $2, 'build', 'ACTION_STATUS_RUNNING', 'Container', 'rust') RETURNING id",
+        )
+        .bind(pipeline_id)
+        .bind(run_id)
+        .fetch_one(&database.pool)
+        .await
+        .unwrap();
+        (pipeline_id, action_id)
+    }
+
+    async fn delete_pipeline(database: &Database, pipeline_id: i64) {
+        for query in [
+            "DELETE FROM actions WHERE pipeline_id = $1",
+            "DELETE FROM pipeline_runs WHERE pipeline_id = $1",
+            "DELETE FROM pipelines WHERE id = $1",
+        ] {
+            sqlx::query(query)
+                .bind(pipeline_id)
+                .execute(&database.pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Compares storing the lines of an action one by one with storing them in batches. Needs the database of
+    /// `DATABASE_URL`, run with `cargo test --release log_ingestion -- --ignored --nocapture`.
+    #[tokio::test]
+    #[ignore = "benchmark, needs a database"]
+    async fn bench_log_ingestion() {
+        let database = Database::new(&std::env::var("DATABASE_URL").unwrap()).await;
+        let repository = Arc::new(LogRepository::new(Arc::new(database.pool.clone())));
+        let hub = Arc::new(LogHub::new());
+
+        let (pipeline_id, action_id) = create_action(&database).await;
+        let started = Instant::now();
+        for sequence in 1..=LINES {
+            repository
+                .create_line(action_id, 1, &line(sequence))
+                .await
+                .unwrap();
+        }
+        let one_by_one = started.elapsed();
+        delete_pipeline(&database, pipeline_id).await;
+
+        let (pipeline_id, action_id) = create_action(&database).await;
+        let mut writer = LogWriter::new(Arc::clone(&repository), hub, action_id, 1);
+        let started = Instant::now();
+        for sequence in 1..=LINES {
+            writer.write(line(sequence)).await.unwrap();
+        }
+        writer.flush().await.unwrap();
+        let batched = started.elapsed();
+
+        let logs = repository.find_by_action_id(action_id).await.unwrap();
+        delete_pipeline(&database, pipeline_id).await;
+        assert_eq!(logs.len(), LINES);
+        assert!(logs
+            .iter()
+            .zip(1..)
+            .all(|(log, sequence)| log.sequence == Some(sequence)));
+
+        for (name, elapsed) in [("one by one", one_by_one), ("batched", batched)] {
+            println!(
+                "{} lines {}: {:.2}s, {:.0} lines/s",
+                LINES,
+                name,
+                elapsed.as_secs_f64(),
+                LINES as f64 / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
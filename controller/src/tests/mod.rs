@@ -1 +1,7 @@
 pub mod yaml_parser_tests;
+pub mod secret_tests;
+pub mod run_tests;
+pub mod log_writer_tests;
+pub mod condition_tests;
+pub mod retry_tests;
+pub mod artifact_tests;
@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::grpc_scheduler::ActionStatus;
+    use crate::parser::manifest::FailureClass;
+    use crate::parser::pipe_parser::RetryPolicy;
+    use crate::pipeline::pipeline_service::PipelineServiceError;
+    use crate::scheduler::{failure_class, AttemptResult};
+
+    fn attempt(status: ActionStatus, exit_code: Option<i32>) -> AttemptResult {
+        AttemptResult { status, exit_code }
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            backoff: 10,
+            on: vec![FailureClass::Infrastructure],
+        };
+        assert!(retry.retries(1, FailureClass::Infrastructure));
+        assert!(retry.retries(2, FailureClass::Infrastructure));
+        assert!(!retry.retries(3, FailureClass::Infrastructure));
+        assert!(!retry.retries(1, FailureClass::ExitCode));
+
+        assert_eq!(retry.delay(1), Duration::from_secs(10));
+        assert_eq!(retry.delay(2), Duration::from_secs(20));
+        assert_eq!(retry.delay(3), Duration::from_secs(40));
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let retry = RetryPolicy {
+            max_attempts: 10,
+            backoff: 3600,
+            on: vec![FailureClass::Infrastructure],
+        };
+        assert_eq!(retry.delay(1), Duration::from_secs(3600));
+        // Doubled after each failed attempt, the wait before the last one would be more than 10 days
+        assert_eq!(retry.delay(9), Duration::from_secs(3600));
+
+        let retry = RetryPolicy {
+            backoff: 10,
+            ..retry
+        };
+        assert_eq!(retry.delay(8), Duration::from_secs(1280));
+        assert_eq!(retry.delay(9), Duration::from_secs(2560));
+        assert_eq!(retry.delay(10), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn test_failure_class() {
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::Error, Some(2)))),
+            Some(FailureClass::ExitCode)
+        );
+        // The agent was lost, or the container could not start
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::Error, None))),
+            Some(FailureClass::Infrastructure)
+        );
+        // The stream ended before the action was over
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::Running, None))),
+            Some(FailureClass::Infrastructure)
+        );
+        assert_eq!(
+            failure_class(&Err(PipelineServiceError::SchedulerError)),
+            Some(FailureClass::Infrastructure)
+        );
+
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::Completed, Some(0)))),
+            None
+        );
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::TimedOut, None))),
+            None
+        );
+        assert_eq!(
+            failure_class(&Ok(attempt(ActionStatus::Cancelled, None))),
+            None
+        );
+        assert_eq!(failure_class(&Err(PipelineServiceError::SecretError)), None);
+    }
+}
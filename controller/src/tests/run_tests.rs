@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use time::{Duration, OffsetDateTime};
+
+    use crate::{grpc_scheduler::ActionStatus, run::RunStatus, timing::Timing};
+
+    #[test]
+    fn test_run_status_aggregation() {
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Completed, ActionStatus::Running]),
+            RunStatus::Running
+        );
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Error, ActionStatus::Pending]),
+            RunStatus::Running
+        );
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Completed, ActionStatus::Completed]),
+            RunStatus::Succeeded
+        );
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Completed, ActionStatus::TimedOut]),
+            RunStatus::Failed
+        );
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Error, ActionStatus::Cancelled]),
+            RunStatus::Cancelled
+        );
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Completed, ActionStatus::Skipped]),
+            RunStatus::Succeeded
+        );
+        assert_eq!(
+            RunStatus::aggregate([
+                ActionStatus::Error,
+                ActionStatus::Skipped,
+                ActionStatus::Completed
+            ]),
+            RunStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_run_status_all_skipped() {
+        assert_eq!(
+            RunStatus::aggregate([ActionStatus::Skipped, ActionStatus::Skipped]),
+            RunStatus::Skipped
+        );
+        assert!(RunStatus::Skipped.is_finished());
+    }
+
+    #[test]
+    fn test_run_status_parsing() {
+        for status in [
+            RunStatus::Pending,
+            RunStatus::Running,
+            RunStatus::Succeeded,
+            RunStatus::Failed,
+            RunStatus::Cancelled,
+            RunStatus::Skipped,
+        ] {
+            assert_eq!(RunStatus::from_str(&status.to_string()), Ok(status));
+        }
+        assert!(RunStatus::from_str("SUCCEEDED").is_err());
+    }
+
+    #[test]
+    fn test_timing_duration() {
+        let started_at = OffsetDateTime::now_utc();
+        let finished_at = started_at + Duration::seconds(42);
+
+        assert_eq!(
+            Timing::new(None, Some(started_at), Some(finished_at)).duration,
+            Some(42)
+        );
+        assert_eq!(Timing::new(None, Some(started_at), None).duration, None);
+    }
+}
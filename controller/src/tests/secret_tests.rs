@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::secret::{
+        secret_cipher::{SecretCipher, SecretCipherError},
+        secret_reference::{is_valid_name, references, substitute},
+    };
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_secret_references() {
+        let command = "docker login -u ${{ secrets.REGISTRY_USER }} -p ${{secrets.REGISTRY_TOKEN}}";
+
+        assert_eq!(references(command), vec!["REGISTRY_USER", "REGISTRY_TOKEN"]);
+        assert!(
+            references("echo $HOME ${{ env.HOME }} ${{ secrets.bad-name }} ${{ secrets.")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_secret_substitution() {
+        let secrets = HashMap::from([("TOKEN".to_string(), "hunter2".to_string())]);
+
+        assert_eq!(
+            substitute(
+                "curl -H 'Authorization: ${{ secrets.TOKEN }}' ${{ secrets.OTHER }} ${{ env.X }}",
+                &secrets
+            ),
+            "curl -H 'Authorization: hunter2' ${{ secrets.OTHER }} ${{ env.X }}"
+        );
+    }
+
+    #[test]
+    fn test_secret_name_validation() {
+        assert!(is_valid_name("DEPLOY_TOKEN"));
+        assert!(is_valid_name("_token2"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2FA"));
+        assert!(!is_valid_name("DEPLOY-TOKEN"));
+    }
+
+    #[test]
+    fn test_secret_cipher_round_trip() {
+        let cipher = SecretCipher::new(KEY).unwrap();
+        let (nonce, ciphertext) = cipher.encrypt("TOKEN", "hunter2").unwrap();
+
+        assert_ne!(ciphertext, b"hunter2");
+        assert_eq!(
+            cipher.decrypt("TOKEN", &nonce, &ciphertext).unwrap(),
+            "hunter2"
+        );
+        // The value is bound to the name of its secret
+        assert!(matches!(
+            cipher.decrypt("OTHER", &nonce, &ciphertext),
+            Err(SecretCipherError::DecryptionError)
+        ));
+    }
+
+    #[test]
+    fn test_secret_cipher_rejects_invalid_key() {
+        assert!(matches!(
+            SecretCipher::new("not hex"),
+            Err(SecretCipherError::InvalidKey)
+        ));
+        assert!(matches!(
+            SecretCipher::new("0011"),
+            Err(SecretCipherError::InvalidKey)
+        ));
+    }
+}
@@ -12,7 +12,11 @@ fn read_yaml_file(file_path: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::pipe_parser::{ManifestParser, ParsingError, PipeParser, Type};
+    use crate::parser::manifest::{json_schema, FailureClass};
+    use crate::parser::pipe_parser::{
+        IncludedFiles, ManifestParser, ParsingError, PipeParser, RetryPolicy, Span, Type,
+        WarningKind,
+    };
 
     use super::*;
 
@@ -53,6 +57,165 @@ mod tests {
         assert_eq!(deploy_action.commands.len(), 2);
         assert!(deploy_action.commands[0].contains("s3://my-app-bucket"));
         assert!(deploy_action.commands[1].contains("aws cloudfront create-invalidation"));
+        assert!(deploy_action.labels.is_empty());
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_labels() {
+        let yaml_content = read_yaml_file("src/tests/data/labeled_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_ok());
+        let pipeline = result.unwrap();
+        assert_eq!(pipeline.actions.len(), 1);
+        assert_eq!(pipeline.actions[0].configuration_version, "rust:1.81");
+        assert_eq!(pipeline.actions[0].labels, vec!["arch:arm64", "privileged"]);
+    }
+
+    #[test]
+    fn test_block_lists_after_commands_are_not_commands() {
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/block_lists_pipeline.yaml"))
+            .unwrap();
+        assert_eq!(pipeline.actions.len(), 4);
+        assert_eq!(pipeline.actions[0].name, "build (x86_64)");
+        assert_eq!(pipeline.actions[0].commands.len(), 2);
+        assert_eq!(pipeline.actions[2].labels, vec!["gpu"]);
+        let deploy = &pipeline.actions[3];
+        assert_eq!(deploy.needs, vec!["build (x86_64)", "build (aarch64)", "test"]);
+        assert_eq!(deploy.commands, vec!["./deploy.sh", "echo done"]);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_timeouts() {
+        let yaml_content = read_yaml_file("src/tests/data/timed_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_ok());
+        let pipeline = result.unwrap();
+        assert_eq!(pipeline.default_timeout, Some(1800));
+
+        // Actions without a timeout fall back to the default timeout
+        let build_action = pipeline.actions.iter().find(|a| a.name == "build").unwrap();
+        assert_eq!(build_action.timeout, Some(1800));
+        let test_action = pipeline.actions.iter().find(|a| a.name == "test").unwrap();
+        assert_eq!(test_action.timeout, Some(90));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_invalid_timeout() {
+        let yaml_content = read_yaml_file("src/tests/data/invalid_timeout_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind, ParsingError::InvalidTimeout);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_shell_and_scripts() {
+        let yaml_content = read_yaml_file("src/tests/data/shell_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_ok());
+        let pipeline = result.unwrap();
+        assert_eq!(pipeline.shell.as_deref(), Some("bash -eo pipefail"));
+
+        let build_action = pipeline.actions.iter().find(|a| a.name == "build").unwrap();
+        assert_eq!(build_action.shell.as_deref(), Some("bash -eo pipefail"));
+        assert_eq!(build_action.commands.len(), 2);
+        assert_eq!(build_action.commands[0], "cargo build --release 2>&1 | tee build.log");
+        assert!(build_action.commands[1].starts_with("for crate in agent controller scheduler; do\n"));
+        assert!(build_action.commands[1].contains("  echo \"Testing $crate\"\n"));
+
+        let lint_action = pipeline.actions.iter().find(|a| a.name == "lint").unwrap();
+        assert_eq!(lint_action.shell.as_deref(), Some("sh"));
+        assert_eq!(lint_action.commands, vec!["echo \"multi-line\"\necho 'script'\n"]);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_env() {
+        let yaml_content = read_yaml_file("src/tests/data/env_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_ok());
+        let pipeline = result.unwrap();
+        assert_eq!(pipeline.env.len(), 2);
+
+        // Action variables override the pipeline ones
+        let build_action = pipeline.actions.iter().find(|a| a.name == "build").unwrap();
+        assert_eq!(build_action.env["RUST_LOG"], "debug");
+        assert_eq!(build_action.env["CARGO_TERM_COLOR"], "always");
+        assert_eq!(build_action.env["JOBS"], "4");
+        assert_eq!(build_action.env["RELEASE"], "true");
+
+        let test_action = pipeline.actions.iter().find(|a| a.name == "test").unwrap();
+        assert_eq!(test_action.env, pipeline.env);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_invalid_env() {
+        let yaml_content = read_yaml_file("src/tests/data/invalid_env_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert_eq!(result.unwrap_err().kind, ParsingError::InvalidEnv);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_needs() {
+        let yaml_content = read_yaml_file("src/tests/data/needs_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert!(result.is_ok());
+        let pipeline = result.unwrap();
+
+        let needs = |name: &str| {
+            pipeline
+                .actions
+                .iter()
+                .find(|a| a.name == name)
+                .unwrap()
+                .needs
+                .clone()
+        };
+        assert_eq!(needs("deploy"), vec!["build", "test"]);
+        assert_eq!(needs("test"), vec!["build"]);
+        assert!(needs("build").is_empty());
+        assert!(needs("lint").is_empty());
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_cyclic_needs() {
+        let yaml_content = read_yaml_file("src/tests/data/cyclic_needs_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert_eq!(result.unwrap_err().kind, ParsingError::DependencyCycle);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_unknown_needs() {
+        let yaml_content = read_yaml_file("src/tests/data/unknown_needs_pipeline.yaml");
+
+        let parser = PipeParser {};
+        let result = parser.parse(yaml_content);
+
+        assert_eq!(result.unwrap_err().kind, ParsingError::UnknownDependency);
     }
 
     #[test]
@@ -63,7 +226,7 @@ mod tests {
         let result = parser.parse(yaml_content);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParsingError::MissingName);
+        assert_eq!(result.unwrap_err().kind, ParsingError::MissingName);
     }
 
     #[test]
@@ -74,7 +237,7 @@ mod tests {
         let result = parser.parse(yaml_content);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParsingError::MissingActions);
+        assert_eq!(result.unwrap_err().kind, ParsingError::MissingActions);
     }
 
     #[test]
@@ -85,7 +248,7 @@ mod tests {
         let result = parser.parse(yaml_content);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParsingError::YamlNotCompliant);
+        assert_eq!(result.unwrap_err().kind, ParsingError::YamlNotCompliant);
     }
 
     #[test]
@@ -95,7 +258,7 @@ mod tests {
         let result = parser.parse(yaml_content);
 
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParsingError::MissingCommands);
+        assert_eq!(result.unwrap_err().kind, ParsingError::MissingCommands);
     }
 
     #[test]
@@ -126,7 +289,7 @@ mod tests {
         let result = parser.parse(yaml_content);
 
         assert!(result.is_err());
-        assert!(matches!(result, Err(ParsingError::InvalidActionName)));
+        assert!(matches!(result, Err(e) if e.kind == ParsingError::InvalidActionName));
     }
 
     #[test]
@@ -138,7 +301,369 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result,
-            Err(ParsingError::InconsistentCommandIndentation)
+            Err(e) if e.kind == ParsingError::InconsistentCommandIndentation
         ));
     }
+
+    #[test]
+    fn test_errors_point_at_the_action_and_field() {
+        let parser = PipeParser {};
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/unknown_needs_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.action.as_deref(), Some("test"));
+        assert_eq!(error.span, Some(Span { line: 7, column: 5 }));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/invalid_timeout_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidTimeout);
+        assert_eq!(error.action.as_deref(), Some("build"));
+        assert_eq!(error.span, Some(Span { line: 7, column: 5 }));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/inconsistent_command_indentation.yaml"))
+            .unwrap_err();
+        assert_eq!(error.span, Some(Span { line: 9, column: 9 }));
+    }
+
+    #[test]
+    fn test_missing_field_points_at_its_action() {
+        let parser = PipeParser {};
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/empty_commands_pipeline.yaml"))
+            .unwrap_err();
+
+        assert_eq!(error.kind, ParsingError::MissingCommands);
+        assert!(error.action.is_some());
+        assert!(error.span.is_some());
+    }
+
+    #[test]
+    fn test_malformed_yaml_is_located() {
+        let parser = PipeParser {};
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/malformed_yaml_pipeline.yaml"))
+            .unwrap_err();
+
+        assert_eq!(error.kind, ParsingError::YamlNotCompliant);
+        assert!(error.message.starts_with("The manifest is not valid YAML"));
+        assert!(error.span.is_some_and(|span| span.line >= 6));
+    }
+
+    #[test]
+    fn test_validation_warns_about_unpinned_images_and_unknown_keys() {
+        let parser = PipeParser {};
+        let validation = parser
+            .validate(read_yaml_file("src/tests/data/unpinned_pipeline.yaml"))
+            .unwrap();
+
+        let warnings: Vec<_> = validation
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.action.as_deref(), warning.span))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (
+                    WarningKind::UnpinnedImage,
+                    Some("build"),
+                    Some(Span { line: 6, column: 7 })
+                ),
+                (
+                    WarningKind::UnpinnedImage,
+                    Some("test"),
+                    Some(Span { line: 11, column: 7 })
+                ),
+                (
+                    WarningKind::UnknownKey,
+                    Some("test"),
+                    Some(Span { line: 12, column: 5 })
+                ),
+            ]
+        );
+        assert_eq!(validation.pipeline.actions.len(), 4);
+    }
+
+    #[test]
+    fn test_manifest_error_serialization() {
+        let parser = PipeParser {};
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/unknown_needs_pipeline.yaml"))
+            .unwrap_err();
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["code"], "UnknownDependency");
+        assert_eq!(json["action"], "test");
+        assert_eq!(json["span"]["line"], 7);
+        assert_eq!(json["span"]["column"], 5);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_version() {
+        let parser = PipeParser {};
+
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/versioned_pipeline.yaml"))
+            .unwrap();
+        assert_eq!(pipeline.name, "Versioned Pipeline");
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/unsupported_version_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::UnsupportedVersion);
+        assert_eq!(error.span, Some(Span { line: 1, column: 1 }));
+    }
+
+    #[test]
+    fn test_manifest_json_schema() {
+        let schema = serde_json::to_value(json_schema()).unwrap();
+
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(schema["required"], serde_json::json!(["name"]));
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["version"]["enum"], serde_json::json!([1]));
+
+        let action = &schema["definitions"]["ActionDefinition"];
+        assert_eq!(
+            action["required"],
+            serde_json::json!(["configuration", "commands"])
+        );
+        assert_eq!(action["additionalProperties"], false);
+        assert!(action["properties"]["needs"].is_object());
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_matrix() {
+        let yaml_content = read_yaml_file("src/tests/data/matrix_pipeline.yaml");
+        let parser = PipeParser {};
+        let pipeline = parser.parse(yaml_content).unwrap();
+
+        let names: Vec<&str> = pipeline
+            .actions
+            .iter()
+            .map(|action| action.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "test (18, alpine)",
+                "test (18, bookworm)",
+                "test (20, alpine)",
+                "test (20, bookworm)",
+                "test (22, alpine)",
+                "test (22, bookworm)",
+                "report",
+            ]
+        );
+
+        let test = &pipeline.actions[2];
+        assert_eq!(test.configuration_version, "node:20-alpine");
+        assert_eq!(test.commands[0], "echo \"Testing on node 20\"");
+        assert_eq!(test.env.get("NODE").map(String::as_str), Some("20"));
+        assert_eq!(test.env.get("OS").map(String::as_str), Some("alpine"));
+        assert_eq!(test.env.get("CI").map(String::as_str), Some("true"));
+
+        let report = &pipeline.actions[6];
+        assert_eq!(report.needs, names[..6]);
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_invalid_matrix() {
+        let parser = PipeParser {};
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/empty_matrix_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidMatrix);
+        assert_eq!(error.action.as_deref(), Some("test"));
+        assert_eq!(error.span, Some(Span { line: 7, column: 5 }));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/unknown_matrix_value_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::UnknownMatrixValue);
+        assert_eq!(error.span, Some(Span { line: 6, column: 7 }));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/duplicate_matrix_value_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::DuplicateMatrixValue);
+        assert_eq!(error.action.as_deref(), Some("test"));
+        assert_eq!(error.span, Some(Span { line: 8, column: 28 }));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_templates() {
+        let yaml_content = read_yaml_file("src/tests/data/templates_pipeline.yaml");
+        let parser = PipeParser {};
+        let pipeline = parser.parse(yaml_content).unwrap();
+
+        let lint = &pipeline.actions[0];
+        assert_eq!(lint.name, "lint");
+        assert_eq!(lint.configuration_version, "node:20");
+        assert_eq!(lint.labels, vec!["linux"]);
+        assert_eq!(lint.commands, vec!["npm run lint"]);
+
+        let test = &pipeline.actions[1];
+        assert_eq!(test.configuration_version, "node:22");
+        assert_eq!(test.labels, vec!["linux"]);
+        assert_eq!(test.env.get("NODE_ENV").map(String::as_str), Some("test"));
+        assert_eq!(test.env.get("CACHE").map(String::as_str), Some("none"));
+        assert_eq!(test.env.get("CI").map(String::as_str), Some("true"));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/unknown_template_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::UnknownTemplate);
+        assert_eq!(error.action.as_deref(), Some("build"));
+        assert_eq!(error.span, Some(Span { line: 4, column: 5 }));
+    }
+
+    fn included_files() -> IncludedFiles {
+        IncludedFiles::from([
+            (
+                "ci/templates.yml".to_string(),
+                read_yaml_file("src/tests/data/includes/templates.yml"),
+            ),
+            (
+                "ci/lint.yml".to_string(),
+                read_yaml_file("src/tests/data/includes/lint.yml"),
+            ),
+            (
+                "ci/cycle.yml".to_string(),
+                read_yaml_file("src/tests/data/includes/cycle.yml"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_includes() {
+        let yaml_content = read_yaml_file("src/tests/data/included_pipeline.yaml");
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse_with_includes(yaml_content.clone(), &included_files())
+            .unwrap();
+
+        let names: Vec<&str> = pipeline
+            .actions
+            .iter()
+            .map(|action| action.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["build", "clippy"]);
+        assert_eq!(pipeline.actions[1].configuration_version, "rust:1.81");
+        assert_eq!(pipeline.actions[1].needs, vec!["build"]);
+
+        let error = parser.parse(yaml_content).unwrap_err();
+        assert_eq!(error.kind, ParsingError::IncludeNotFound);
+        assert!(error.message.contains("ci/templates.yml"));
+        assert_eq!(error.span, Some(Span { line: 3, column: 5 }));
+
+        let error = parser
+            .parse_with_includes(
+                read_yaml_file("src/tests/data/cyclic_include_pipeline.yaml"),
+                &included_files(),
+            )
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::IncludeCycle);
+        assert_eq!(error.include_chain, vec!["ci/cycle.yml"]);
+        assert_eq!(error.span, Some(Span { line: 1, column: 1 }));
+        assert!(error
+            .to_string()
+            .contains("ci/cycle.yml > ci/cycle.yml in ci/cycle.yml at line 1"));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_condition() {
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/conditional_pipeline.yaml"))
+            .unwrap();
+        assert_eq!(pipeline.actions[0].condition, None);
+        assert_eq!(pipeline.actions[1].condition.as_deref(), Some("failure()"));
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/invalid_condition_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidCondition);
+        assert_eq!(error.action.as_deref(), Some("build"));
+        assert_eq!(error.span, Some(Span { line: 4, column: 5 }));
+        assert!(error.message.ends_with("unknown function `failed()`"));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_retry() {
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/retry_pipeline.yaml"))
+            .unwrap();
+        assert_eq!(
+            pipeline.actions[0].retry,
+            Some(RetryPolicy {
+                max_attempts: 2,
+                backoff: 10,
+                on: vec![FailureClass::Infrastructure],
+            })
+        );
+        assert_eq!(
+            pipeline.actions[1].retry,
+            Some(RetryPolicy {
+                max_attempts: 3,
+                backoff: 30,
+                on: vec![FailureClass::Infrastructure, FailureClass::ExitCode],
+            })
+        );
+        assert_eq!(pipeline.actions[2].retry, None);
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/invalid_retry_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidRetry);
+        assert_eq!(error.action.as_deref(), Some("build"));
+        assert_eq!(error.span, Some(Span { line: 6, column: 12 }));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_artifacts() {
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/artifacts_pipeline.yaml"))
+            .unwrap();
+        assert_eq!(pipeline.actions[0].artifacts, vec!["target/release/app"]);
+        assert_eq!(pipeline.actions[1].artifacts, vec!["dist/*.tar.gz", "docs/"]);
+        assert!(pipeline.actions[2].artifacts.is_empty());
+
+        let error = parser
+            .parse(read_yaml_file("src/tests/data/invalid_artifacts_pipeline.yaml"))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidArtifacts);
+        assert_eq!(error.action.as_deref(), Some("build"));
+        assert_eq!(error.span, Some(Span { line: 6, column: 9 }));
+    }
+
+    #[test]
+    fn test_yaml_parsing_with_artifacts_from() {
+        let parser = PipeParser {};
+        let pipeline = parser
+            .parse(read_yaml_file("src/tests/data/artifacts_from_pipeline.yaml"))
+            .unwrap();
+        let deploy = &pipeline.actions[3];
+        assert_eq!(deploy.name, "deploy");
+        assert_eq!(
+            deploy.artifacts_from,
+            vec!["build (x86_64)", "build (aarch64)"]
+        );
+        assert!(pipeline.actions[2].artifacts_from.is_empty());
+
+        let error = parser
+            .parse(read_yaml_file(
+                "src/tests/data/invalid_artifacts_from_pipeline.yaml",
+            ))
+            .unwrap_err();
+        assert_eq!(error.kind, ParsingError::InvalidArtifactsFrom);
+        assert_eq!(error.action.as_deref(), Some("deploy"));
+        assert_eq!(error.span, Some(Span { line: 11, column: 5 }));
+    }
 }
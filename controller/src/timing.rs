@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// When a pipeline or an action was created, started and finished.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timing {
+    #[serde(with = "time::serde::rfc3339::option", skip_serializing_if = "Option::is_none", default)]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", skip_serializing_if = "Option::is_none", default)]
+    pub started_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option", skip_serializing_if = "Option::is_none", default)]
+    pub finished_at: Option<OffsetDateTime>,
+    /// Seconds between the start and the end, once finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<i64>,
+}
+
+impl Timing {
+    pub fn new(
+        created_at: Option<OffsetDateTime>,
+        started_at: Option<OffsetDateTime>,
+        finished_at: Option<OffsetDateTime>,
+    ) -> Self {
+        let duration = match (started_at, finished_at) {
+            (Some(started_at), Some(finished_at)) => {
+                Some((finished_at - started_at).whole_seconds())
+            }
+            _ => None,
+        };
+        Self {
+            created_at,
+            started_at,
+            finished_at,
+            duration,
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use reqwest::multipart::{Form, Part};
-use reqwest::{Client, Response};
+use reqwest::{Client, Response, StatusCode};
+use serde_json::Value;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
+/// Send the actions file to the controller, along with what started the run, `commit` or `pull_request`.
 pub async fn send_to_controller(
     repo_url: &str,
+    trigger: &str,
     actions_file_path: &Path,
     controller_endpoint: Arc<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -18,19 +21,28 @@ pub async fn send_to_controller(
     let mut buffer: Vec<u8> = Vec::new();
     file.read_to_end(&mut buffer)?;
 
-    // Créer une partie de formulaire avec le contenu du fichier
-    let file_part: Part = Part::bytes(buffer).file_name(
-        actions_file_path
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned(),
-    );
+    let file_name: String = actions_file_path
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+
+    let directory = actions_file_path.parent().unwrap_or(Path::new(""));
+    let includes = read_includes(&buffer, directory);
+
+    validate_manifest(
+        &client,
+        &buffer,
+        &file_name,
+        &includes,
+        &controller_endpoint,
+    )
+    .await?;
 
     // Créer le formulaire multipart et ajouter les parties
-    let form: Form = Form::new()
+    let form: Form = manifest_form(&buffer, &file_name, &includes)
         .text("repo_url", repo_url.to_string())
-        .part("body", file_part);
+        .text("trigger", trigger.to_string());
 
     debug!("Sending pipeline to controller {}", controller_endpoint);
     // Envoyer la requête POST
@@ -44,3 +56,107 @@ pub async fn send_to_controller(
 
     Ok(())
 }
+
+/// Check the manifest with the controller before sending it, so that an invalid one does not create a pipeline.
+/// A controller without the validation endpoint accepts every manifest.
+async fn validate_manifest(
+    client: &Client,
+    buffer: &[u8],
+    file_name: &str,
+    includes: &[(String, Vec<u8>)],
+    controller_endpoint: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let form: Form = manifest_form(buffer, file_name, includes);
+    let validate_endpoint = format!("{}/validate", controller_endpoint.trim_end_matches('/'));
+    debug!("Validating pipeline with controller {}", validate_endpoint);
+    let res: Response = client
+        .post(validate_endpoint.as_str())
+        .multipart(form)
+        .send()
+        .await?;
+
+    match res.status() {
+        StatusCode::OK | StatusCode::UNPROCESSABLE_ENTITY => {
+            let report: Value = res.json().await?;
+            for warning in report["warnings"].as_array().into_iter().flatten() {
+                warn!("{}: {}", file_name, describe(warning));
+            }
+            if report["valid"].as_bool() == Some(false) {
+                return Err(format!("{}: {}", file_name, describe(&report["error"])).into());
+            }
+        }
+        status => debug!("Manifest not validated, controller answered {}", status),
+    }
+    Ok(())
+}
+
+/// The manifest along with the manifests it includes, each one named by its path from the directory of the manifest.
+fn manifest_form(buffer: &[u8], file_name: &str, includes: &[(String, Vec<u8>)]) -> Form {
+    let form = Form::new().part(
+        "body",
+        Part::bytes(buffer.to_vec()).file_name(file_name.to_string()),
+    );
+    includes.iter().fold(form, |form, (path, content)| {
+        form.part(
+            "include",
+            Part::bytes(content.clone()).file_name(path.clone()),
+        )
+    })
+}
+
+/// Read the manifests the manifest includes, and those they include in turn, by path from the directory of the
+/// manifest. The missing ones are left for the controller to report.
+fn read_includes(buffer: &[u8], directory: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut includes: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut pending = included_paths(buffer);
+    while let Some(path) = pending.pop() {
+        if includes.iter().any(|(included, _)| *included == path) {
+            continue;
+        }
+        match std::fs::read(directory.join(&path)) {
+            Ok(content) => {
+                pending.extend(included_paths(&content));
+                includes.push((path, content));
+            }
+            Err(e) => debug!("Included manifest {} not read: {}", path, e),
+        }
+    }
+    includes
+}
+
+/// Paths listed under `include` in a manifest.
+fn included_paths(buffer: &[u8]) -> Vec<String> {
+    let Ok(manifest) = serde_yaml::from_slice::<serde_yaml::Value>(buffer) else {
+        return Vec::new();
+    };
+    match &manifest["include"] {
+        serde_yaml::Value::String(path) => vec![path.clone()],
+        serde_yaml::Value::Sequence(paths) => paths
+            .iter()
+            .filter_map(|path| path.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The message of an error or a warning of the controller, along with where it is in the manifest.
+fn describe(problem: &Value) -> String {
+    let mut description = problem["message"]
+        .as_str()
+        .unwrap_or("invalid manifest")
+        .to_string();
+    if let Some(action) = problem["action"].as_str() {
+        description.push_str(&format!(" (action {})", action));
+    }
+    if let Some(chain) = problem["include_chain"].as_array() {
+        let files: Vec<&str> = chain.iter().filter_map(Value::as_str).collect();
+        description.push_str(&format!(" in {}", files.join(" > ")));
+    }
+    if let (Some(line), Some(column)) = (
+        problem["span"]["line"].as_u64(),
+        problem["span"]["column"].as_u64(),
+    ) {
+        description.push_str(&format!(" at line {} column {}", line, column));
+    }
+    description
+}
@@ -175,6 +175,7 @@ pub fn create_commit_listener(
                 Arc::clone(&config),
                 repo_url.clone(),
                 Arc::clone(&controller_endpoint),
+                "commit",
             );
             let _ = listen_to_commits(&config, callback).await;
         }
@@ -192,6 +193,7 @@ pub fn create_pull_request_listener(
                 Arc::clone(&config),
                 repo_url.clone(),
                 Arc::clone(&controller_endpoint),
+                "pull_request",
             );
             let _ = listen_to_pull_requests(&config, callback).await;
         }
@@ -202,6 +204,7 @@ pub fn create_callback(
     config: Arc<SingleConfig>,
     repo_url: String,
     controller_endpoint: Arc<String>,
+    trigger: &'static str,
 ) -> impl Fn() {
     move || {
         info!("Callback triggered");
@@ -212,6 +215,7 @@ pub fn create_callback(
             info!("Sending pipeline to controller...");
             match send_to_controller(
                 &repo_url,
+                trigger,
                 Path::new(&config.actions_path),
                 controller_endpoint_clone,
             )
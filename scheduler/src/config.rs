@@ -0,0 +1,20 @@
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+
+use crate::logic::scheduling_strategy::STRATEGY_NAMES;
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct Config {
+    /// The address the Scheduler gRPC server listens on
+    #[clap(env, long, default_value = "[::0]:50051")]
+    pub addr: String,
+
+    /// The strategy used to select the Agent that runs an Action
+    #[clap(env, long, default_value = "least-loaded", value_parser = PossibleValuesParser::new(STRATEGY_NAMES))]
+    pub strategy: String,
+
+    /// Seconds without a health report after which an Agent is evicted from the Pool
+    #[clap(env, long, default_value = "30")]
+    pub heartbeat_timeout: u64,
+}
@@ -8,7 +8,7 @@ use tonic::Request;
 use std::error::Error;
 use log::{info, error};
 
-pub(crate) async fn execution_action(action: Action, agent_address: String) -> Result<tonic::Streaming<proto::ActionResponseStream>, Box<dyn Error + Send + Sync>> {
+pub(crate) async fn execution_action(action: &Action, agent_address: String) -> Result<tonic::Streaming<proto::ActionResponseStream>, Box<dyn Error + Send + Sync>> {
     // Handle case where hostname is empty
     if agent_address == "unknown:unknown" {
         error!("Hostname is empty. Cannot resolve IP address.");
@@ -23,12 +23,45 @@ pub(crate) async fn execution_action(action: Action, agent_address: String) -> R
         context: Some(proto::ExecutionContext {
             r#type: action.get_runner_type(),
             container_image: Some(String::from(action.get_container_image())),
+            labels: action.get_labels().to_vec(),
+            shell: action.get_context().shell.clone(),
+            env: action.get_context().env.clone(),
+            secrets: action.get_context().secrets.clone(),
         }),
         commands: action.get_commands().iter().map(|comm: &String| String::from(comm)).collect(),
         repo_url: action.get_repo_url().clone(),
+        timeout: action.get_timeout(),
+        artifacts: action.get_artifacts().to_vec(),
+        input_artifacts: action.get_input_artifacts().iter().map(|artifact| proto::ArtifactReference { path: artifact.path.clone(), url: artifact.url.clone() }).collect(),
     });
 
     // The response stream is returned to the caller function for further processing. (controller_interface.rs)
     let response_stream = client.execution_action(request).await?.into_inner();
     Ok(response_stream)
 }
+
+pub(crate) async fn cancel_action(action_id: u32, agent_address: String) -> Result<(), tonic::Status> {
+    let endpoint = Channel::builder(agent_address.parse().map_err(|_| tonic::Status::internal("Invalid Agent address"))?);
+    let channel = endpoint.connect().await.map_err(|e| {
+        error!("Failed to connect to Agent {}: {}", agent_address, e);
+        tonic::Status::unavailable("Agent unreachable")
+    })?;
+    let mut client = ActionClient::new(channel);
+
+    client.cancel_action(Request::new(proto::CancelActionRequest { action_id })).await?;
+    info!("Cancellation of Action {} sent to Agent {}", action_id, agent_address);
+    Ok(())
+}
+
+pub(crate) async fn download_artifacts(action_id: u32, agent_address: String) -> Result<tonic::Streaming<proto::ArtifactChunk>, tonic::Status> {
+    let endpoint = Channel::builder(agent_address.parse().map_err(|_| tonic::Status::internal("Invalid Agent address"))?);
+    let channel = endpoint.connect().await.map_err(|e| {
+        error!("Failed to connect to Agent {}: {}", agent_address, e);
+        tonic::Status::unavailable("Agent unreachable")
+    })?;
+    let mut client = ActionClient::new(channel);
+
+    let response_stream = client.download_artifacts(Request::new(proto::ArtifactsRequest { action_id })).await?.into_inner();
+    info!("Downloading the artifacts of Action {} from Agent {}", action_id, agent_address);
+    Ok(response_stream)
+}
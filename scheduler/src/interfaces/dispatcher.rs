@@ -0,0 +1,356 @@
+use crate::interfaces::client::agent_client;
+
+use crate::logic::action_queue_logic::{Action, ActionsQueue};
+use crate::logic::agent_pool_logic::AgentPool;
+
+//use crate::proto::controller as proto
+use crate::proto::actions as agent_proto;
+use crate::proto::scheduler as proto;
+
+use log::{info, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Mutex};
+
+/// Capacity of the channel announcing evicted Agents to the tasks running Actions.
+const EVICTIONS_CAPACITY: usize = 64;
+
+/// Time after which the artifacts of a completed Action are no longer offered, if the Controller never downloaded them.
+const ARTIFACT_SOURCE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The Agent keeping the artifacts of a completed Action.
+struct ArtifactSource {
+    agent_id: u32,
+    agent_ip: String,
+    completed_at: Instant,
+}
+
+/// How an Action ended on the Agent it was dispatched to.
+enum ExecutionOutcome {
+    /// The Agent's response stream ended, or the Agent could not be reached.
+    Finished,
+    /// The Agent was evicted from the Pool before the Action ended.
+    AgentLost,
+}
+
+/// The Dispatcher takes the Actions waiting in the Action Queue and sends them to the Agents of the Agent Pool.
+/// It is cheap to clone: every clone shares the same Agent Pool and Action Queue.
+#[derive(Clone)]
+pub struct ActionDispatcher {
+    agent_pool: Arc<Mutex<AgentPool>>,
+    action_queue: Arc<Mutex<ActionsQueue>>,
+    evictions: broadcast::Sender<u32>, // IDs of the Agents removed from the Pool
+    artifact_sources: Arc<Mutex<HashMap<u32, ArtifactSource>>>, // Agents keeping the artifacts of completed Actions, by Action ID
+}
+
+impl ActionDispatcher {
+    pub fn new(agent_pool: Arc<Mutex<AgentPool>>, action_queue: Arc<Mutex<ActionsQueue>>) -> Self {
+        let (evictions, _) = broadcast::channel(EVICTIONS_CAPACITY);
+        Self {
+            agent_pool,
+            action_queue,
+            evictions,
+            artifact_sources: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Add an Action to the Action Queue, tell the Controller it is pending, and try to dispatch it right away.
+    pub(crate) async fn enqueue(&self, action: Action) {
+        let pending_response = status_response(
+            action.get_action_id(),
+            "Action queued, waiting for an available Agent",
+            proto::ActionStatus::Pending,
+        );
+        let _ = action.get_response_tx().send(Ok(pending_response));
+
+        self.action_queue.lock().await.push(action);
+        self.dispatch().await;
+    }
+
+    /// Send as many queued Actions as possible to the Agents of the Pool.
+    /// Called whenever an Action is queued, an Agent registers or reports its health, or an Agent finishes an Action.
+    /// The future is boxed because dispatching spawns tasks that dispatch again once their Action is done.
+    pub fn dispatch(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async move {
+            // Always lock the Agent Pool before the Action Queue to avoid deadlocks.
+            let mut pool = self.agent_pool.lock().await;
+            let mut queue = self.action_queue.lock().await;
+
+            let abandoned = queue.remove_abandoned();
+            if abandoned > 0 {
+                warn!("Dropped {} queued Action(s) no longer awaited by the Controller", abandoned);
+            }
+
+            // Actions are dispatched in order of arrival. An Action that no Agent can take stays in the queue,
+            // without blocking the Actions behind it (e.g. when it requires labels no registered Agent advertises).
+            let mut index = 0;
+            while let Some(queued) = queue.get(index) {
+                let (agent_id, agent_ip) = match pool.select(queued.get_labels()) {
+                    Some(agent) => (agent.get_id(), agent.get_ip_address()),
+                    None => {
+                        index += 1;
+                        continue;
+                    }
+                };
+
+                let action = match queue.remove(index) {
+                    Some(action) => action,
+                    None => break,
+                };
+
+                // Reserve the slot right away, so the next Actions of the queue do not land on a full Agent.
+                pool.reserve(agent_id, action.get_action_id());
+                info!("Dispatching Action {} to Agent {}", action.get_action_id(), agent_ip);
+                let scheduled_response = status_response(
+                    action.get_action_id(),
+                    &format!("Action scheduled on Agent {}", agent_ip),
+                    proto::ActionStatus::Scheduled,
+                );
+                let _ = action.get_response_tx().send(Ok(scheduled_response));
+
+                let dispatcher = self.clone();
+                // Subscribe before spawning, so an eviction happening right away is not missed.
+                let evictions = self.evictions.subscribe();
+                tokio::spawn(async move {
+                    let action_id = action.get_action_id();
+                    dispatcher.execute(action, agent_id, agent_ip, evictions).await;
+                    // The Agent is done with this Action: it may now take one from the queue.
+                    dispatcher.agent_pool.lock().await.release(agent_id, action_id);
+                    dispatcher.dispatch().await;
+                });
+            }
+
+            if !queue.is_empty() {
+                info!("No Agents available, {} Action(s) left in the queue", queue.len());
+            }
+        })
+    }
+
+    /// Cancel an Action: a queued Action is dropped from the Action Queue, a dispatched one is cancelled by its Agent.
+    /// Either way, the Controller receives a final cancelled status on the Action's response stream.
+    pub async fn cancel(&self, action_id: u32) -> Result<(), tonic::Status> {
+        let agent_ip = {
+            let pool = self.agent_pool.lock().await;
+            let mut queue = self.action_queue.lock().await;
+
+            if let Some(action) = queue.remove_by_id(action_id) {
+                info!("Action {} cancelled while queued", action_id);
+                let cancelled_response = status_response(action_id, "Action cancelled", proto::ActionStatus::Cancelled);
+                let _ = action.get_response_tx().send(Ok(cancelled_response));
+                return Ok(());
+            }
+
+            match pool.find_running(action_id) {
+                Some(agent) => agent.get_ip_address(),
+                None => return Err(tonic::Status::not_found(format!("Action {} is neither queued nor running", action_id))),
+            }
+        };
+
+        // The Agent reports the cancelled status through the Action's response stream.
+        agent_client::cancel_action(action_id, agent_ip).await
+    }
+
+    /// Give the address of the Agent keeping the artifacts of a completed Action, and forget it: the Agent deletes the
+    /// artifacts once they are downloaded.
+    pub async fn take_artifact_source(&self, action_id: u32) -> Result<String, tonic::Status> {
+        self.artifact_sources
+            .lock()
+            .await
+            .remove(&action_id)
+            .map(|source| source.agent_ip)
+            .ok_or_else(|| tonic::Status::not_found(format!("Action {} has no artifacts to download", action_id)))
+    }
+
+    /// Remove the Agent of the given ID from the Pool, and notify the tasks running Actions on it.
+    pub async fn evict_agent(&self, agent_id: u32) {
+        let removed = self.agent_pool.lock().await.remove(agent_id);
+        if let Some(agent) = removed {
+            warn!("Agent {} evicted from the Pool with {} Action(s) in flight", agent_id, agent.get_in_flight().len());
+            let _ = self.evictions.send(agent_id);
+            self.forget_artifact_sources(agent_id).await;
+        }
+    }
+
+    /// Remove from the Pool every Agent that has not reported its health for longer than the timeout.
+    pub async fn evict_expired_agents(&self, timeout: Duration) {
+        let expired = self.agent_pool.lock().await.remove_expired(timeout);
+        for agent in expired {
+            warn!(
+                "Agent {} missed its heartbeat deadline, evicted from the Pool with {} Action(s) in flight",
+                agent.get_id(),
+                agent.get_in_flight().len()
+            );
+            let _ = self.evictions.send(agent.get_id());
+            self.forget_artifact_sources(agent.get_id()).await;
+        }
+    }
+
+    /// Forget the artifacts kept by an evicted Agent, they can no longer be downloaded.
+    async fn forget_artifact_sources(&self, agent_id: u32) {
+        self.artifact_sources.lock().await.retain(|_, source| source.agent_id != agent_id);
+    }
+
+    /// Forget the artifacts the Controller did not download in time.
+    pub async fn expire_artifact_sources(&self, ttl: Duration) {
+        let mut artifact_sources = self.artifact_sources.lock().await;
+        let before = artifact_sources.len();
+        artifact_sources.retain(|_, source| source.completed_at.elapsed() < ttl);
+        let expired = before - artifact_sources.len();
+        if expired > 0 {
+            info!("Forgot the artifacts of {} Action(s) that were not downloaded in time", expired);
+        }
+    }
+
+    /// Periodically evict the Agents that went silent, and forget the artifacts that were not downloaded.
+    /// Runs forever, meant to be spawned in its own task.
+    pub async fn watch_liveness(self, period: Duration, timeout: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            self.evict_expired_agents(timeout).await;
+            self.expire_artifact_sources(ARTIFACT_SOURCE_TTL).await;
+        }
+    }
+
+    /// Run the Action on the Agent until it ends or the Agent is evicted.
+    /// An Action lost before the Agent sent anything back is rescheduled, otherwise it is failed.
+    async fn execute(&self, action: Action, agent_id: u32, agent_ip: String, mut evictions: broadcast::Receiver<u32>) {
+        let mut forwarded = 0;
+        let outcome = tokio::select! {
+            _ = self.forward(&action, agent_id, agent_ip, &mut forwarded) => ExecutionOutcome::Finished,
+            _ = wait_for_eviction(&mut evictions, agent_id) => ExecutionOutcome::AgentLost,
+        };
+
+        if let ExecutionOutcome::AgentLost = outcome {
+            if forwarded == 0 {
+                warn!("Agent {} lost before running Action {}, rescheduling it", agent_id, action.get_action_id());
+                let pending_response = status_response(
+                    action.get_action_id(),
+                    &format!("Agent {} was lost before running the Action, rescheduling", agent_id),
+                    proto::ActionStatus::Pending,
+                );
+                let _ = action.get_response_tx().send(Ok(pending_response));
+                self.action_queue.lock().await.push_front(action);
+            } else {
+                warn!("Agent {} lost while running Action {}", agent_id, action.get_action_id());
+                let error_response = status_response(
+                    action.get_action_id(),
+                    &format!("Agent {} was lost while running the Action", agent_id),
+                    proto::ActionStatus::Error,
+                );
+                let _ = action.get_response_tx().send(Ok(error_response));
+            }
+        }
+    }
+
+    /// Send the Action to the Agent and forward the response/transfer the logs back to the Controller.
+    /// `forwarded` counts the responses forwarded so far, and stays readable if the forwarding is interrupted.
+    async fn forward(&self, action: &Action, agent_id: u32, agent_ip: String, forwarded: &mut usize) {
+        let tx = action.get_response_tx();
+
+        // execution_action returns a Stream, which is validated, error-handled, and passed to schedule action's response stream. This is the log transfer operation.
+        match agent_client::execution_action(action, agent_ip.clone()).await {
+            // The response stream from the Agent is received and processed here directly; in a spawned task. This is simply because it is much easier than handling multiple streams by ID.
+            // Each received message is forwarded back to the controller.
+            Ok(mut response_stream) => {
+                while let Some(response) = response_stream.message().await.unwrap_or(None) {
+                    // Use match to handle the presence or absence of a result in the response
+                    match response.result {
+                        Some(result) => {
+                            info!("Received a response with a result {:?}", result);
+                            let completion = match result.exit_code {
+                                Some(exit_code) => {
+                                    if exit_code == 0 {
+                                        proto::ActionStatus::Completed
+                                    } else {
+                                        proto::ActionStatus::Error
+                                    }
+                                }
+                                // The Agent and Scheduler status enums do not share the same values.
+                                None => match agent_proto::ActionStatus::try_from(result.completion) {
+                                    Ok(agent_proto::ActionStatus::Cancelled) => proto::ActionStatus::Cancelled,
+                                    Ok(agent_proto::ActionStatus::TimedOut) => proto::ActionStatus::TimedOut,
+                                    Ok(agent_proto::ActionStatus::Error) => proto::ActionStatus::Error,
+                                    _ => proto::ActionStatus::try_from(result.completion)
+                                        .unwrap_or(proto::ActionStatus::Running),
+                                },
+                            };
+                            // The Agent keeps the artifacts of the Action once it completed, until the Controller downloads them.
+                            // The source is known before the Controller hears of the completion.
+                            if completion == proto::ActionStatus::Completed && !action.get_artifacts().is_empty() {
+                                let source = ArtifactSource { agent_id, agent_ip: agent_ip.clone(), completed_at: Instant::now() };
+                                self.artifact_sources.lock().await.insert(action.get_action_id(), source);
+                            }
+                            let action_response = proto::ActionResponse {
+                                action_id: response.action_id,
+                                log: response.log,
+                                result: Some(proto::ActionResult {
+                                    completion: completion.into(),
+                                    exit_code: result.exit_code,
+                                }),
+                                sequence: response.sequence,
+                                timestamp: response.timestamp,
+                                step: response.step,
+                                // Unlike the status enums, both LogStream enums share the same values.
+                                stream: response.stream,
+                            };
+
+                            if tx.send(Ok(action_response)).is_err() {
+                                warn!("Failed to send action response");
+                                break;
+                            }
+                            *forwarded += 1;
+                        }
+                        None => {
+                            warn!("Received a response with no result");
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Failed to execute Action: {}", e);
+                let _ = tx.send(Err(tonic::Status::internal("Failed to execute Action")));
+            }
+        }
+    }
+}
+
+/// Resolve once the Agent of the given ID is evicted from the Pool.
+async fn wait_for_eviction(evictions: &mut broadcast::Receiver<u32>, agent_id: u32) {
+    loop {
+        match evictions.recv().await {
+            Ok(evicted_id) if evicted_id == agent_id => return,
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("Missed {} Agent eviction(s)", skipped);
+                continue;
+            }
+            // The Dispatcher is gone, no eviction can happen anymore.
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        }
+    }
+}
+
+/// Build a response carrying only a status update for the Controller.
+/// It is not numbered, as the sequence numbers are given by the Agent to the lines of the Action.
+fn status_response(action_id: u32, log: &str, status: proto::ActionStatus) -> proto::ActionResponse {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default();
+    proto::ActionResponse {
+        action_id,
+        log: log.to_string(),
+        result: Some(proto::ActionResult {
+            completion: status.into(),
+            exit_code: None,
+        }),
+        sequence: 0,
+        timestamp,
+        step: None,
+        stream: proto::LogStream::System.into(),
+    }
+}
@@ -1,24 +1,28 @@
 use crate::logic::agent_pool_logic::Agent as PoolAgent;
 use crate::logic::agent_pool_logic::{compute_score, AgentPool};
 use crate::logic::agent_pool_logic::Hostname;
+use crate::interfaces::dispatcher::ActionDispatcher;
 use log::{error, info};
 
 //use crate::proto::agent as proto;
 use crate::proto::scheduler as proto;
 use proto::agent_server::Agent;
 
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 
 pub struct AgentService {
     agent_pool: Arc<Mutex<AgentPool>>, // The ArcMutex is on the agent_pool, for the highest level of granularity on concurrency control
+    dispatcher: ActionDispatcher,
 }
 
 impl AgentService {
-    pub fn new(agent_pool: Arc<Mutex<AgentPool>>) -> Self {
+    pub fn new(agent_pool: Arc<Mutex<AgentPool>>, dispatcher: ActionDispatcher) -> Self {
         Self {
             agent_pool,
+            dispatcher,
         }
     }
 }
@@ -64,11 +68,13 @@ impl Agent for AgentService {
         let mut pool = self.agent_pool.lock().await;
 
         let id = pool.generate_unique_id();
-        let score = compute_score(input.cpu_avail, input.memory_avail);
+        let score = compute_score(input.cpu_avail, input.memory_avail / 100_000_000);  // Same scale as the health status reports.
         let new_hostname = Hostname::new(hostname.host, hostname.port);
 
+        info!("Agent advertises labels: {:?}", inner_req.labels);
+
         // Create a new Agent and add it to the Pool (it gets sorted)
-        let new_agent = PoolAgent::new(id, new_hostname, score);
+        let new_agent = PoolAgent::new(id, new_hostname, score, inner_req.labels, inner_req.max_concurrency);
 
         // Response is the newly created Agent's ID.
         let response = proto::RegisterAgentResponse {
@@ -76,6 +82,10 @@ impl Agent for AgentService {
         };
 
         pool.push(new_agent);
+        drop(pool);
+
+        // A new Agent is available: hand it the Actions waiting in the queue.
+        self.dispatcher.dispatch().await;
 
         Ok(tonic::Response::new(response))
     }
@@ -86,11 +96,15 @@ impl Agent for AgentService {
     ) -> Result<tonic::Response<proto::Empty>, tonic::Status> {
         let mut stream = request.into_inner();
 
+        // The Agents reporting through this stream. The stream is their lifeline: once it ends, they are considered dead.
+        let mut reporting_agents = HashSet::new();
+
         while let Some(health_status) = stream.next().await {
             let status = match health_status {
                 Ok(status) => status,
                 Err(e) => {
                     error!("Error receiving health status: {:?}", e);
+                    self.evict_agents(reporting_agents).await;
                     return Err(tonic::Status::internal("Error receiving health status"));
                 }
             };
@@ -120,6 +134,9 @@ impl Agent for AgentService {
                 }
             };
 
+            agent.heartbeat();
+            reporting_agents.insert(status.agent_id);
+
             // Compute the Agent's new score and set it.
             let updated_score = compute_score(health.cpu_avail, health.memory_avail / 100_000_000);  // Divide by 10^8 to have the same scale/order of magnitude as the CPU.
             agent.set_score(updated_score);
@@ -129,8 +146,24 @@ impl Agent for AgentService {
             if is_out_of_order {
                 pool.sort(); // Resort the Pool if the Agent is out of order
             }
+            drop(pool);
+
+            // The Agent may have freed up: dispatch the Actions waiting in the queue.
+            self.dispatcher.dispatch().await;
         }
 
+        info!("Health status stream closed");
+        self.evict_agents(reporting_agents).await;
+
         Ok(tonic::Response::new(proto::Empty {}))
     }
 }
+
+impl AgentService {
+    /// Remove the given Agents from the Pool, once their health status stream is closed.
+    async fn evict_agents(&self, agent_ids: HashSet<u32>) {
+        for agent_id in agent_ids {
+            self.dispatcher.evict_agent(agent_id).await;
+        }
+    }
+}
@@ -1,34 +1,35 @@
 use crate::interfaces::client::agent_client;
+use crate::interfaces::dispatcher::ActionDispatcher;
 
-use crate::logic::action_queue_logic::Action;
-use crate::logic::agent_pool_logic::AgentPool;
+use crate::logic::action_queue_logic::{Action, ActionSettings};
 
-use crate::proto::scheduler::ActionStatus;
 //use crate::proto::controller as proto
 use crate::proto::scheduler as proto;
 use proto::controller_server::Controller;
 
-use log::{info, warn};
-use std::sync::Arc;
+use log::info;
+use std::pin::Pin;
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
 use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::{Stream, StreamExt};
 
 pub struct ControllerService {
-    agent_pool: Arc<Mutex<AgentPool>>,
+    dispatcher: ActionDispatcher,
 }
 
 impl ControllerService {
-    pub fn new(agent_pool: Arc<Mutex<AgentPool>>) -> Self {
-        Self { agent_pool }
+    pub fn new(dispatcher: ActionDispatcher) -> Self {
+        Self { dispatcher }
     }
 }
 
 type ScheduleActionStream = UnboundedReceiverStream<Result<proto::ActionResponse, tonic::Status>>;
+type DownloadArtifactsStream = Pin<Box<dyn Stream<Item = Result<proto::ArtifactChunk, tonic::Status>> + Send>>;
 
 #[tonic::async_trait]
 impl Controller for ControllerService {
     type ScheduleActionStream = ScheduleActionStream;
+    type DownloadArtifactsStream = DownloadArtifactsStream;
 
     async fn schedule_action(
         &self,
@@ -45,100 +46,64 @@ impl Controller for ControllerService {
             runner_type.as_str_name()
         );
 
-        // Lock the agent pool a moment to check for available agents
-        let pool = self.agent_pool.lock().await;
-        let agent = match pool.peek() {
-            Some(agent) => agent,
-            None => {
-                warn!("No Agents available to execute Action");
-                // Send back an error response now, and close the stream.
-                let (tx, rx) = mpsc::unbounded_channel();
-                let error_response = proto::ActionResponse {
-                    action_id: action_request.action_id,
-                    log: "No agents available".to_string(),
-                    result: Some(proto::ActionResult {
-                        completion: proto::ActionStatus::Error.into(),
-                        exit_code: None,
-                    }),
-                };
-                tx.send(Ok(error_response)).unwrap_or_default(); // Send Ok or Err back? need to say schedule_action errored!!
-                return Ok(tonic::Response::new(UnboundedReceiverStream::new(rx)));
-            }
-        };
-
-        let agent_ip = agent.get_ip_address().to_string();
+        // Use an unbounded channel to create the response stream.
+        // The transmitter travels with the Action through the Action Queue, so the stream stays open while the Action waits for an Agent.
+        let (tx, rx) = mpsc::unbounded_channel();
 
         // Create the action object
+        let context = action_request.context.unwrap_or_default();
         let action = Action::new(
             action_request.action_id,
             proto::ExecutionContext {
                 container_image,
                 r#type: runner_type.into(),
+                labels: context.labels,
+                shell: context.shell,
+                env: context.env,
+                secrets: context.secrets,
             },
             action_request.commands,
             action_request.repo_url,
+            ActionSettings {
+                timeout: action_request.timeout,
+                artifacts: action_request.artifacts,
+                input_artifacts: action_request.input_artifacts,
+            },
+            tx,
         );
 
-        // Use an unbounded channel to create the response stream
-        let (tx, rx) = mpsc::unbounded_channel();
-        // The transmitter is passed into the spawned task to send the response back to the client.
-
-        // Spawn an async task to handle action execution
-        tokio::spawn(async move {
-            // Send the action to the agent and forward the response/transfer the logs
-            // The tokio::spawn function is used to create a new asynchronous task. To call execution_action without blocking the main schedule_action procedure.
-            // execution_action returns a Stream, which is validated, error-handled, and passed to schedule action's response stream. This is the log transfer operation.
-            match agent_client::execution_action(action, agent_ip).await {
-                // The response stream from the Agent is received and processed here directly; in a spawned task. This is simply because it is much easier than handling multiple streams by ID.
-                // Each received message is forwarded back to the controller.
-                Ok(mut response_stream) => {
-                    while let Some(response) = response_stream.message().await.unwrap_or(None) {
-                        // Use match to handle the presence or absence of a result in the response
-                        match response.result {
-                            Some(result) => {
-                                println!("Received a response with a result {:?}", result);
-                                let completion = match result.exit_code {
-                                    Some(exit_code) => {
-                                        if exit_code == 0 {
-                                            3
-                                        } else {
-                                            4
-                                        }
-                                    }
-                                    None => result.completion,
-                                };
-                                let action_response = proto::ActionResponse {
-                                    action_id: response.action_id,
-                                    log: response.log,
-                                    result: Some(proto::ActionResult {
-                                        completion: completion.into(),
-                                        exit_code: result.exit_code,
-                                    }),
-                                };
-
-                                if tx.send(Ok(action_response)).is_err() {
-                                    warn!("Failed to send action response");
-                                    break;
-                                }
-                            }
-                            None => {
-                                warn!("Received a response with no result");
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to execute Action: {}", e);
-                    let _ = tx.send(Err(tonic::Status::internal("Failed to execute Action")));
-                }
-            }
-        });
-
-        // Now outside the spawned task, the response stream is created and the receiver side of the channel is returned to the client/calling service.
+        // Queue the Action. It is sent to an Agent as soon as one is available, now or once an Agent registers or frees up.
+        self.dispatcher.enqueue(action).await;
 
+        // The receiver side of the channel is returned to the client/calling service.
         let response_stream = UnboundedReceiverStream::new(rx);
         Ok(tonic::Response::new(response_stream))
     }
+
+    async fn cancel_action(
+        &self,
+        request: tonic::Request<proto::CancelActionRequest>,
+    ) -> Result<tonic::Response<proto::CancelActionResponse>, tonic::Status> {
+        let action_id = request.into_inner().action_id;
+        info!("Received cancellation of Action {}", action_id);
+
+        self.dispatcher.cancel(action_id).await?;
+        Ok(tonic::Response::new(proto::CancelActionResponse { action_id }))
+    }
+
+    async fn download_artifacts(
+        &self,
+        request: tonic::Request<proto::ArtifactsRequest>,
+    ) -> Result<tonic::Response<Self::DownloadArtifactsStream>, tonic::Status> {
+        let action_id = request.into_inner().action_id;
+        info!("Received download of the artifacts of Action {}", action_id);
+
+        // The artifacts are streamed from the Agent that ran the Action, through the Scheduler.
+        let agent_ip = self.dispatcher.take_artifact_source(action_id).await?;
+        let artifact_stream = agent_client::download_artifacts(action_id, agent_ip).await?;
+        let chunks = artifact_stream.map(|chunk| chunk.map(|chunk| proto::ArtifactChunk { path: chunk.path, data: chunk.data }));
+        Ok(tonic::Response::new(Box::pin(chunks)))
+    }
 }
 
 impl ControllerService {
@@ -152,8 +117,8 @@ impl ControllerService {
             .ok_or_else(|| tonic::Status::invalid_argument("Context field is missing"))?;
 
         // Convert `context.r#type` (which is an `i32`) to a `RunnerType`
-        let runner_type = proto::RunnerType::from_i32(context.r#type)
-            .ok_or_else(|| tonic::Status::invalid_argument("Invalid RunnerType"))?;
+        let runner_type = proto::RunnerType::try_from(context.r#type)
+            .map_err(|_| tonic::Status::invalid_argument("Invalid RunnerType"))?;
 
         let container_image = context
             .container_image
@@ -1,24 +1,41 @@
 //use crate::proto::controller as proto;
 use crate::proto::scheduler as proto;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Sending half of the response stream opened by the Controller when it scheduled the Action.
+pub(crate) type ResponseSender = UnboundedSender<Result<proto::ActionResponse, tonic::Status>>;
 
 /// A struct representing an action in the queue.
 /// The action has an ID, a score, and additional fields from the ActionRequest proto.
+/// It also holds the sender of the Controller's response stream, so the Action can be answered once it leaves the queue.
 #[derive(Debug)]
 pub(crate) struct Action {
     action_id: u32,
     context: proto::ExecutionContext,
     commands: Vec<String>,
     repo_url: String,
+    settings: ActionSettings,
+    response_tx: ResponseSender,
+}
+
+/// How the Agent runs an Action, besides its context and commands.
+#[derive(Debug, Default)]
+pub(crate) struct ActionSettings {
+    pub(crate) timeout: Option<u32>, // Seconds after which the Agent stops the Action
+    pub(crate) artifacts: Vec<String>, // Glob patterns of the files the Agent keeps once the Action completed
+    pub(crate) input_artifacts: Vec<proto::ArtifactReference>, // Artifacts of other Actions the Agent unpacks before the first command
 }
 
 impl Action {
     /// Constructor
-    pub fn new(action_id: u32, context: proto::ExecutionContext, commands: Vec<String>, repo_url: String) -> Self {
+    pub fn new(action_id: u32, context: proto::ExecutionContext, commands: Vec<String>, repo_url: String, settings: ActionSettings, response_tx: ResponseSender) -> Self {
         Self {
             action_id,
             context,
             commands,
             repo_url,
+            settings,
+            response_tx,
         }
     }
 
@@ -45,6 +62,11 @@ impl Action {
         }
     }
 
+    /// Required labels getter
+    pub(crate) fn get_labels(&self) -> &[String] {
+        &self.context.labels
+    }
+
     /// Commands getter
     pub(crate) fn get_commands(&self) -> &[String] {
         &self.commands
@@ -55,6 +77,31 @@ impl Action {
         &self.repo_url
     }
 
+    /// Timeout getter
+    pub(crate) fn get_timeout(&self) -> Option<u32> {
+        self.settings.timeout
+    }
+
+    /// Artifacts getter
+    pub(crate) fn get_artifacts(&self) -> &[String] {
+        &self.settings.artifacts
+    }
+
+    /// Input artifacts getter
+    pub(crate) fn get_input_artifacts(&self) -> &[proto::ArtifactReference] {
+        &self.settings.input_artifacts
+    }
+
+    /// Response sender getter
+    pub(crate) fn get_response_tx(&self) -> &ResponseSender {
+        &self.response_tx
+    }
+
+    /// Check if the Controller is still listening to the Action's response stream
+    pub(crate) fn is_abandoned(&self) -> bool {
+        self.response_tx.is_closed()
+    }
+
     /// Action ID setter
     pub(crate) fn set_action_id(&mut self, action_id: u32) {
         self.action_id = action_id;
@@ -88,7 +135,7 @@ impl Action {
 }
 
 /// ActionsQueue is a collection of Actions stored in a vector.
-/// The vector is sorted whenever necessary to maintain order.
+/// Actions wait in the Queue, in order of arrival, until an Agent is available to execute them.
 pub struct ActionsQueue {
     actions: Vec<Action>,
 }
@@ -99,20 +146,37 @@ impl ActionsQueue {
         Self { actions: Vec::new() }
     }
 
-    /// Insert an Action into the Action Queue and sort the Queue by score.
+    /// Insert an Action at the back of the Action Queue.
     pub(crate) fn push(&mut self, item: Action) {
         self.actions.push(item);
     }
 
-    /// Remove and return the Action with the lowest score (that is, the first Action), or return None if the Queue is empty.
-    pub(crate) fn pop(&mut self) -> Option<Action> {
-        if self.actions.is_empty() {
-            None
+    /// Insert an Action at the front of the Action Queue, so it is the next one to be dispatched.
+    /// Used to give back its place to an Action that could not run on its Agent.
+    pub(crate) fn push_front(&mut self, item: Action) {
+        self.actions.insert(0, item);
+    }
+
+    /// Return a reference to the Action at the given position, or None if the position is out of the Queue.
+    pub(crate) fn get(&self, index: usize) -> Option<&Action> {
+        self.actions.get(index)
+    }
+
+    /// Remove and return the Action at the given position, or return None if the position is out of the Queue.
+    pub(crate) fn remove(&mut self, index: usize) -> Option<Action> {
+        if index < self.actions.len() {
+            Some(self.actions.remove(index))
         } else {
-            Some(self.actions.remove(0))
+            None
         }
     }
 
+    /// Remove and return the Action of the given ID, or return None if the Action is not in the Queue.
+    pub(crate) fn remove_by_id(&mut self, action_id: u32) -> Option<Action> {
+        let index = self.actions.iter().position(|action| action.action_id == action_id)?;
+        self.remove(index)
+    }
+
     /// Return the number of Actions in the Queue
     pub fn len(&self) -> usize {
         self.actions.len()
@@ -122,5 +186,17 @@ impl ActionsQueue {
     pub fn is_empty(&self) -> bool {
         self.actions.is_empty()
     }
-    
+
+    /// Drop the Actions whose Controller stopped listening to the response stream, and return how many were dropped.
+    pub(crate) fn remove_abandoned(&mut self) -> usize {
+        let before = self.actions.len();
+        self.actions.retain(|action| !action.is_abandoned());
+        before - self.actions.len()
+    }
+}
+
+impl Default for ActionsQueue {
+    fn default() -> Self {
+        Self::new()
+    }
 }
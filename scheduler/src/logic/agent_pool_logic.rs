@@ -1,4 +1,7 @@
 use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::logic::scheduling_strategy::{LeastLoaded, SchedulingStrategy};
 
 /// Top secret algorithm used to mathematically compute the freeness score of an Agent. Do not leak!
 pub(crate) fn compute_score(cpu_avail: u32, memory_avail: u64) -> u64 {
@@ -6,33 +9,74 @@ pub(crate) fn compute_score(cpu_avail: u32, memory_avail: u64) -> u64 {
 }
 
 /// A struct representing an Agent in the Pool.
-/// The Agent has an ID and a score.
+/// The Agent has an ID and a score, and the instant of its last sign of life (registration or health report).
+/// Its labels are the capabilities it advertised at registration, matched against the labels required by an Action.
+/// The Agent runs at most `max_concurrency` Actions at once (0 for no limit): a slot is reserved for each Action dispatched to it.
 #[derive(Eq, PartialEq, Debug)]
-pub(crate) struct Agent {
+pub struct Agent {
     id: u32,
     hostname: Hostname,
     score: u64,
+    labels: Vec<String>,
+    max_concurrency: u32,
+    in_flight: Vec<u32>, // IDs of the Actions running on the Agent
+    last_heartbeat: Instant,
 }
 
 impl Agent {
     /// Constructor
-    pub(crate) fn new(id: u32, hostname: Hostname, score: u64) -> Self {
+    pub(crate) fn new(id: u32, hostname: Hostname, score: u64, labels: Vec<String>, max_concurrency: u32) -> Self {
         Self {
-            id: id,
-            hostname: hostname,
-            score: score,
+            id,
+            hostname,
+            score,
+            labels,
+            max_concurrency,
+            in_flight: Vec::new(),
+            last_heartbeat: Instant::now(),
         }
     }
     /// ID getter
-    pub(crate) fn get_id(&self) -> u32 {
+    pub fn get_id(&self) -> u32 {
         self.id
     }
 
     /// Score getter
-    pub(crate) fn get_score(&self) -> u64 {
+    pub fn get_score(&self) -> u64 {
         self.score
     }
 
+    /// In-flight Actions getter
+    pub fn get_in_flight(&self) -> &[u32] {
+        &self.in_flight
+    }
+
+    /// Check if the Agent can take one more Action without exceeding its max concurrency.
+    pub fn has_capacity(&self) -> bool {
+        self.max_concurrency == 0 || (self.in_flight.len() as u32) < self.max_concurrency
+    }
+
+    /// Reserve a slot of the Agent for the given Action.
+    pub(crate) fn reserve(&mut self, action_id: u32) {
+        self.in_flight.push(action_id);
+    }
+
+    /// Release the slot reserved for the given Action, returning false if the Action was not running on the Agent.
+    pub(crate) fn release(&mut self, action_id: u32) -> bool {
+        match self.in_flight.iter().position(|id| *id == action_id) {
+            Some(index) => {
+                self.in_flight.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check if the Agent advertises every one of the required labels.
+    pub(crate) fn has_labels(&self, required_labels: &[String]) -> bool {
+        required_labels.iter().all(|label| self.labels.contains(label))
+    }
+
     /// ID setter
     pub(crate) fn set_id(&mut self, id: u32) {
         self.id = id;
@@ -43,6 +87,16 @@ impl Agent {
         self.score = score;
     }
 
+    /// Record a sign of life from the Agent, pushing back its heartbeat deadline.
+    pub(crate) fn heartbeat(&mut self) {
+        self.last_heartbeat = Instant::now();
+    }
+
+    /// Check if the Agent has been silent for longer than the given timeout, i.e. missed its heartbeat deadline.
+    pub(crate) fn is_expired(&self, timeout: Duration) -> bool {
+        self.last_heartbeat.elapsed() > timeout
+    }
+
     /// Returns the Agent's IP address in the format "host:port"
     /// If the hostname is empty, returns "unknown:unknown"
     pub(crate) fn get_ip_address(&self) -> String {
@@ -103,62 +157,98 @@ impl Hostname {
 
 /// AgentPool is a collection of Agents stored in a vector.
 /// The vector is sorted whenever necessary to maintain order.
+/// The Scheduling strategy decides which Agent of the Pool runs the next Action.
 pub struct AgentPool {
     agents: Vec<Agent>,
+    strategy: Box<dyn SchedulingStrategy>,
+}
+
+impl Default for AgentPool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AgentPool {
-    /// Constructor
+    /// Constructor, using the least-loaded Scheduling strategy
     pub fn new() -> Self {
+        Self::with_strategy(Box::new(LeastLoaded))
+    }
+
+    /// Constructor with a given Scheduling strategy
+    pub fn with_strategy(strategy: Box<dyn SchedulingStrategy>) -> Self {
         Self {
             agents: Vec::new(),
+            strategy,
         }
     }
 
+    /// Select the Agent to run the next Action according to the Scheduling strategy, or return None if no Agent can take it.
+    /// Only the Agents advertising all the labels required by the Action, and with a free slot, are considered.
+    pub(crate) fn select(&mut self, required_labels: &[String]) -> Option<&Agent> {
+        let candidates: Vec<&Agent> = self
+            .agents
+            .iter()
+            .filter(|agent| agent.has_capacity() && agent.has_labels(required_labels))
+            .collect();
+        let id = self.strategy.select(&candidates)?;
+        self.agents.iter().find(|agent| agent.id == id)
+    }
+
     /// Insert an Agent into the Agent Pool and sort the Pool by score.
     pub(crate) fn push(&mut self, item: Agent) {
         self.agents.push(item);
         self.sort();  // Keep the vector sorted after each insertion of a new Agent
     }
 
-    /// Remove and return the Agent with the lowest score (that is, the first Agent), or return None if the Pool is empty.
-    pub(crate) fn pop(&mut self) -> Option<Agent> {
-        if self.agents.is_empty() {
-            None
-        } else {
-            Some(self.agents.remove(0))
-        }
+    /// Sort the Agents by score (ascending)
+    /// Uses Rust's built-in sorting algorithm to sort the Agents by score. It is a Timsort.
+    pub(crate) fn sort(&mut self) {
+        self.agents.sort_by_key(|agent| agent.score);
     }
 
-    /// Peek at the Agent with the lowest score without removing it, or return None if the Pool is empty.
-    pub(crate) fn peek(&self) -> Option<&Agent> {
-        if self.agents.is_empty() {
-            None
-        } else {
-            self.agents.first()  // The first element has the lowest score
+    /// Reserve a slot of the Agent of the given ID for the given Action, returning false if the Agent is not found.
+    pub(crate) fn reserve(&mut self, agent_id: u32, action_id: u32) -> bool {
+        match self.find_agent_mut(agent_id) {
+            Some(agent) => {
+                agent.reserve(action_id);
+                true
+            }
+            None => false,
         }
     }
 
-    /// Return the number of Agents in the Pool
-    pub(crate) fn len(&self) -> usize {
-        self.agents.len()
+    /// Release the slot the Agent of the given ID reserved for the given Action, returning false if there was none.
+    pub(crate) fn release(&mut self, agent_id: u32, action_id: u32) -> bool {
+        match self.find_agent_mut(agent_id) {
+            Some(agent) => agent.release(action_id),
+            None => false,
+        }
     }
 
-    /// Check if the Agent Pool is empty
-    pub(crate) fn is_empty(&self) -> bool {
-        self.agents.is_empty()
+    /// Remove and return the Agent of the given ID, or return None if the Agent is not found.
+    /// The Pool stays sorted, as removing an element does not change the order of the others.
+    pub(crate) fn remove(&mut self, id: u32) -> Option<Agent> {
+        let index = self.agents.iter().position(|agent| agent.id == id)?;
+        Some(self.agents.remove(index))
     }
 
-    /// Sort the Agents by score (ascending)
-    /// Uses Rust's built-in sorting algorithm to sort the Agents by score. It is a Timsort.
-    pub(crate) fn sort(&mut self) {
-        self.agents.sort_by_key(|agent| agent.score);
+    /// Remove and return every Agent that missed its heartbeat deadline.
+    pub(crate) fn remove_expired(&mut self, timeout: Duration) -> Vec<Agent> {
+        let (expired, alive) = self.agents.drain(..).partition(|agent| agent.is_expired(timeout));
+        self.agents = alive;
+        expired
     }
 
     /// Return a *mutable* reference to the Agent of the given ID, or None if the Agent is not found.
     pub(crate) fn find_agent_mut(&mut self, id: u32) -> Option<&mut Agent> {
         self.agents.iter_mut().find(|agent| agent.id == id)
     }
+
+    /// Return the Agent running the Action of the given ID, or None if the Action is not in flight.
+    pub(crate) fn find_running(&self, action_id: u32) -> Option<&Agent> {
+        self.agents.iter().find(|agent| agent.in_flight.contains(&action_id))
+    }
     
 
     /// Check if the Agent with the given ID is out of order compared to its neighbors
@@ -180,3 +270,27 @@ impl AgentPool {
         self.agents.iter().map(|agent| agent.id).max().unwrap_or(0) + 1  // unwrap_or(0) is used to handle the case when the Agent Pool is empty
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_skips_agents_without_free_slot() {
+        let mut pool = AgentPool::new();
+        pool.push(Agent::new(1, Hostname::new("agent-1".to_string(), 9001), 90, vec![], 1));
+        pool.push(Agent::new(2, Hostname::new("agent-2".to_string(), 9001), 10, vec![], 0));
+
+        assert_eq!(pool.select(&[]).map(Agent::get_id), Some(1));
+        assert!(pool.reserve(1, 42));
+        // Agent 1 is full, Agent 2 has no limit.
+        assert_eq!(pool.select(&[]).map(Agent::get_id), Some(2));
+        assert!(pool.reserve(2, 43));
+        assert!(pool.reserve(2, 44));
+        assert_eq!(pool.select(&[]).map(Agent::get_id), Some(2));
+
+        assert!(pool.release(1, 42));
+        assert!(!pool.release(1, 42));
+        assert_eq!(pool.select(&[]).map(Agent::get_id), Some(1));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod agent_pool_logic;
-pub mod action_queue_logic;
\ No newline at end of file
+pub mod action_queue_logic;
+pub mod scheduling_strategy;
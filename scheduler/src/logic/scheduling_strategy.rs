@@ -0,0 +1,193 @@
+use crate::logic::agent_pool_logic::Agent;
+use std::cmp::Reverse;
+
+/// Names of the built-in Scheduling strategies, as given in the Scheduler configuration.
+pub const STRATEGY_NAMES: [&str; 3] = ["least-loaded", "round-robin", "bin-packing"];
+
+/// A Scheduling strategy selects the Agent of the Pool on which to run the next Action.
+/// Strategies may keep state between two selections (e.g. round-robin), hence the `&mut self`.
+pub trait SchedulingStrategy: Send + Sync {
+    /// Return the ID of the selected Agent, or None if no Agent can take the Action.
+    /// The candidate Agents are sorted by score (ascending), that is from the busiest to the freest, and all have a free slot.
+    fn select(&mut self, agents: &[&Agent]) -> Option<u32>;
+}
+
+/// Build the built-in Scheduling strategy of the given name, or return None if the name is unknown.
+pub fn strategy_from_name(name: &str) -> Option<Box<dyn SchedulingStrategy>> {
+    match name {
+        "least-loaded" => Some(Box::new(LeastLoaded)),
+        "round-robin" => Some(Box::new(RoundRobin::default())),
+        "bin-packing" => Some(Box::new(BinPacking)),
+        _ => None,
+    }
+}
+
+/// Select the Agent running the fewest Actions, then with the highest freeness score, spreading the load across the Pool.
+/// The in-flight Actions come first as the score only reflects them once the Agent reports its health again.
+#[derive(Debug, Default)]
+pub struct LeastLoaded;
+
+impl SchedulingStrategy for LeastLoaded {
+    fn select(&mut self, agents: &[&Agent]) -> Option<u32> {
+        agents
+            .iter()
+            .max_by_key(|agent| (Reverse(agent.get_in_flight().len()), agent.get_score()))
+            .map(|agent| agent.get_id())
+    }
+}
+
+/// Select the Agents one after the other, by ascending ID, regardless of their load.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    last_selected: Option<u32>,
+}
+
+impl SchedulingStrategy for RoundRobin {
+    fn select(&mut self, agents: &[&Agent]) -> Option<u32> {
+        let next = agents.iter().map(|agent| agent.get_id());
+        let selected = match self.last_selected {
+            // The next ID after the last selected one, or wrap around to the lowest ID.
+            Some(last) => next
+                .clone()
+                .filter(|id| *id > last)
+                .min()
+                .or_else(|| next.min()),
+            None => next.min(),
+        };
+        if selected.is_some() {
+            self.last_selected = selected;
+        }
+        selected
+    }
+}
+
+/// Select the busiest Agent that still has free capacity, keeping the other Agents as free as possible.
+/// The busiest Agent is the one running the most Actions, then with the lowest freeness score.
+/// An Agent whose score fell to 0 is only selected when no other Agent can take the Action.
+#[derive(Debug, Default)]
+pub struct BinPacking;
+
+impl SchedulingStrategy for BinPacking {
+    fn select(&mut self, agents: &[&Agent]) -> Option<u32> {
+        agents
+            .iter()
+            .min_by_key(|agent| (agent.get_score() == 0, Reverse(agent.get_in_flight().len()), agent.get_score()))
+            .map(|agent| agent.get_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::agent_pool_logic::Hostname;
+
+    /// A synthetic Pool, sorted by score as the Agent Pool keeps it.
+    fn synthetic_pool() -> Vec<Agent> {
+        let mut agents = vec![
+            Agent::new(1, Hostname::new("agent-1".to_string(), 9001), 40, vec![], 0),
+            Agent::new(2, Hostname::new("agent-2".to_string(), 9001), 0, vec![], 0),
+            Agent::new(3, Hostname::new("agent-3".to_string(), 9001), 90, vec![], 0),
+            Agent::new(4, Hostname::new("agent-4".to_string(), 9001), 10, vec![], 0),
+        ];
+        agents.sort();
+        agents
+    }
+
+    fn candidates(agents: &[Agent]) -> Vec<&Agent> {
+        agents.iter().collect()
+    }
+
+    #[test]
+    fn test_least_loaded_selects_freest_agent() {
+        let agents = synthetic_pool();
+        let mut strategy = LeastLoaded;
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(3));
+        assert_eq!(strategy.select(&candidates(&agents)), Some(3));
+    }
+
+    #[test]
+    fn test_bin_packing_falls_back_to_agents_without_score() {
+        let agents = vec![
+            Agent::new(1, Hostname::new("agent-1".to_string(), 9001), 0, vec![], 0),
+            Agent::new(2, Hostname::new("agent-2".to_string(), 9001), 0, vec![], 0),
+        ];
+        let mut strategy = BinPacking;
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(1));
+    }
+
+    #[test]
+    fn test_least_loaded_avoids_agents_running_actions() {
+        let mut agents = synthetic_pool();
+        let mut strategy = LeastLoaded;
+
+        // Agent 3 has not reported its health since it was given an Action, its score is stale.
+        agents.iter_mut().find(|agent| agent.get_id() == 3).unwrap().reserve(7);
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(1));
+    }
+
+    #[test]
+    fn test_round_robin_cycles_through_agents() {
+        let agents = synthetic_pool();
+        let mut strategy = RoundRobin::default();
+
+        let selections: Vec<_> = (0..5).map(|_| strategy.select(&candidates(&agents))).collect();
+
+        assert_eq!(selections, vec![Some(1), Some(2), Some(3), Some(4), Some(1)]);
+    }
+
+    #[test]
+    fn test_round_robin_skips_removed_agents() {
+        let mut agents = synthetic_pool();
+        let mut strategy = RoundRobin::default();
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(1));
+        agents.retain(|agent| agent.get_id() != 2);
+        assert_eq!(strategy.select(&candidates(&agents)), Some(3));
+    }
+
+    #[test]
+    fn test_bin_packing_selects_busiest_agent_with_capacity() {
+        let agents = synthetic_pool();
+        let mut strategy = BinPacking;
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(4));
+    }
+
+    #[test]
+    fn test_bin_packing_fills_agents_running_actions() {
+        let mut agents = synthetic_pool();
+        let mut strategy = BinPacking;
+
+        agents.iter_mut().find(|agent| agent.get_id() == 3).unwrap().reserve(7);
+
+        assert_eq!(strategy.select(&candidates(&agents)), Some(3));
+    }
+
+    #[test]
+    fn test_strategies_disagree_on_same_pool() {
+        let agents = synthetic_pool();
+
+        let choices: Vec<_> = STRATEGY_NAMES
+            .iter()
+            .map(|name| strategy_from_name(name).unwrap().select(&candidates(&agents)))
+            .collect();
+
+        assert_eq!(choices, vec![Some(3), Some(1), Some(4)]);
+    }
+
+    #[test]
+    fn test_strategies_on_empty_pool() {
+        for name in STRATEGY_NAMES {
+            let mut strategy = strategy_from_name(name).unwrap();
+            assert_eq!(strategy.select(&[]), None);
+        }
+    }
+
+    #[test]
+    fn test_unknown_strategy_name() {
+        assert!(strategy_from_name("random").is_none());
+    }
+}
@@ -1,10 +1,13 @@
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
+use clap::Parser;
 use env_logger;
 use log::info;
 use logic::agent_pool_logic::AgentPool;
-// use logic::action_queue_logic::ActionsQueue;
+use logic::scheduling_strategy::strategy_from_name;
+use logic::action_queue_logic::ActionsQueue;
 use tonic::transport::Server;
 
 mod proto;
@@ -14,24 +17,42 @@ use proto::scheduler::agent_server::AgentServer;
 use proto::scheduler::controller_server::ControllerServer;
 
 mod interfaces;
+use interfaces::dispatcher::ActionDispatcher;
 use interfaces::server::agent_interface::AgentService;
 use interfaces::server::controller_interface::ControllerService;
 
 mod logic;
 
+mod config;
+use config::Config;
+
+/// How often the Agent Pool is checked for Agents that missed their heartbeat deadline.
+const LIVENESS_CHECK_PERIOD: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
 	env_logger::init();
+	let config = Config::parse();
+
+	let addr = config.addr.parse()?;
 
-	let addr = "[::0]:50051".parse()?;
+	// The strategy name is validated by the configuration parser.
+	let strategy = strategy_from_name(&config.strategy).ok_or("Unknown scheduling strategy")?;
+	info!("Scheduling Actions with the {} strategy", config.strategy);
 
 	// Initializes the Agent Pool and Action queue. They are lost when the Scheduler dies.
-	let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
-	//let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+	let agent_pool = Arc::new(Mutex::new(AgentPool::with_strategy(strategy)));
+	let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+
+	// The Dispatcher sends queued Actions to the Agents of the Pool.
+	let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+
+	// Pass the shared Agent Pool and Dispatcher to Agent and Controller services.
+	let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+	let controller = ControllerService::new(dispatcher.clone());
 
-	// Pass the shared Agent Pool to Agent and Controller services.
-	let agent = AgentService::new(agent_pool.clone());
-	let controller = ControllerService::new(agent_pool.clone());
+	// Evict the Agents that went silent, and reschedule or fail the Actions they were running.
+	tokio::spawn(dispatcher.clone().watch_liveness(LIVENESS_CHECK_PERIOD, Duration::from_secs(config.heartbeat_timeout)));
 
 	let service = tonic_reflection::server::Builder::configure()
 		.register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
@@ -0,0 +1,80 @@
+//use scheduler::proto::controller as controller;
+use scheduler::proto::scheduler as controller;
+use controller::controller_server::ControllerServer;
+use controller::{controller_client::ControllerClient, ActionRequest, ActionStatus, CancelActionRequest, ExecutionContext, RunnerType};
+
+use scheduler::interfaces::server as server;
+use scheduler::interfaces::dispatcher::ActionDispatcher;
+use server::controller_interface::ControllerService;
+
+use scheduler::logic as logic;
+use logic::agent_pool_logic::AgentPool;
+use logic::action_queue_logic::ActionsQueue;
+
+use tonic::transport::Server;
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+use std::error::Error;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+
+#[tokio::test]
+async fn test_cancel_queued_action() -> Result<(), Box<dyn Error>> {
+    tokio::spawn(async {
+        let addr = "[::1]:50051".parse().unwrap();
+        let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let controller = ControllerService::new(dispatcher.clone());
+
+        Server::builder()
+            .add_service(ControllerServer::new(controller))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let channel = Channel::from_static("http://[::1]:50051").connect().await?;
+    let mut client = ControllerClient::new(channel);
+
+    // No Agent is registered: the Action waits in the queue.
+    let request = Request::new(ActionRequest {
+        action_id: 21,
+        context: Some(ExecutionContext {
+            r#type: RunnerType::Docker.into(),
+            container_image: Some("test_image".to_string()),
+            labels: vec![],
+            shell: None,
+            env: HashMap::new(),
+            secrets: vec![],
+        }),
+        commands: vec![String::from("sleep 3600")],
+        repo_url: String::from("sealci-repo-url"),
+        timeout: None,
+        artifacts: vec![],
+        input_artifacts: vec![],
+    });
+    let mut response_stream = client.schedule_action(request).await?.into_inner();
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Pending);
+
+    client.cancel_action(Request::new(CancelActionRequest { action_id: 21 })).await?;
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.action_id, 21);
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Cancelled);
+
+    // The Action left the queue: it is no longer known to the Scheduler.
+    let status = client
+        .cancel_action(Request::new(CancelActionRequest { action_id: 21 }))
+        .await
+        .expect_err("the Action should be unknown");
+    assert_eq!(status.code(), Code::NotFound);
+
+    Ok(())
+}
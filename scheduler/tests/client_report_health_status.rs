@@ -2,25 +2,28 @@
 use scheduler::proto::scheduler as agent;
 use agent::agent_client::AgentClient;
 use agent::agent_server::AgentServer;
-use agent::{HealthStatus, Health, Empty};
+use agent::{HealthStatus, Health, Empty, Hostname, RegisterAgentRequest};
 
 //use scheduler::proto::controller as controller;
 use scheduler::proto::scheduler as controller;
 use controller::controller_server::ControllerServer;
+use controller::{controller_client::ControllerClient, ActionRequest, ActionStatus, ExecutionContext, RunnerType};
 
 use scheduler::interfaces::server as server;
+use scheduler::interfaces::dispatcher::ActionDispatcher;
 use server::agent_interface::AgentService;
 use server::controller_interface::ControllerService;
 
 use scheduler::logic as logic;
 use logic::agent_pool_logic::AgentPool;
-// use logic::action_queue_logic::ActionsQueue;
+use logic::action_queue_logic::ActionsQueue;
 
 use tonic::transport::Server;
 use tonic::transport::Channel;
 use tonic::Request;
 use tokio_stream::iter;
 use std::error::Error;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::Duration;
@@ -30,9 +33,10 @@ async fn test_report_health_status() -> Result<(), Box<dyn Error>> {
     tokio::spawn(async {
         let addr = "[::1]:50051".parse().unwrap();
         let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
-        // let action_queue: Arc<Mutex<ActionsQueue>> = Arc::new(Mutex::new(ActionsQueue::new()));
-        let agent = AgentService::new(agent_pool.clone());
-        let controller = ControllerService::new(agent_pool.clone());
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+        let controller = ControllerService::new(dispatcher.clone());
         let service = tonic_reflection::server::Builder::configure()
             .register_encoded_file_descriptor_set(scheduler::proto::FILE_DESCRIPTOR_SET)
             .build()
@@ -75,3 +79,72 @@ async fn test_report_health_status() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_agent_evicted_when_health_stream_closes() -> Result<(), Box<dyn Error>> {
+    tokio::spawn(async {
+        let addr = "[::1]:50052".parse().unwrap();
+        let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+        let controller = ControllerService::new(dispatcher.clone());
+
+        Server::builder()
+            .add_service(AgentServer::new(agent))
+            .add_service(ControllerServer::new(controller))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let channel = Channel::from_static("http://[::1]:50052").connect().await?;
+    let mut agent_client = AgentClient::new(channel.clone());
+    let mut controller_client = ControllerClient::new(channel);
+
+    let response = agent_client
+        .register_agent(Request::new(RegisterAgentRequest {
+            health: Some(Health { cpu_avail: 4, memory_avail: 1024 }),
+            hostname: Some(Hostname { host: "http://localhost".to_string(), port: 1 }),
+            labels: vec![],
+            max_concurrency: 0,
+        }))
+        .await?;
+    let agent_id = response.get_ref().id;
+
+    // The health status stream ends: the Agent is considered dead and leaves the Pool.
+    let health_status_stream = iter(vec![HealthStatus {
+        agent_id,
+        health: Some(Health { cpu_avail: 4, memory_avail: 1024 }),
+    }]);
+    agent_client.report_health_status(Request::new(health_status_stream)).await?;
+
+    let request = Request::new(ActionRequest {
+        action_id: 7,
+        context: Some(ExecutionContext {
+            r#type: RunnerType::Docker.into(),
+            container_image: Some("test_image".to_string()),
+            labels: vec![],
+            shell: None,
+            env: HashMap::new(),
+            secrets: vec![],
+        }),
+        commands: vec![String::from("echo 'evicted'")],
+        repo_url: String::from("sealci-repo-url"),
+        timeout: None,
+        artifacts: vec![],
+        input_artifacts: vec![],
+    });
+    let mut response_stream = controller_client.schedule_action(request).await?.into_inner();
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Pending);
+
+    // With no Agent left in the Pool, the Action is never scheduled.
+    let next = tokio::time::timeout(Duration::from_millis(500), response_stream.message()).await;
+    assert!(next.is_err(), "the Action should stay queued");
+
+    Ok(())
+}
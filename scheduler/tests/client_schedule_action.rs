@@ -5,20 +5,24 @@ use agent::agent_server::AgentServer;
 //use scheduler::proto::controller as controller;
 use scheduler::proto::scheduler as controller;
 use controller::controller_server::ControllerServer;
-use controller::{controller_client::ControllerClient, ActionRequest, ExecutionContext, RunnerType};
+use controller::{controller_client::ControllerClient, ActionRequest, ActionStatus, ExecutionContext, RunnerType};
+use agent::agent_client::AgentClient;
+use agent::{Health, Hostname, RegisterAgentRequest};
 
 use scheduler::interfaces::server as server;
+use scheduler::interfaces::dispatcher::ActionDispatcher;
 use server::agent_interface::AgentService;
 use server::controller_interface::ControllerService;
 
 use scheduler::logic as logic;
 use logic::agent_pool_logic::AgentPool;
-// use logic::action_queue_logic::ActionsQueue;
+use logic::action_queue_logic::ActionsQueue;
 
 use tonic::transport::Server;
 use tonic::transport::Channel;
 use tonic::Request;
 use std::error::Error;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::Duration;
@@ -28,9 +32,10 @@ async fn test_schedule_action() -> Result<(), Box<dyn Error>> {
     tokio::spawn(async {
         let addr = "[::1]:50051".parse().unwrap();
         let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
-        // let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
-        let agent = AgentService::new(agent_pool.clone());
-        let controller = ControllerService::new(agent_pool.clone());
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+        let controller = ControllerService::new(dispatcher.clone());
         let service = tonic_reflection::server::Builder::configure()
             .register_encoded_file_descriptor_set(scheduler::proto::FILE_DESCRIPTOR_SET)
             .build()
@@ -55,16 +60,163 @@ async fn test_schedule_action() -> Result<(), Box<dyn Error>> {
         context: Some(ExecutionContext {
             r#type: RunnerType::Docker.into(),
             container_image: Some("test_image".to_string()),
+            labels: vec![],
+            shell: None,
+            env: HashMap::new(),
+            secrets: vec![],
         }),
         commands: vec![String::from("echo 'Salut les zagennntss!!!'"), String::from("shutdown now")],
         repo_url: String::from("sealci-repo-url"),
+        timeout: None,
+        artifacts: vec![],
+        input_artifacts: vec![],
     });
 
     let mut response_stream = client.schedule_action(request).await?.into_inner();
 
-    while let Some(response) = response_stream.message().await? {
-        assert_eq!(response.action_id, 69420);
-    }
+    // No Agent is registered: the Action is queued and the stream stays open.
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.action_id, 69420);
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Pending);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schedule_action_dispatched_on_agent_registration() -> Result<(), Box<dyn Error>> {
+    tokio::spawn(async {
+        let addr = "[::1]:50052".parse().unwrap();
+        let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+        let controller = ControllerService::new(dispatcher.clone());
+
+        Server::builder()
+            .add_service(AgentServer::new(agent))
+            .add_service(ControllerServer::new(controller))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let channel = Channel::from_static("http://[::1]:50052").connect().await?;
+    let mut controller_client = ControllerClient::new(channel.clone());
+    let mut agent_client = AgentClient::new(channel);
+
+    let request = Request::new(ActionRequest {
+        action_id: 42,
+        context: Some(ExecutionContext {
+            r#type: RunnerType::Docker.into(),
+            container_image: Some("test_image".to_string()),
+            labels: vec![],
+            shell: None,
+            env: HashMap::new(),
+            secrets: vec![],
+        }),
+        commands: vec![String::from("echo 'queued'")],
+        repo_url: String::from("sealci-repo-url"),
+        timeout: None,
+        artifacts: vec![],
+        input_artifacts: vec![],
+    });
+
+    let mut response_stream = controller_client.schedule_action(request).await?.into_inner();
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Pending);
+
+    // Registering an Agent dispatches the queued Action to it.
+    agent_client
+        .register_agent(Request::new(RegisterAgentRequest {
+            health: Some(Health { cpu_avail: 4, memory_avail: 1024 }),
+            hostname: Some(Hostname { host: "http://localhost".to_string(), port: 1 }),
+            labels: vec![],
+            max_concurrency: 0,
+        }))
+        .await?;
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.action_id, 42);
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Scheduled);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schedule_action_waits_for_agent_with_required_labels() -> Result<(), Box<dyn Error>> {
+    tokio::spawn(async {
+        let addr = "[::1]:50053".parse().unwrap();
+        let agent_pool = Arc::new(Mutex::new(AgentPool::new()));
+        let action_queue = Arc::new(Mutex::new(ActionsQueue::new()));
+        let dispatcher = ActionDispatcher::new(agent_pool.clone(), action_queue.clone());
+        let agent = AgentService::new(agent_pool.clone(), dispatcher.clone());
+        let controller = ControllerService::new(dispatcher.clone());
+
+        Server::builder()
+            .add_service(AgentServer::new(agent))
+            .add_service(ControllerServer::new(controller))
+            .serve(addr)
+            .await
+            .unwrap();
+    });
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let channel = Channel::from_static("http://[::1]:50053").connect().await?;
+    let mut controller_client = ControllerClient::new(channel.clone());
+    let mut agent_client = AgentClient::new(channel);
+
+    // This Agent cannot run privileged containers.
+    agent_client
+        .register_agent(Request::new(RegisterAgentRequest {
+            health: Some(Health { cpu_avail: 4, memory_avail: 1024 }),
+            hostname: Some(Hostname { host: "http://localhost".to_string(), port: 1 }),
+            labels: vec![String::from("arch:amd64")],
+            max_concurrency: 0,
+        }))
+        .await?;
+
+    let request = Request::new(ActionRequest {
+        action_id: 1337,
+        context: Some(ExecutionContext {
+            r#type: RunnerType::Docker.into(),
+            container_image: Some("test_image".to_string()),
+            labels: vec![String::from("arch:amd64"), String::from("privileged")],
+            shell: None,
+            env: HashMap::new(),
+            secrets: vec![],
+        }),
+        commands: vec![String::from("echo 'privileged'")],
+        repo_url: String::from("sealci-repo-url"),
+        timeout: None,
+        artifacts: vec![],
+        input_artifacts: vec![],
+    });
+
+    let mut response_stream = controller_client.schedule_action(request).await?.into_inner();
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Pending);
+
+    // No registered Agent matches the required labels: the Action stays queued.
+    let next = tokio::time::timeout(Duration::from_millis(500), response_stream.message()).await;
+    assert!(next.is_err(), "the Action should stay queued");
+
+    agent_client
+        .register_agent(Request::new(RegisterAgentRequest {
+            health: Some(Health { cpu_avail: 4, memory_avail: 1024 }),
+            hostname: Some(Hostname { host: "http://localhost".to_string(), port: 2 }),
+            labels: vec![String::from("arch:amd64"), String::from("privileged"), String::from("gpu")],
+            max_concurrency: 0,
+        }))
+        .await?;
+
+    let response = response_stream.message().await?.expect("stream should stay open");
+    assert_eq!(response.result.unwrap().completion(), ActionStatus::Scheduled);
+    assert!(response.log.ends_with("localhost:2"));
 
     Ok(())
 }
@@ -64,7 +64,7 @@ export default function PipelinePage() {
                     fontSize: "20px",
                     fontWeight: "500",
                   }}
-                  text={action.logs ? action.logs.join("\n") : ""}
+                  text={action.logs ? action.logs.map((log) => log.message).join("\n") : ""}
                   language={"bash"}
                   showLineNumbers={true}
                   theme={a11yDark}
@@ -1,4 +1,4 @@
-import { Pipeline } from "@/types";
+import { Pipeline, PipelinePage } from "@/types";
 import { useQuery } from "@tanstack/react-query";
 import ky from "ky";
 
@@ -6,13 +6,13 @@ const fetchPipelines = async ({
   verbose,
 }: {
   verbose?: boolean;
-} = {}): Promise<Pipeline[]> => {
+} = {}): Promise<PipelinePage> => {
   const endpoint = verbose
     ? "/pipeline?verbose=true"
     : "/pipeline?verbose=false";
   const json = await ky
     .get(import.meta.env.VITE_CONTROLLER_ENDPOINT + endpoint)
-    .json<Pipeline[]>();
+    .json<PipelinePage>();
 
   return json;
 };
@@ -5,15 +5,29 @@ export interface Pipeline {
   actions: Action[];
 }
 
+export interface PipelinePage {
+  pipelines: Pipeline[];
+  next_cursor?: number;
+}
+
 export interface Action {
   id: number;
   pipeline_id: number;
   name: string;
   container_uri: string;
-  commands: string[];
+  commands?: string[];
   type: string;
   status: string;
-  logs?: string[];
+  logs?: Log[];
+}
+
+export interface Log {
+  id: number;
+  sequence?: number;
+  timestamp: string;
+  step?: number;
+  stream: "system" | "stdout" | "stderr";
+  message: string;
 }
 
 export type PipelineStatus =